@@ -0,0 +1,138 @@
+//! Declarations from `epan/expert.h`.
+//!
+//! The pre-generated `bindings.rs` predates `epan/expert.h` being part of `wrapper.h`, so these
+//! mirror the upstream declarations by hand. They are glob re-exported from the crate root, which
+//! means that bindings regenerated with `--features bindgen` take precedence over them.
+//!
+//! `expert_field` is left out on purpose, since `bindings.rs` already declares it as an opaque
+//! type. In C it is `{ int ei; int hf; }`.
+
+use crate::{gint, hf_register_info, packet_info, proto_item, proto_tree, tvbuff_t};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct expert_field_info {
+    pub name: *const ::std::os::raw::c_char,
+    pub group: ::std::os::raw::c_int,
+    pub severity: ::std::os::raw::c_int,
+    pub summary: *const ::std::os::raw::c_char,
+    pub id: ::std::os::raw::c_int,
+    pub protocol: *const ::std::os::raw::c_char,
+    pub orig_severity: ::std::os::raw::c_int,
+    pub hf_info: hf_register_info,
+}
+#[test]
+fn bindgen_test_layout_expert_field_info() {
+    const UNINIT: ::std::mem::MaybeUninit<expert_field_info> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<expert_field_info>(),
+        128usize,
+        concat!("Size of: ", stringify!(expert_field_info))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<expert_field_info>(),
+        8usize,
+        concat!("Alignment of ", stringify!(expert_field_info))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).summary) as usize - ptr as usize },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(expert_field_info),
+            "::",
+            stringify!(summary)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).hf_info) as usize - ptr as usize },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(expert_field_info),
+            "::",
+            stringify!(hf_info)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ei_register_info {
+    pub ids: *mut crate::expert_field,
+    pub eiinfo: expert_field_info,
+}
+#[test]
+fn bindgen_test_layout_ei_register_info() {
+    const UNINIT: ::std::mem::MaybeUninit<ei_register_info> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<ei_register_info>(),
+        136usize,
+        concat!("Size of: ", stringify!(ei_register_info))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).eiinfo) as usize - ptr as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(ei_register_info),
+            "::",
+            stringify!(eiinfo)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct expert_module {
+    _unused: [u8; 0],
+}
+pub type expert_module_t = expert_module;
+extern "C" {
+    pub fn expert_register_protocol(id: ::std::os::raw::c_int) -> *mut expert_module_t;
+}
+extern "C" {
+    pub fn expert_register_field_array(
+        module: *mut expert_module_t,
+        ei: *mut ei_register_info,
+        num_records: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn expert_add_info(
+        pinfo: *mut packet_info,
+        pi: *mut proto_item,
+        eiindex: *mut crate::expert_field,
+    );
+}
+extern "C" {
+    pub fn expert_add_info_format(
+        pinfo: *mut packet_info,
+        pi: *mut proto_item,
+        eiindex: *mut crate::expert_field,
+        format: *const ::std::os::raw::c_char,
+        ...
+    );
+}
+extern "C" {
+    pub fn proto_tree_add_expert(
+        tree: *mut proto_tree,
+        pinfo: *mut packet_info,
+        eiindex: *mut crate::expert_field,
+        tvb: *mut tvbuff_t,
+        start: gint,
+        length: gint,
+    ) -> *mut proto_item;
+}
+extern "C" {
+    pub fn proto_tree_add_expert_format(
+        tree: *mut proto_tree,
+        pinfo: *mut packet_info,
+        eiindex: *mut crate::expert_field,
+        tvb: *mut tvbuff_t,
+        start: gint,
+        length: gint,
+        format: *const ::std::os::raw::c_char,
+        ...
+    ) -> *mut proto_item;
+}
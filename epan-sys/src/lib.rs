@@ -8,3 +8,6 @@
 #![allow(clippy::all)]
 
 include!(concat!(env!("CARGO_MANIFEST_DIR"), "/bindings.rs"));
+
+mod expert;
+pub use expert::*;
@@ -1,5 +1,5 @@
 use quote::{format_ident, quote};
-use syn::{parse_quote, punctuated::Punctuated};
+use syn::{ext::IdentExt, parse_quote, punctuated::Punctuated};
 
 use crate::util::*;
 
@@ -28,9 +28,13 @@ where
 pub(crate) struct ProtocolOptions {
     /// The dissector table(s) to register the protocol to.
     pub(crate) decode_from: Vec<DecodeFrom>,
+    /// The heuristic dissector table(s) to register the protocol to.
+    pub(crate) heuristic: Vec<Heuristic>,
     pub(crate) proto_desc: Option<String>,
     pub(crate) proto_name: Option<String>,
     pub(crate) proto_filter: Option<String>,
+    /// Path to a type implementing `wsdf::Preferences`, which is registered with the protocol.
+    pub(crate) prefs: Option<syn::Path>,
 }
 
 /// Options for anything which can derive ProtocolField.
@@ -38,6 +42,19 @@ pub(crate) struct ProtocolOptions {
 pub(crate) struct ProtocolFieldOptions {
     pub(crate) pre_dissect: Vec<syn::Path>,
     pub(crate) post_dissect: Vec<syn::Path>,
+    /// For enums only. The integer type of a tag which is read from the packet to pick the
+    /// variant, e.g. "u8".
+    pub(crate) tag: Option<syn::Ident>,
+    /// Format string for the Info column, e.g. "Seq={seq}".
+    pub(crate) info: Option<String>,
+    /// The default byte order of the fields, including those of nested types.
+    pub(crate) byte_order: Option<ByteOrder>,
+    /// Pads the end of the type to a multiple of this many bytes from the start of the packet.
+    pub(crate) align: Option<usize>,
+    /// Whether padding is shown in the tree.
+    pub(crate) show_padding: Option<bool>,
+    /// For structs only. Whether the fields are packed bit by bit, rather than byte by byte.
+    pub(crate) bits: Option<bool>,
 }
 
 /// Options for a field. A field may be a named field or a unit tuple element, in a struct or an
@@ -48,6 +65,45 @@ pub(crate) struct FieldOptions {
     /// An identifier for an integer field which is used to determine the size of this field. Only
     /// used by vector types to denote their number of elements.
     pub(crate) size_hint: Option<syn::Ident>,
+    /// An identifier for an integer field which gives the size of this field in bytes. Only used
+    /// by vector types, whose elements are dissected until exactly that many bytes are consumed.
+    pub(crate) bytes_len: Option<syn::Ident>,
+    /// An arithmetic expression over prior integer fields, which is used like `size_hint`.
+    pub(crate) len_expr: Option<syn::Expr>,
+    /// For vector types only. Whether elements are dissected until the end of the packet.
+    pub(crate) until_end: Option<bool>,
+    /// For vector types only. Path to a function which is given the remaining bytes before each
+    /// element, and returns true to end the list.
+    pub(crate) until: Option<syn::Path>,
+    /// For vector types only. A byte which ends the list.
+    pub(crate) terminator: Option<u8>,
+    /// For `Option` fields only. An expression over prior fields which is true if the field is
+    /// present.
+    pub(crate) if_expr: Option<syn::Expr>,
+    /// For `Option` fields only. Path to a function, taking the same parameters as a tap, which
+    /// returns true if the field is present.
+    pub(crate) present_if: Option<syn::Path>,
+    /// Whether the field is read without moving past it.
+    pub(crate) is_virtual: Option<bool>,
+    /// Whether the field is marked as generated in the UI, i.e. shown in square brackets.
+    pub(crate) generated: Option<bool>,
+    /// Skips ahead to a multiple of this many bytes from the start of the packet before the field.
+    pub(crate) align: Option<usize>,
+    /// A number of bytes to skip before the field.
+    pub(crate) skip: Option<usize>,
+    /// Like `skip`, but an arithmetic expression over prior integer fields.
+    pub(crate) skip_expr: Option<syn::Expr>,
+    /// Whether the bytes skipped by `align`, `skip` or `skip_expr` are shown in the tree.
+    pub(crate) show_padding: Option<bool>,
+    /// For fields of a bits struct only. The number of bits the field takes up.
+    pub(crate) bits: Option<u32>,
+    /// The name of a built-in checksum which the field holds, e.g. "crc32c".
+    pub(crate) checksum: Option<String>,
+    /// Path to a function, taking the same parameters as a tap, which computes the checksum which
+    /// the field holds.
+    pub(crate) checksum_fn: Option<syn::Path>,
+    /// For checksum fields only. The first and last fields covered by the checksum.
+    pub(crate) over: Option<(syn::Ident, syn::Ident)>,
     /// Wireshark type, e.g. "FT_UINT8".
     pub(crate) ws_type: Option<String>,
     /// Wireshark encoding option, e.g. "ENC_LITTLE_ENDIAN".
@@ -70,6 +126,61 @@ pub(crate) struct FieldOptions {
     /// Custom name for the field.
     pub(crate) rename: Option<String>,
     pub(crate) save: Option<bool>,
+    /// Path to a type implementing `wsdf::ValueStrings`, whose names are used to display the
+    /// field's values.
+    pub(crate) value_strings: Option<syn::Path>,
+    /// For bool fields only. The unsigned integer type holding the boolean, e.g. "u16".
+    pub(crate) width: Option<syn::Ident>,
+    /// For bool fields only. The bits which must be set for the field to be true.
+    pub(crate) mask: Option<u64>,
+    /// For bool fields only. Labels to display for true and false, respectively.
+    pub(crate) tfs: Option<(String, String)>,
+    /// For string fields only. A fixed size in bytes, which may be padded with NULs or spaces.
+    pub(crate) size: Option<usize>,
+    /// For string fields only. Whether the string ends with a NUL byte.
+    pub(crate) nul_terminated: Option<bool>,
+    /// For string fields only. The unsigned integer type of a length which precedes the string.
+    pub(crate) len_prefix: Option<syn::Ident>,
+    /// For string fields only. Whether to trim white space from both ends of the string.
+    pub(crate) trim: Option<bool>,
+    /// Shorthand for an encoding of ENC_LITTLE_ENDIAN or ENC_BIG_ENDIAN. For fields of nested
+    /// types, this is the default byte order of the nested type's fields.
+    pub(crate) byte_order: Option<ByteOrder>,
+    /// For byte arrays only. Whether the bytes are shown as an ASCII string.
+    pub(crate) ascii: Option<bool>,
+    /// A previous field which is passed to `enc_fn` to pick the encoding at dissection time.
+    pub(crate) enc_field: Option<syn::Ident>,
+    /// Path to a function which returns the encoding of this field.
+    pub(crate) enc_fn: Option<syn::Path>,
+}
+
+/// Options for a type which derives Flags.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlagsOptions {
+    /// The unsigned integer type of the whole bitmask. Required if every flag is a bool.
+    pub(crate) width: Option<syn::Ident>,
+    /// Wireshark encoding option for the whole bitmask, e.g. "ENC_LITTLE_ENDIAN".
+    pub(crate) ws_enc: Option<String>,
+    /// Shorthand for an encoding of ENC_LITTLE_ENDIAN or ENC_BIG_ENDIAN.
+    pub(crate) byte_order: Option<ByteOrder>,
+    /// Wireshark display hint for the whole bitmask, e.g. "BASE_HEX".
+    pub(crate) ws_display: Option<FieldDisplayPair>,
+}
+
+/// Options for a single flag, i.e. a field within a type which derives Flags.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlagOptions {
+    /// The bits of the bitmask which belong to this flag.
+    pub(crate) mask: Option<u64>,
+    /// Custom name for the flag.
+    pub(crate) rename: Option<String>,
+    /// Wireshark display hint, e.g. "BASE_HEX".
+    pub(crate) ws_display: Option<FieldDisplayPair>,
+    pub(crate) save: Option<bool>,
+    /// Path to a type implementing `wsdf::ValueStrings`.
+    pub(crate) value_strings: Option<syn::Path>,
+    /// For bool flags only. Labels to display for true and false, respectively.
+    pub(crate) tfs: Option<(String, String)>,
 }
 
 /// Options for an enum variant.
@@ -77,6 +188,64 @@ pub(crate) struct FieldOptions {
 pub(crate) struct VariantOptions {
     /// Custom name for the variant.
     pub(crate) rename: Option<String>,
+    /// For tagged enums only. The tag value which picks this variant.
+    pub(crate) tag_value: Option<u64>,
+    /// For tagged enums only. Whether this variant is picked when the tag matches no other one.
+    pub(crate) fallback: Option<bool>,
+    /// Format string for the Info column, e.g. "Seq={seq}".
+    pub(crate) info: Option<String>,
+}
+
+/// Options for a variant of an enum which derives ValueStrings.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ValueStringOptions {
+    /// Custom name for the value(s).
+    pub(crate) rename: Option<String>,
+    /// An inclusive range of values which map to this variant, instead of its discriminant.
+    pub(crate) range: Option<(u64, u64)>,
+}
+
+/// Options for a field of a struct which derives Preferences.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PreferenceOptions {
+    /// Custom title for the preference.
+    pub(crate) rename: Option<String>,
+    /// The default value, as a literal. For enum preferences, a string holding the variant.
+    pub(crate) default: Option<syn::Expr>,
+    /// For range preferences only. The dissector table whose values the protocol is bound to.
+    pub(crate) decode_from: Option<String>,
+    /// For range preferences only. The largest value which may be entered.
+    pub(crate) max: Option<u32>,
+}
+
+/// The byte order of a field, given by the `le` and `be` shorthands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub(crate) fn ws_enc(&self) -> &'static str {
+        match self {
+            ByteOrder::Little => "ENC_LITTLE_ENDIAN",
+            ByteOrder::Big => "ENC_BIG_ENDIAN",
+        }
+    }
+
+    /// Parses an `le` or `be` meta item, or returns None if it is something else. Giving both is
+    /// an error.
+    fn extract(current: Option<ByteOrder>, meta: &syn::Meta) -> syn::Result<Option<ByteOrder>> {
+        let order = match meta.path().get_ident() {
+            Some(ident) if ident == META_LE => ByteOrder::Little,
+            Some(ident) if ident == META_BE => ByteOrder::Big,
+            _ => return Ok(None),
+        };
+        match current {
+            Some(current) if current != order => make_err(meta, "expected only one of le and be"),
+            _ => Ok(Some(order)),
+        }
+    }
 }
 
 /// Some way of consuming and decoding bytes, when we don't know its size beforehand.
@@ -122,6 +291,14 @@ pub(crate) enum DecodeFrom {
     Uint(String, Vec<u32>),
 }
 
+/// A heuristic dissector table to register with, e.g. "udp", along with a path to a function which
+/// checks whether a packet belongs to the protocol.
+#[derive(Debug, Clone)]
+pub(crate) struct Heuristic {
+    pub(crate) table: String,
+    pub(crate) check: syn::Path,
+}
+
 impl DecodeFrom {
     pub(crate) fn to_tokens(&self) -> proc_macro2::TokenStream {
         match self {
@@ -152,6 +329,7 @@ impl OptionBuilder for ProtocolOptions {
                 None => return make_err(meta, "expected identifier"),
                 Some(ident) => match ident.to_string().as_str() {
                     META_DECODE_FROM => self.extract_decode_from(nv, meta)?,
+                    META_HEURISTIC => self.extract_heuristic(nv)?,
                     META_PROTO_DESC => {
                         let proto_desc = get_lit_str(&nv.value)?.value();
                         self.proto_desc = Some(proto_desc);
@@ -164,6 +342,10 @@ impl OptionBuilder for ProtocolOptions {
                         let proto_filter = get_lit_str(&nv.value)?.value();
                         self.proto_filter = Some(proto_filter);
                     }
+                    META_PREFS => {
+                        let prefs = get_lit_str(&nv.value)?.parse::<syn::Path>()?;
+                        self.prefs = Some(prefs);
+                    }
                     // These meta items belong to ProtocolFieldOptions. But they may appear in
                     // the same list of attributes, e.g.
                     //
@@ -175,10 +357,19 @@ impl OptionBuilder for ProtocolOptions {
                     //
                     // Because we have Protocol : ProtocolField and they all share the same
                     // #[wsdf(...)] look.
-                    META_PRE_DISSECT | META_POST_DISSECT => (),
+                    META_PRE_DISSECT | META_POST_DISSECT | META_TAG | META_INFO | META_ALIGN
+                    | META_SHOW_PADDING | META_BITS => (),
                     _ => return make_err(meta, "unrecognized attribute"),
                 },
             },
+            syn::Meta::Path(path) => match path.get_ident() {
+                Some(ident)
+                    if ident == META_LE
+                        || ident == META_BE
+                        || ident == META_SHOW_PADDING
+                        || ident == META_BITS => {}
+                _ => return make_err(meta, "unrecognized attribute"),
+            },
             _ => return make_err(meta, "unexpected meta item"),
         };
         Ok(())
@@ -193,12 +384,35 @@ impl OptionBuilder for ProtocolFieldOptions {
                 Some(ident) => match ident.to_string().as_str() {
                     META_PRE_DISSECT => self.pre_dissect = parse_strings(&nv.value)?,
                     META_POST_DISSECT => self.post_dissect = parse_strings(&nv.value)?,
+                    META_TAG => self.tag = Some(parse_type_ident(&nv.value)?),
+                    META_INFO => {
+                        let info = get_lit_str(&nv.value)?.value();
+                        self.info = Some(info);
+                    }
+                    META_ALIGN => self.align = Some(parse_align(&nv.value)?),
+                    META_SHOW_PADDING => {
+                        let show_padding = get_lit_bool(&nv.value)?.value;
+                        self.show_padding = Some(show_padding);
+                    }
+                    META_BITS => {
+                        let bits = get_lit_bool(&nv.value)?.value;
+                        self.bits = Some(bits);
+                    }
                     // These meta items belong to ProtocolOptions. But they may appear in the same
                     // list of attributes.
-                    META_PROTO_DESC | META_PROTO_NAME | META_PROTO_FILTER | META_DECODE_FROM => (),
+                    META_PROTO_DESC | META_PROTO_NAME | META_PROTO_FILTER | META_DECODE_FROM
+                    | META_HEURISTIC | META_PREFS => (),
                     _ => return make_err(meta, "unrecognized attribute"),
                 },
             },
+            syn::Meta::Path(path) if path.is_ident(META_SHOW_PADDING) => {
+                self.show_padding = Some(true)
+            }
+            syn::Meta::Path(path) if path.is_ident(META_BITS) => self.bits = Some(true),
+            syn::Meta::Path(_) => match ByteOrder::extract(self.byte_order, meta)? {
+                Some(order) => self.byte_order = Some(order),
+                None => return make_err(meta, "unrecognized attribute"),
+            },
             _ => return make_err(meta, "unexpected meta item"),
         }
         Ok(())
@@ -206,6 +420,25 @@ impl OptionBuilder for ProtocolFieldOptions {
 }
 
 impl ProtocolOptions {
+    /// Parses either a single `("table", "check_fn")` pair, or a list of them.
+    fn extract_heuristic(&mut self, nv: &syn::MetaNameValue) -> syn::Result<()> {
+        let items = match &nv.value {
+            syn::Expr::Array(xs) => xs.elems.iter().collect(),
+            value => vec![value],
+        };
+        for item in items {
+            match unpack_expr(item).as_slice() {
+                [table, check] => {
+                    let table = get_lit_str(table)?.value();
+                    let check = get_lit_str(check)?.parse::<syn::Path>()?;
+                    self.heuristic.push(Heuristic { table, check });
+                }
+                _ => return make_err(item, "expected heuristic to be (\"table\", \"check_fn\")"),
+            }
+        }
+        Ok(())
+    }
+
     fn extract_decode_from(
         &mut self,
         nv: &syn::MetaNameValue,
@@ -242,6 +475,16 @@ impl OptionBuilder for FieldOptions {
                 Some(ident) => match ident.to_string().as_str() {
                     META_HIDE => self.hidden = Some(true),
                     META_SAVE => self.save = Some(true),
+                    META_NUL_TERMINATED => self.nul_terminated = Some(true),
+                    META_TRIM => self.trim = Some(true),
+                    META_UNTIL_END => self.until_end = Some(true),
+                    META_VIRTUAL => self.is_virtual = Some(true),
+                    META_GENERATED => self.generated = Some(true),
+                    META_SHOW_PADDING => self.show_padding = Some(true),
+                    META_ASCII => self.ascii = Some(true),
+                    META_LE | META_BE => {
+                        self.byte_order = ByteOrder::extract(self.byte_order, meta)?
+                    }
                     _ => return make_err(meta, "unrecognized attribute"),
                 },
             },
@@ -260,6 +503,14 @@ impl OptionBuilder for FieldOptions {
                         let len = get_lit_str(&nv.value)?.value();
                         self.size_hint = Some(format_ident!("{}", len));
                     }
+                    META_LEN_EXPR => {
+                        let len_expr = get_lit_str(&nv.value)?.value();
+                        self.len_expr = Some(syn::parse_str::<syn::Expr>(&len_expr)?);
+                    }
+                    META_BYTES_LEN => {
+                        let bytes_len = get_lit_str(&nv.value)?.value();
+                        self.bytes_len = Some(format_ident!("{}", bytes_len));
+                    }
                     META_WS_TYPE => {
                         let ws_type = get_lit_str(&nv.value)?.value();
                         self.ws_type = Some(ws_type);
@@ -268,7 +519,7 @@ impl OptionBuilder for FieldOptions {
                         let ws_enc = get_lit_str(&nv.value)?.value();
                         self.ws_enc = Some(ws_enc);
                     }
-                    META_WS_DISPLAY => self.extract_ws_display(nv)?,
+                    META_WS_DISPLAY => self.ws_display = Some(parse_ws_display(&nv.value)?),
                     META_DISPATCH => {
                         let dispatch = get_lit_str(&nv.value)?.value();
                         self.dispatch = Some(format_ident!("{}", dispatch));
@@ -288,6 +539,99 @@ impl OptionBuilder for FieldOptions {
                         let rename = get_lit_str(&nv.value)?.value();
                         self.rename = Some(rename);
                     }
+                    META_VALUE_STRINGS => {
+                        let value_strings = get_lit_str(&nv.value)?.value();
+                        self.value_strings = Some(syn::parse_str::<syn::Path>(&value_strings)?);
+                    }
+                    META_WIDTH => self.width = Some(parse_type_ident(&nv.value)?),
+                    META_MASK => {
+                        let mask = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.mask = Some(mask);
+                    }
+                    META_TFS => self.tfs = Some(parse_tfs(&nv.value)?),
+                    META_SIZE => {
+                        let size = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.size = Some(size);
+                    }
+                    META_NUL_TERMINATED => {
+                        let nul_terminated = get_lit_bool(&nv.value)?.value;
+                        self.nul_terminated = Some(nul_terminated);
+                    }
+                    META_LEN_PREFIX => self.len_prefix = Some(parse_type_ident(&nv.value)?),
+                    META_TRIM => {
+                        let trim = get_lit_bool(&nv.value)?.value;
+                        self.trim = Some(trim);
+                    }
+                    META_UNTIL_END => {
+                        let until_end = get_lit_bool(&nv.value)?.value;
+                        self.until_end = Some(until_end);
+                    }
+                    META_UNTIL => {
+                        let until = get_lit_str(&nv.value)?.value();
+                        self.until = Some(syn::parse_str::<syn::Path>(&until)?);
+                    }
+                    META_TERMINATOR => {
+                        let terminator = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.terminator = Some(terminator);
+                    }
+                    META_VIRTUAL => {
+                        let is_virtual = get_lit_bool(&nv.value)?.value;
+                        self.is_virtual = Some(is_virtual);
+                    }
+                    META_GENERATED => {
+                        let generated = get_lit_bool(&nv.value)?.value;
+                        self.generated = Some(generated);
+                    }
+                    META_ALIGN => self.align = Some(parse_align(&nv.value)?),
+                    META_SKIP => {
+                        let skip = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.skip = Some(skip);
+                    }
+                    META_SKIP_EXPR => {
+                        let skip_expr = get_lit_str(&nv.value)?.value();
+                        self.skip_expr = Some(syn::parse_str::<syn::Expr>(&skip_expr)?);
+                    }
+                    META_SHOW_PADDING => {
+                        let show_padding = get_lit_bool(&nv.value)?.value;
+                        self.show_padding = Some(show_padding);
+                    }
+                    META_BITS => {
+                        let lit = get_lit_int(&nv.value)?;
+                        match lit.base10_parse()? {
+                            0 => return make_err(lit, "bits must be greater than zero"),
+                            bits => self.bits = Some(bits),
+                        }
+                    }
+                    META_IF => {
+                        let if_expr = get_lit_str(&nv.value)?.value();
+                        self.if_expr = Some(syn::parse_str::<syn::Expr>(&if_expr)?);
+                    }
+                    META_PRESENT_IF => {
+                        let present_if = get_lit_str(&nv.value)?.value();
+                        self.present_if = Some(syn::parse_str::<syn::Path>(&present_if)?);
+                    }
+                    META_CHECKSUM => {
+                        let lit = get_lit_str(&nv.value)?;
+                        if !CHECKSUMS.contains(&lit.value().as_str()) {
+                            let msg =
+                                format!("expected checksum to be one of {}", CHECKSUMS.join(", "));
+                            return make_err(lit, &msg);
+                        }
+                        self.checksum = Some(lit.value());
+                    }
+                    META_CHECKSUM_FN => {
+                        let checksum_fn = get_lit_str(&nv.value)?.value();
+                        self.checksum_fn = Some(syn::parse_str::<syn::Path>(&checksum_fn)?);
+                    }
+                    META_OVER => self.over = Some(parse_over(get_lit_str(&nv.value)?)?),
+                    META_ENC_FIELD => {
+                        let enc_field = get_lit_str(&nv.value)?.value();
+                        self.enc_field = Some(format_ident!("{}", enc_field));
+                    }
+                    META_ENC_FN => {
+                        let enc_fn = get_lit_str(&nv.value)?.value();
+                        self.enc_fn = Some(syn::parse_str::<syn::Path>(&enc_fn)?);
+                    }
                     _ => return make_err(meta, "unrecognized attribute"),
                 },
             },
@@ -297,34 +641,93 @@ impl OptionBuilder for FieldOptions {
     }
 }
 
-impl FieldOptions {
-    fn extract_ws_display(&mut self, nv: &syn::MetaNameValue) -> Result<(), syn::Error> {
-        // Wireshark display is either a single string, or a "bitwise-OR" or two strings.
-        if let syn::Expr::Binary(syn::ExprBinary {
-            op: syn::BinOp::BitOr(..),
-            left,
-            right,
+/// Parses the value of an `over` meta item, which is either a single field or a range of fields
+/// like `"header..payload"`. Both ends of the range are covered.
+fn parse_over(lit: &syn::LitStr) -> syn::Result<(syn::Ident, syn::Ident)> {
+    let err_msg = "expected over to be a field or a range of fields, e.g. \"header..payload\"";
+    let get_ident = |expr: &Option<Box<syn::Expr>>| match expr.as_deref() {
+        Some(syn::Expr::Path(path)) => path.path.get_ident().cloned(),
+        _ => None,
+    };
+    let over = match lit.parse::<syn::Expr>() {
+        Ok(syn::Expr::Path(path)) => path
+            .path
+            .get_ident()
+            .map(|ident| (ident.clone(), ident.clone())),
+        Ok(syn::Expr::Range(syn::ExprRange {
+            start,
+            limits: syn::RangeLimits::HalfOpen(_),
+            end,
             ..
-        }) = &nv.value
-        {
-            let display = get_lit_str(left)?.value();
-            let ext = get_lit_str(right)?.value();
-
-            let display = FieldDisplay::new(&display);
-            let ext = FieldDisplay::new(&ext);
-
-            self.ws_display = Some(FieldDisplayPair {
-                display,
-                ext: Some(ext),
-            });
-        } else {
-            let display = get_lit_str(&nv.value)?.value();
-            let display = FieldDisplay::new(&display);
-            self.ws_display = Some(FieldDisplayPair { display, ext: None });
-        }
-        Ok(())
+        })) => get_ident(&start).zip(get_ident(&end)),
+        _ => None,
+    };
+    over.ok_or_else(|| syn::Error::new(lit.span(), err_msg))
+}
+
+/// Parses the value of an `align` meta item, which must be a positive integer.
+fn parse_align(value: &syn::Expr) -> syn::Result<usize> {
+    let lit = get_lit_int(value)?;
+    match lit.base10_parse()? {
+        0 => make_err(lit, "align must be greater than zero"),
+        n => Ok(n),
     }
+}
 
+/// Parses the value of a meta item naming an integer type. Both `u8` and `"u8"` are accepted.
+fn parse_type_ident(value: &syn::Expr) -> syn::Result<syn::Ident> {
+    let ident = match value {
+        syn::Expr::Path(path) => path.path.get_ident().cloned(),
+        value => Some(format_ident!("{}", get_lit_str(value)?.value())),
+    };
+    match ident {
+        None => make_err(value, "expected an integer type"),
+        Some(ident) => Ok(ident),
+    }
+}
+
+/// Parses the value of a `tfs` meta item, i.e. a pair of labels for true and false.
+fn parse_tfs(value: &syn::Expr) -> syn::Result<(String, String)> {
+    match unpack_expr(value).as_slice() {
+        [true_string, false_string] => Ok((
+            get_lit_str(true_string)?.value(),
+            get_lit_str(false_string)?.value(),
+        )),
+        _ => make_err(
+            value,
+            "expected tfs to be (\"true label\", \"false label\")",
+        ),
+    }
+}
+
+/// Parses the value of a `display` meta item.
+fn parse_ws_display(value: &syn::Expr) -> syn::Result<FieldDisplayPair> {
+    // Wireshark display is either a single string, or a "bitwise-OR" or two strings.
+    if let syn::Expr::Binary(syn::ExprBinary {
+        op: syn::BinOp::BitOr(..),
+        left,
+        right,
+        ..
+    }) = value
+    {
+        let display = get_lit_str(left)?.value();
+        let ext = get_lit_str(right)?.value();
+
+        let display = FieldDisplay::new(&display);
+        let ext = FieldDisplay::new(&ext);
+
+        Ok(FieldDisplayPair {
+            display,
+            ext: Some(ext),
+        })
+    } else {
+        let display = get_lit_str(value)?.value();
+        let display = FieldDisplay::new(&display);
+        Ok(FieldDisplayPair { display, ext: None })
+    }
+}
+
+impl FieldOptions {
     fn extract_subdissector(
         &mut self,
         nv: &syn::MetaNameValue,
@@ -358,7 +761,110 @@ impl FieldOptions {
     }
 }
 
+impl OptionBuilder for FlagsOptions {
+    fn add_option(&mut self, meta: &syn::Meta) -> syn::Result<()> {
+        match meta {
+            syn::Meta::NameValue(nv) => match nv.path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_WS_ENC => {
+                        let ws_enc = get_lit_str(&nv.value)?.value();
+                        self.ws_enc = Some(ws_enc);
+                    }
+                    META_WS_DISPLAY => self.ws_display = Some(parse_ws_display(&nv.value)?),
+                    META_WIDTH => self.width = Some(parse_type_ident(&nv.value)?),
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            syn::Meta::Path(_) => match ByteOrder::extract(self.byte_order, meta)? {
+                Some(order) => self.byte_order = Some(order),
+                None => return make_err(meta, "unrecognized attribute"),
+            },
+            _ => return make_err(meta, "unexpected meta item"),
+        };
+        Ok(())
+    }
+}
+
+impl OptionBuilder for FlagOptions {
+    fn add_option(&mut self, meta: &syn::Meta) -> syn::Result<()> {
+        match meta {
+            syn::Meta::Path(path) => match path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_SAVE => self.save = Some(true),
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            syn::Meta::NameValue(nv) => match nv.path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_MASK => {
+                        let mask = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.mask = Some(mask);
+                    }
+                    META_RENAME => {
+                        let rename = get_lit_str(&nv.value)?.value();
+                        self.rename = Some(rename);
+                    }
+                    META_WS_DISPLAY => self.ws_display = Some(parse_ws_display(&nv.value)?),
+                    META_SAVE => {
+                        let save = get_lit_bool(&nv.value)?.value;
+                        self.save = Some(save);
+                    }
+                    META_VALUE_STRINGS => {
+                        let value_strings = get_lit_str(&nv.value)?.value();
+                        self.value_strings = Some(syn::parse_str::<syn::Path>(&value_strings)?);
+                    }
+                    META_TFS => self.tfs = Some(parse_tfs(&nv.value)?),
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            syn::Meta::List(_) => return make_err(meta, "unexpected meta item"),
+        };
+        Ok(())
+    }
+}
+
 impl OptionBuilder for VariantOptions {
+    fn add_option(&mut self, meta: &syn::Meta) -> syn::Result<()> {
+        match meta {
+            syn::Meta::Path(path) => match path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_FALLBACK => self.fallback = Some(true),
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            syn::Meta::NameValue(nv) => match nv.path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_RENAME => {
+                        let rename = get_lit_str(&nv.value)?.value();
+                        self.rename = Some(rename);
+                    }
+                    META_TAG_VALUE => {
+                        let tag_value = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.tag_value = Some(tag_value);
+                    }
+                    META_FALLBACK => {
+                        let fallback = get_lit_bool(&nv.value)?.value;
+                        self.fallback = Some(fallback);
+                    }
+                    META_INFO => {
+                        let info = get_lit_str(&nv.value)?.value();
+                        self.info = Some(info);
+                    }
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            syn::Meta::List(_) => return make_err(meta, "unexpected meta item"),
+        };
+        Ok(())
+    }
+}
+
+impl OptionBuilder for ValueStringOptions {
     fn add_option(&mut self, meta: &syn::Meta) -> syn::Result<()> {
         match meta {
             syn::Meta::NameValue(nv) => match nv.path.get_ident() {
@@ -368,6 +874,48 @@ impl OptionBuilder for VariantOptions {
                         let rename = get_lit_str(&nv.value)?.value();
                         self.rename = Some(rename);
                     }
+                    META_RANGE => match unpack_expr(&nv.value).as_slice() {
+                        [min, max] => {
+                            let min: u64 = get_lit_int(min)?.base10_parse()?;
+                            let max: u64 = get_lit_int(max)?.base10_parse()?;
+                            if min > max {
+                                return make_err(&nv.value, "expected range to be (min, max)");
+                            }
+                            self.range = Some((min, max));
+                        }
+                        _ => return make_err(&nv.value, "expected range to be (min, max)"),
+                    },
+                    _ => return make_err(meta, "unrecognized attribute"),
+                },
+            },
+            _ => return make_err(meta, "unexpected meta item"),
+        };
+        Ok(())
+    }
+}
+
+impl OptionBuilder for PreferenceOptions {
+    fn add_option(&mut self, meta: &syn::Meta) -> syn::Result<()> {
+        match meta {
+            syn::Meta::NameValue(nv) => match nv.path.get_ident() {
+                None => return make_err(meta, "expected identifier"),
+                Some(ident) => match ident.to_string().as_str() {
+                    META_RENAME => {
+                        let rename = get_lit_str(&nv.value)?.value();
+                        self.rename = Some(rename);
+                    }
+                    META_DEFAULT => match &nv.value {
+                        syn::Expr::Lit(_) => self.default = Some(nv.value.clone()),
+                        _ => return make_err(&nv.value, "expected a literal"),
+                    },
+                    META_DECODE_FROM => {
+                        let decode_from = get_lit_str(&nv.value)?.value();
+                        self.decode_from = Some(decode_from);
+                    }
+                    META_MAX => {
+                        let max = get_lit_int(&nv.value)?.base10_parse()?;
+                        self.max = Some(max);
+                    }
                     _ => return make_err(meta, "unrecognized attribute"),
                 },
             },
@@ -378,12 +926,44 @@ impl OptionBuilder for VariantOptions {
 }
 
 const META_DECODE_FROM: &str = "decode_from";
+const META_HEURISTIC: &str = "heuristic";
+const META_PREFS: &str = "prefs";
+const META_DEFAULT: &str = "default";
+const META_MAX: &str = "max";
 const META_PROTO_DESC: &str = "proto_desc";
 const META_PROTO_NAME: &str = "proto_name";
 const META_PROTO_FILTER: &str = "proto_filter";
 const META_HIDE: &str = "hide";
 const META_SAVE: &str = "save";
 const META_LEN: &str = "len_field";
+const META_BYTES_LEN: &str = "bytes_len_field";
+const META_LEN_EXPR: &str = "len";
+const META_UNTIL_END: &str = "until_end";
+const META_UNTIL: &str = "until";
+const META_TERMINATOR: &str = "terminator";
+const META_IF: &str = "if";
+const META_PRESENT_IF: &str = "present_if";
+const META_VIRTUAL: &str = "virtual";
+const META_GENERATED: &str = "generated";
+const META_ALIGN: &str = "align";
+const META_SKIP: &str = "skip";
+const META_SKIP_EXPR: &str = "skip_expr";
+const META_SHOW_PADDING: &str = "show_padding";
+const META_BITS: &str = "bits";
+const META_CHECKSUM: &str = "checksum";
+const META_CHECKSUM_FN: &str = "checksum_fn";
+const META_OVER: &str = "over";
+
+/// The built-in checksums, which are named after the functions in `wsdf::checksum`.
+const CHECKSUMS: &[&str] = &[
+    "crc16",
+    "crc32",
+    "crc32c",
+    "internet",
+    "adler32",
+    "fletcher16",
+    "fletcher32",
+];
 const META_WS_TYPE: &str = "typ";
 const META_WS_ENC: &str = "enc";
 const META_WS_DISPLAY: &str = "display";
@@ -395,18 +975,55 @@ const META_SUBDISSECTOR: &str = "subdissector";
 const META_RENAME: &str = "rename";
 const META_PRE_DISSECT: &str = "pre_dissect";
 const META_POST_DISSECT: &str = "post_dissect";
+const META_MASK: &str = "mask";
+const META_VALUE_STRINGS: &str = "value_strings";
+const META_RANGE: &str = "range";
+const META_TAG: &str = "tag";
+const META_TAG_VALUE: &str = "tag_value";
+const META_FALLBACK: &str = "fallback";
+const META_INFO: &str = "info";
+const META_WIDTH: &str = "width";
+const META_TFS: &str = "tfs";
+const META_SIZE: &str = "size";
+const META_NUL_TERMINATED: &str = "nul_terminated";
+const META_LEN_PREFIX: &str = "len_prefix";
+const META_TRIM: &str = "trim";
+const META_LE: &str = "le";
+const META_BE: &str = "be";
+const META_ASCII: &str = "ascii";
+const META_ENC_FIELD: &str = "enc_field";
+const META_ENC_FN: &str = "enc_fn";
 
 /// Extracts all the meta items from a list of attributes.
 pub(crate) fn get_meta_items(attrs: &[&syn::Attribute]) -> syn::Result<Vec<syn::Meta>> {
     let mut xs = Vec::new();
     for attr in attrs {
         let pairs: Punctuated<syn::Meta, syn::Token![,]> =
-            attr.parse_args_with(Punctuated::parse_terminated)?;
+            attr.parse_args_with(|input: syn::parse::ParseStream| {
+                Punctuated::parse_terminated_with(input, parse_meta)
+            })?;
         xs.extend(pairs);
     }
     Ok(xs)
 }
 
+/// Parses a meta item. Unlike `syn::Meta`'s own parser, this also accepts the `if` and `virtual`
+/// keywords as names.
+fn parse_meta(input: syn::parse::ParseStream) -> syn::Result<syn::Meta> {
+    if !input.peek(syn::Token![if]) && !input.peek(syn::Token![virtual]) {
+        return input.parse();
+    }
+    let path: syn::Path = syn::Ident::parse_any(input)?.into();
+    if !input.peek(syn::Token![=]) {
+        return Ok(syn::Meta::Path(path));
+    }
+    Ok(syn::Meta::NameValue(syn::MetaNameValue {
+        path,
+        eq_token: input.parse()?,
+        value: input.parse()?,
+    }))
+}
+
 /// Extracts the attributes which start with some identifier.
 pub(crate) fn get_attrs<'a>(attrs: &'a [syn::Attribute], ident: &str) -> Vec<&'a syn::Attribute> {
     attrs
@@ -462,6 +1079,55 @@ mod test_attribute_parsing {
         let attr: syn::Attribute = parse_quote! { #[doc = "foo"] };
         assert_eq!(get_docs(&attr), Some("foo".to_string()));
     }
+
+    #[test]
+    fn heuristic_takes_one_or_many_pairs() {
+        let attrs: Vec<syn::Attribute> =
+            vec![parse_quote! { #[wsdf(heuristic = ("udp", "is_mine"))] }];
+        let opts = init_options::<ProtocolOptions>(&attrs).unwrap();
+        assert_eq!(opts.heuristic.len(), 1);
+        assert_eq!(opts.heuristic[0].table, "udp");
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! {
+            #[wsdf(heuristic = [("udp", "is_mine"), ("tcp", "checks::is_mine")])]
+        }];
+        let opts = init_options::<ProtocolOptions>(&attrs).unwrap();
+        assert_eq!(opts.heuristic.len(), 2);
+        assert_eq!(opts.heuristic[1].check, parse_quote!(checks::is_mine));
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! { #[wsdf(heuristic = "udp")] }];
+        assert!(init_options::<ProtocolOptions>(&attrs).is_err());
+    }
+
+    #[test]
+    fn byte_order_shorthands() {
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! { #[wsdf(le, info = "foo")] }];
+        let opts = init_options::<ProtocolFieldOptions>(&attrs).unwrap();
+        assert_eq!(opts.byte_order, Some(ByteOrder::Little));
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! { #[wsdf(be, ascii)] }];
+        let opts = init_options::<FieldOptions>(&attrs).unwrap();
+        assert_eq!(opts.byte_order, Some(ByteOrder::Big));
+        assert_eq!(opts.ascii, Some(true));
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! { #[wsdf(le, be)] }];
+        assert!(init_options::<FieldOptions>(&attrs).is_err());
+    }
+
+    #[test]
+    fn keywords_are_accepted() {
+        let attrs: Vec<syn::Attribute> =
+            vec![parse_quote! { #[wsdf(save, if = "flags & 0x80 != 0")] }];
+        let opts = init_options::<FieldOptions>(&attrs).unwrap();
+        let want: syn::Expr = parse_quote! { flags & 0x80 != 0 };
+        assert_eq!(opts.if_expr, Some(want));
+        assert_eq!(opts.save, Some(true));
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote! { #[wsdf(virtual, generated)] }];
+        let opts = init_options::<FieldOptions>(&attrs).unwrap();
+        assert_eq!(opts.is_virtual, Some(true));
+        assert_eq!(opts.generated, Some(true));
+    }
 }
 
 /// Represents the value of a wireshark display option. It is a pair where the second item is
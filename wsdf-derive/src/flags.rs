@@ -0,0 +1,465 @@
+use quote::quote;
+use syn::parse_quote;
+
+use crate::attributes::*;
+use crate::types::{DataType, HfStrings, PrimitiveType};
+use crate::util::*;
+
+/// A type which derives Flags. It is a bitmask, where each field is one flag, i.e. some subset of
+/// the bits.
+///
+/// ```ignore
+/// #[derive(Flags)]
+/// struct Flags {
+///     #[wsdf(mask = 0x8000)]
+///     qr: u16,
+///     #[wsdf(mask = 0x7800)]
+///     opcode: u16,
+/// }
+/// ```
+///
+/// A flag may also be a `bool`, in which case it is true iff any of its bits are set.
+#[derive(Debug)]
+pub(crate) struct FlagsRoot<'a> {
+    ident: &'a syn::Ident,
+    /// The integer type shared by the whole bitmask and each of its flags.
+    typ: PrimitiveType,
+    opts: FlagsOptions,
+    docs: Option<String>,
+    flags: Vec<Flag<'a>>,
+}
+
+#[derive(Debug)]
+struct Flag<'a> {
+    ident: &'a syn::Ident,
+    is_bool: bool,
+    mask: u64,
+    opts: FlagOptions,
+    docs: Option<String>,
+}
+
+impl<'a> FlagsRoot<'a> {
+    pub(crate) fn from_input(input: &'a syn::DeriveInput) -> syn::Result<Self> {
+        let fields = match &input.data {
+            syn::Data::Struct(syn::DataStruct {
+                fields: syn::Fields::Named(fields),
+                ..
+            }) => &fields.named,
+            _ => {
+                return make_err(
+                    &input.ident,
+                    "only structs with named fields can derive Flags",
+                )
+            }
+        };
+        if fields.is_empty() {
+            return make_err(&input.ident, "expected at least one flag");
+        }
+
+        let opts = init_options::<FlagsOptions>(&input.attrs)?;
+        if opts.byte_order.is_some() && opts.ws_enc.is_some() {
+            return make_err(&input.ident, "le and be cannot be combined with enc");
+        }
+        let docs = input.attrs.iter().find_map(get_docs);
+
+        let mut typ = match &opts.width {
+            None => None,
+            Some(ident) => match PrimitiveType::from_unsigned_ident(ident) {
+                Some(typ) => Some(typ),
+                None => return make_err(ident, "expected width to be an unsigned integer type"),
+            },
+        };
+        let mut flags = Vec::with_capacity(fields.len());
+
+        for field in fields {
+            let ident = field.ident.as_ref().unwrap(); // safe to unwrap, the fields are named
+            let field_typ = Self::flag_type(&field.ty)?;
+            let is_bool = field_typ.is_none();
+            match (&typ, field_typ) {
+                // Bools take on the type of the whole bitmask.
+                (_, None) => (),
+                (None, Some(field_typ)) => typ = Some(field_typ),
+                (Some(typ), Some(field_typ)) if *typ != field_typ => {
+                    return make_err(&field.ty, "all flags must have the same type");
+                }
+                (Some(_), Some(_)) => (),
+            }
+
+            let flag_opts = init_options::<FlagOptions>(&field.attrs)?;
+            if is_bool && flag_opts.value_strings.is_some() {
+                return make_err(ident, "value_strings can only be used on integer flags");
+            }
+            if !is_bool && flag_opts.tfs.is_some() {
+                return make_err(ident, "tfs can only be used on bool flags");
+            }
+
+            let mask = match flag_opts.mask {
+                None => return make_err(ident, "expected a mask for this flag"),
+                Some(0) => return make_err(ident, "mask must not be zero"),
+                Some(mask) => mask,
+            };
+
+            flags.push(Flag {
+                ident,
+                is_bool,
+                mask,
+                opts: flag_opts,
+                docs: field.attrs.iter().find_map(get_docs),
+            });
+        }
+
+        let typ = match typ {
+            Some(typ) => typ,
+            None => {
+                return make_err(
+                    &input.ident,
+                    "expected a width, e.g. #[wsdf(width = u8)], since every flag is a bool",
+                )
+            }
+        };
+        for flag in &flags {
+            if flag.mask & !typ.unsigned_max() != 0 {
+                return make_err(flag.ident, "mask does not fit in the flag's type");
+            }
+        }
+
+        Ok(Self {
+            ident: &input.ident,
+            typ,
+            opts,
+            docs,
+            flags,
+        })
+    }
+
+    /// Returns the unsigned integer type of a flag, or None if the flag is a bool.
+    fn flag_type(typ: &syn::Type) -> syn::Result<Option<PrimitiveType>> {
+        let ident = match typ {
+            syn::Type::Path(path) => path.path.get_ident(),
+            _ => None,
+        };
+        if ident.is_some_and(|ident| ident == "bool") {
+            return Ok(None);
+        }
+        match ident.and_then(PrimitiveType::from_unsigned_ident) {
+            Some(typ) => Ok(Some(typ)),
+            None => make_err(
+                typ,
+                "expected flag to be an unsigned integer type or a bool",
+            ),
+        }
+    }
+
+    /// Without an encoding, the bitmask follows the byte order of its container.
+    fn ws_enc(&self) -> syn::Expr {
+        match (&self.opts.ws_enc, self.opts.byte_order) {
+            (Some(ws_enc), _) => format_ws_enc(ws_enc),
+            (None, Some(order)) => format_ws_enc(order.ws_enc()),
+            (None, None) => parse_quote! { #WSDF_BYTE_ORDER },
+        }
+    }
+
+    pub(crate) fn dissection_fn(&self) -> syn::ItemFn {
+        let self_ident = self.ident;
+        let size = self.typ.size_expr();
+        let ws_enc = self.ws_enc();
+        let default_label = self.ident.to_wsdf_title_case();
+        let default_label_cstr: syn::Expr = cstr!(default_label);
+
+        let get_flag_hfs = self.flags.iter().map(|flag| {
+            let snake_cased = flag.ident.to_wsdf_snake_case();
+            quote! {{
+                let #WSDF_PREFIX_NEXT = #WSDF_PREFIX.to_owned() + "." + #snake_cased;
+                <#self_ident as wsdf::ProtocolField>::hf_map(
+                    wsdf::HfMapOp::Get(#WSDF_PREFIX_NEXT.as_str()),
+                ).unwrap_or_else(|| panic!("expected hf for {} to exist", #WSDF_PREFIX_NEXT))
+            }}
+        });
+        let flag_field_ptrs = (0..self.flags.len()).map(|idx| {
+            quote! { &mut #WSDF_FLAG_HFS[#idx] as *mut std::ffi::c_int }
+        });
+
+        let save_flags = self.save_flags();
+
+        parse_quote! {
+            #[allow(clippy::too_many_arguments, clippy::ptr_arg)]
+            fn dissect<'a>(#DISSECTION_PARAMS) -> std::result::Result<std::ffi::c_int, std::ffi::c_int> {
+                let #WSDF_FIELD_NAME = unsafe {
+                    std::ffi::CStr::from_ptr(#WSDF_SUBTREE_LABEL.unwrap_or(#default_label_cstr))
+                };
+                if !wsdf::expert::check_bounds(
+                    #WSDF_PINFO,
+                    #WSDF_PARENT_NODE,
+                    #WSDF_TVB,
+                    *<#self_ident as wsdf::ProtocolField>::proto_id(),
+                    #WSDF_TVB_BUF.len(),
+                    #WSDF_START,
+                    #size,
+                    &#WSDF_FIELD_NAME.to_string_lossy(),
+                ) {
+                    return std::result::Result::Err(0);
+                }
+
+                let #WSDF_HF = <#self_ident as wsdf::ProtocolField>::hf_map(
+                    wsdf::HfMapOp::Get(#WSDF_PREFIX),
+                ).unwrap_or_else(|| panic!("expected hf for {} to exist", #WSDF_PREFIX));
+
+                // Wireshark expects a null terminated array of pointers to each flag's hf.
+                let mut #WSDF_FLAG_HFS = [#(#get_flag_hfs),*];
+                let #WSDF_FLAG_FIELDS = [#(#flag_field_ptrs,)* std::ptr::null_mut()];
+
+                unsafe {
+                    wsdf::epan_sys::proto_tree_add_bitmask(
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        #WSDF_START as std::ffi::c_uint,
+                        #WSDF_HF,
+                        <#self_ident as wsdf::ProtocolField>::ett(),
+                        #WSDF_FLAG_FIELDS.as_ptr(),
+                        #ws_enc,
+                    );
+                }
+
+                #save_flags
+
+                std::result::Result::Ok(#size)
+            }
+        }
+    }
+
+    /// Produces code which adds the flags marked with `save` to the fields store. Each flag is
+    /// stored already masked and shifted, e.g. a mask of 0x0030 over 0x0020 gives 2.
+    fn save_flags(&self) -> Option<proc_macro2::TokenStream> {
+        let saved: Vec<_> = self
+            .flags
+            .iter()
+            .filter(|flag| flag.opts.save.unwrap_or(false))
+            .collect();
+        if saved.is_empty() {
+            return None;
+        }
+
+        let (get_fn, insert_fn, int_type, ws_enc) = match self.typ {
+            PrimitiveType::U8 => (
+                quote! { tvb_get_uint8 },
+                quote! { insert_u8 },
+                quote! { u8 },
+                None,
+            ),
+            PrimitiveType::U16 => (
+                quote! { tvb_get_uint16 },
+                quote! { insert_u16 },
+                quote! { u16 },
+                Some(self.ws_enc()),
+            ),
+            PrimitiveType::U32 => (
+                quote! { tvb_get_uint32 },
+                quote! { insert_u32 },
+                quote! { u32 },
+                Some(self.ws_enc()),
+            ),
+            _ => (
+                quote! { tvb_get_uint64 },
+                quote! { insert_u64 },
+                quote! { u64 },
+                Some(self.ws_enc()),
+            ),
+        };
+
+        let insert_flags = saved.iter().map(|flag| {
+            let snake_cased = flag.ident.to_wsdf_snake_case();
+            let mask = flag.mask;
+            let shift = flag.mask.trailing_zeros();
+            if flag.is_bool {
+                return quote! {
+                    #WSDF_FIELDS_STORE.insert_bool(
+                        &(#WSDF_PREFIX.to_owned() + "." + #snake_cased),
+                        #WSDF_VALUE as u64 & #mask != 0,
+                    );
+                };
+            }
+            quote! {
+                #WSDF_FIELDS_STORE.#insert_fn(
+                    &(#WSDF_PREFIX.to_owned() + "." + #snake_cased),
+                    ((#WSDF_VALUE as u64 & #mask) >> #shift) as #int_type,
+                );
+            }
+        });
+
+        Some(quote! {
+            let #WSDF_VALUE = unsafe {
+                wsdf::epan_sys::#get_fn(
+                    #WSDF_TVB,
+                    #WSDF_START,
+                    #ws_enc // do not add a comma here!
+                )
+            };
+            #(#insert_flags)*
+        })
+    }
+
+    pub(crate) fn registration_fn(&self) -> syn::ItemFn {
+        let self_ident = self.ident;
+        let ws_type = format_ws_type(self.typ.default_ws_type());
+
+        // The bitmask itself takes on the name and description of the field using it, much like
+        // a unit tuple.
+        let default_display = FieldDisplayPair::new(("BASE_HEX", None));
+        let ws_display = self.opts.ws_display.as_ref().unwrap_or(&default_display);
+        let create_hf = DataType::create_hf_impl(
+            true,
+            self.ident,
+            &self.ident.to_wsdf_title_case(),
+            &self.docs,
+            &ws_type,
+            &ws_display.to_expr(),
+            None,
+            0,
+        );
+
+        let create_flag_hfs = self.flags.iter().map(|flag| {
+            let snake_cased = flag.ident.to_wsdf_snake_case();
+            let name = flag
+                .opts
+                .rename
+                .clone()
+                .unwrap_or_else(|| flag.ident.to_wsdf_title_case());
+            // Wireshark wants the width of the whole bitmask, in bits, as the display of a bool.
+            let (flag_ws_type, default_display) = if flag.is_bool {
+                let bits = self.typ.unsigned_max().count_ones() as i32;
+                (
+                    format_ws_type("FT_BOOLEAN"),
+                    parse_quote! { #bits as std::ffi::c_int },
+                )
+            } else {
+                (
+                    ws_type.clone(),
+                    FieldDisplayPair::new(("BASE_DEC", None)).to_expr(),
+                )
+            };
+            let ws_display = match &flag.opts.ws_display {
+                Some(ws_display) => ws_display.to_expr(),
+                None => default_display,
+            };
+            let strings = match (&flag.opts.value_strings, &flag.opts.tfs) {
+                (Some(path), _) => Some(HfStrings::ValueStrings(path)),
+                (None, Some((true_string, false_string))) => {
+                    Some(HfStrings::Tfs(true_string, false_string))
+                }
+                (None, None) => None,
+            };
+            let create_hf = DataType::create_hf_impl(
+                false,
+                self.ident,
+                &name,
+                &flag.docs,
+                &flag_ws_type,
+                &ws_display,
+                strings,
+                flag.mask,
+            );
+            quote! {
+                let #WSDF_PREFIX_NEXT = #WSDF_PREFIX.to_owned() + "." + #snake_cased;
+                #create_hf
+            }
+        });
+
+        parse_quote! {
+            fn register(#REGISTRATION_PARAMS) {
+                let mut #WSDF_HFS: Vec<wsdf::epan_sys::hf_register_info> = Vec::new();
+
+                let #WSDF_PREFIX_NEXT = #WSDF_PREFIX.to_owned();
+                #create_hf
+                #(#create_flag_hfs)*
+
+                let #WSDF_HFS = std::boxed::Box::leak(#WSDF_HFS.into_boxed_slice());
+                unsafe {
+                    wsdf::epan_sys::proto_register_field_array(
+                        #WSDF_PROTO_ID,
+                        #WSDF_HFS.as_mut_ptr() as *mut wsdf::epan_sys::hf_register_info,
+                        #WSDF_HFS.len() as std::ffi::c_int,
+                    );
+                }
+
+                *<#self_ident as wsdf::ProtocolField>::proto_id() = #WSDF_PROTO_ID; // "cache" the protocol ID
+            }
+        }
+    }
+}
+
+const WSDF_HF: IdentHelper = IdentHelper("__wsdf_hf");
+const WSDF_FIELD_NAME: IdentHelper = IdentHelper("__wsdf_field_name");
+const WSDF_FLAG_HFS: IdentHelper = IdentHelper("__wsdf_flag_hfs");
+const WSDF_FLAG_FIELDS: IdentHelper = IdentHelper("__wsdf_flag_fields");
+const WSDF_VALUE: IdentHelper = IdentHelper("__wsdf_value");
+
+#[cfg(test)]
+mod test_flags_root {
+    use super::*;
+
+    #[test]
+    fn flags_must_share_a_type() {
+        let input: syn::DeriveInput = parse_quote! {
+            struct Flags {
+                #[wsdf(mask = 0x80)]
+                a: u8,
+                #[wsdf(mask = 0x40)]
+                b: u16,
+            }
+        };
+        assert!(FlagsRoot::from_input(&input).is_err());
+    }
+
+    #[test]
+    fn mask_must_fit_in_type() -> syn::Result<()> {
+        let input: syn::DeriveInput = parse_quote! {
+            struct Flags {
+                #[wsdf(mask = 0x100)]
+                a: u8,
+            }
+        };
+        assert!(FlagsRoot::from_input(&input).is_err());
+
+        let input: syn::DeriveInput = parse_quote! {
+            struct Flags {
+                #[wsdf(mask = 0x8000)]
+                qr: u16,
+                #[wsdf(mask = 0x7800, rename = "Opcode")]
+                opcode: u16,
+            }
+        };
+        let root = FlagsRoot::from_input(&input)?;
+        assert_eq!(root.typ, PrimitiveType::U16);
+        assert_eq!(
+            root.flags.iter().map(|flag| flag.mask).collect::<Vec<_>>(),
+            vec![0x8000, 0x7800]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bool_flags_need_a_width() -> syn::Result<()> {
+        let input: syn::DeriveInput = parse_quote! {
+            struct Flags {
+                #[wsdf(mask = 0x80)]
+                a: bool,
+            }
+        };
+        assert!(FlagsRoot::from_input(&input).is_err());
+
+        let input: syn::DeriveInput = parse_quote! {
+            struct Flags {
+                #[wsdf(mask = 0x8000)]
+                a: bool,
+                #[wsdf(mask = 0x00ff)]
+                b: u16,
+            }
+        };
+        let root = FlagsRoot::from_input(&input)?;
+        assert_eq!(root.typ, PrimitiveType::U16);
+        assert!(root.flags[0].is_bool);
+
+        Ok(())
+    }
+}
@@ -4,15 +4,22 @@ use proc_macro::TokenStream;
 
 use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
 
 mod attributes;
+mod flags;
 mod model;
+mod prefs;
 mod types;
 mod util;
+mod value_strings;
 
 use crate::attributes::*;
+use crate::flags::FlagsRoot;
 use crate::model::DataRoot;
+use crate::prefs::PrefsStruct;
 use crate::util::*;
+use crate::value_strings::ValueStringsEnum;
 
 #[derive(Debug)]
 enum PluginType {
@@ -153,7 +160,104 @@ pub fn version(input: TokenStream) -> TokenStream {
     version_info.into()
 }
 
-/// Marks a struct as the protocol root.
+#[derive(Debug)]
+struct PluginMacroInput {
+    protocols: Vec<syn::Path>,
+}
+
+impl Parse for PluginMacroInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let paths: Punctuated<syn::Path, syn::Token![,]> = Punctuated::parse_terminated(input)?;
+        if paths.is_empty() {
+            return Err(input.error("expected at least one protocol"));
+        }
+
+        let mut protocols: Vec<syn::Path> = Vec::with_capacity(paths.len());
+        for path in paths {
+            if protocols.contains(&path) {
+                return make_err(&path, "protocol is registered more than once");
+            }
+            protocols.push(path);
+        }
+
+        Ok(PluginMacroInput { protocols })
+    }
+}
+
+/// Declares the protocols provided by the plugin.
+///
+/// Wireshark looks for exactly one `plugin_register` symbol in each plugin library. This macro
+/// generates it, and registers every type passed in. Each type must derive `Protocol`, and keeps
+/// its own `proto_register` and `proto_reg_handoff` routines.
+///
+/// # Example
+///
+/// ```ignore
+/// wsdf::version!("0.0.1", 4, 4);
+/// wsdf::plugin!(MoldUDP64, Itch);
+///
+/// #[derive(wsdf::Protocol)]
+/// #[wsdf(decode_from = "udp.port")]
+/// struct MoldUDP64 {
+///     session: [u8; 10],
+///     sequence_number: u64,
+///     message_count: u16,
+/// }
+///
+/// #[derive(wsdf::Protocol)]
+/// #[wsdf(decode_from = "udp.port")]
+/// struct Itch {
+///     message_type: u8,
+///     timestamp: u64,
+/// }
+/// ```
+#[proc_macro]
+pub fn plugin(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as PluginMacroInput);
+
+    let register_protocols = input.protocols.iter().map(|path| {
+        // Safe to unwrap, since a path always has at least one segment.
+        let snake_cased = path.segments.last().unwrap().ident.to_wsdf_snake_case();
+
+        let proto_register_ident = format_ident!("proto_register_{}", snake_cased);
+        let proto_reg_handoff_ident = format_ident!("proto_reg_handoff_{}", snake_cased);
+
+        quote! {
+            {
+                // Wrapper names to test convention
+                extern "C" fn #proto_register_ident() {
+                    <#path as wsdf::Protocol>::proto_register()
+                }
+
+                extern "C" fn #proto_reg_handoff_ident() {
+                    <#path as wsdf::Protocol>::proto_reg_handoff()
+                }
+
+                // Wireshark keeps a pointer to this, so it must live for the rest of the program.
+                static PLUG: wsdf::epan_sys::proto_plugin = wsdf::epan_sys::proto_plugin {
+                    register_protoinfo: std::option::Option::Some(#proto_register_ident),
+                    register_handoff: std::option::Option::Some(#proto_reg_handoff_ident),
+                };
+
+                // SAFETY: this code is only called once in a single thread when wireshark starts
+                unsafe {
+                    wsdf::epan_sys::proto_register_plugin(&PLUG);
+                }
+            }
+        }
+    });
+
+    quote! {
+        #[no_mangle]
+        extern "C" fn plugin_register() {
+            #(#register_protocols)*
+        }
+    }
+    .into()
+}
+
+/// Marks a struct as the protocol root. The struct must also be listed in [`plugin!`] for
+/// Wireshark to pick it up.
 #[proc_macro_derive(Protocol, attributes(wsdf))]
 pub fn derive_protocol(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -172,7 +276,23 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
     let root = DataRoot::from_input(input, true)?;
     let proto_opts = init_options::<ProtocolOptions>(&input.attrs)?;
 
-    if proto_opts.decode_from.is_empty() {
+    // Range preferences may bind the protocol to a dissector table instead. Whether they do is
+    // only known to the Preferences impl, so it is checked at compile time through it.
+    let binds_by_prefs = match &proto_opts.prefs {
+        Some(prefs) if proto_opts.decode_from.is_empty() && proto_opts.heuristic.is_empty() => {
+            Some(quote! {
+                const _: () = assert!(
+                    <#prefs as wsdf::Preferences>::BINDS_TABLE,
+                    "expected some way of registering with dissector table, e.g. a preference with decode_from",
+                );
+            })
+        }
+        _ => None,
+    };
+    if proto_opts.decode_from.is_empty()
+        && proto_opts.heuristic.is_empty()
+        && binds_by_prefs.is_none()
+    {
         return make_err(
             &input.ident,
             "expected some way of registering with dissector table",
@@ -197,36 +317,10 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
 
     let input_ident = &input.ident;
 
-    let proto_register_ident = format_ident!("proto_register_{}", snake_cased);
-    let proto_reg_handoff_ident = format_ident!("proto_reg_handoff_{}", snake_cased);
-
-    let plugin_register = quote! {
-        #[no_mangle]
-        extern "C" fn plugin_register() {
-            static mut plug: wsdf::epan_sys::proto_plugin = wsdf::epan_sys::proto_plugin {
-                register_protoinfo: None,
-                register_handoff: None,
-            };
-
-            // Wrapper names to test convention
-            extern "C" fn #proto_register_ident() {
-                <#input_ident as wsdf::Protocol>::proto_register()
-            }
-
-            extern "C" fn #proto_reg_handoff_ident() {
-                <#input_ident as wsdf::Protocol>::proto_reg_handoff()
-            }
-
-            // SAFETY: this code is only called once in a single thread when wireshark starts
-            unsafe {
-                plug.register_protoinfo =
-                    std::option::Option::Some(#proto_register_ident);
-                plug.register_handoff =
-                    std::option::Option::Some(#proto_reg_handoff_ident);
-                wsdf::epan_sys::proto_register_plugin(&plug);
-            }
-        }
-    };
+    let add_heuristic = proto_opts
+        .heuristic
+        .iter()
+        .map(|heuristic| register_heuristic(heuristic, input_ident, proto_name, proto_filter));
 
     let init_rust_owned_tvb_buf = init_tvb_buf();
 
@@ -252,7 +346,9 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
             // Initialize a context to keep fields.
             let mut #WSDF_FIELDS_STORE = wsdf::FieldsStore::default();
 
-            <#input_ident as wsdf::ProtocolField>::dissect(
+            // A malformed packet has already been flagged with an expert item by now, so all
+            // that's left is to report the bytes dissected.
+            let result = <#input_ident as wsdf::ProtocolField>::dissect(
                 0,
                 #WSDF_TVB,
                 #WSDF_PROTO_TREE_ROOT,
@@ -263,10 +359,26 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
                 #WSDF_PINFO,
                 #WSDF_PROTO_TREE_ROOT,
                 &mut #WSDF_FIELDS_STORE,
-            )
+                wsdf::epan_sys::ENC_BIG_ENDIAN,
+            );
+            match result {
+                std::result::Result::Ok(n) | std::result::Result::Err(n) => n,
+            }
         }
     };
 
+    let register_prefs = proto_opts.prefs.as_ref().map(|prefs| {
+        quote! {
+            let module = unsafe {
+                wsdf::epan_sys::prefs_register_protocol(
+                    proto_id,
+                    std::option::Option::Some(<#input_ident as wsdf::Protocol>::proto_reg_handoff),
+                )
+            };
+            <#prefs as wsdf::Preferences>::register(module);
+        }
+    });
+
     let protoinfo_fn = quote! {
         extern "C" fn proto_register() {
             let proto_id = unsafe {
@@ -282,34 +394,60 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
                 wsdf::FieldIdent::null(),
                 wsdf::FieldBlurb::null(),
             );
+            wsdf::expert::register(proto_id, #proto_filter);
+            #register_prefs
         }
     };
 
-    let handoff_fn = quote! {
-        extern "C" fn proto_reg_handoff() {
-            unsafe {
-                let handle = wsdf::epan_sys::create_dissector_handle(
-                    std::option::Option::Some(<#input_ident as wsdf::Protocol>::dissect_main),
-                    *<#input_ident as wsdf::ProtocolField>::proto_id(),
-                );
-                #(#add_dissector)*
+    let handoff_fn = match &proto_opts.prefs {
+        None => quote! {
+            extern "C" fn proto_reg_handoff() {
+                unsafe {
+                    let handle = wsdf::epan_sys::create_dissector_handle(
+                        std::option::Option::Some(<#input_ident as wsdf::Protocol>::dissect_main),
+                        *<#input_ident as wsdf::ProtocolField>::proto_id(),
+                    );
+                    #(#add_dissector)*
+                    #(#add_heuristic)*
+                }
             }
-        }
+        },
+        // Wireshark calls this again whenever the preferences are applied, so the handle is only
+        // created once, while range preferences are bound anew each time.
+        Some(prefs) => quote! {
+            extern "C" fn proto_reg_handoff() {
+                static mut HANDLE: wsdf::epan_sys::dissector_handle_t = std::ptr::null_mut();
+                unsafe {
+                    if HANDLE.is_null() {
+                        let handle = wsdf::epan_sys::create_dissector_handle(
+                            std::option::Option::Some(<#input_ident as wsdf::Protocol>::dissect_main),
+                            *<#input_ident as wsdf::ProtocolField>::proto_id(),
+                        );
+                        #(#add_dissector)*
+                        #(#add_heuristic)*
+                        HANDLE = handle;
+                    }
+                    <#prefs as wsdf::Preferences>::bind(HANDLE);
+                }
+            }
+        },
     };
 
     let static_int_getters = static_int_getters();
     let static_maps = static_map_fns();
 
+    // A struct may borrow its strings, e.g. with a &'a str field.
+    let lifetimes = elided_lifetimes(&input.generics)?;
     let ret = quote! {
-        #plugin_register
+        #binds_by_prefs
 
-        impl wsdf::Protocol for #input_ident {
+        impl wsdf::Protocol for #input_ident #lifetimes {
             #main_dissect_fn
             #protoinfo_fn
             #handoff_fn
         }
 
-        impl wsdf::ProtocolField for #input_ident {
+        impl wsdf::ProtocolField for #input_ident #lifetimes {
             #dissect_fn
             #register_fn
 
@@ -321,12 +459,78 @@ fn derive_protocol_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::To
     Ok(ret)
 }
 
+/// Creates the code to register the protocol with a heuristic dissector table.
+///
+/// The heuristic dissector calls the user's check function on the packet first, and only dissects
+/// it if the check passes. Otherwise, it tells Wireshark that the packet is not ours, so that the
+/// next heuristic dissector in the table can be tried.
+fn register_heuristic(
+    heuristic: &Heuristic,
+    input_ident: &syn::Ident,
+    proto_name: &str,
+    proto_filter: &str,
+) -> proc_macro2::TokenStream {
+    let check = &heuristic.check;
+    let table = &heuristic.table;
+    let table_cstr: syn::Expr = cstr!(table);
+    let display_name = format!("{proto_name} over {}", heuristic.table.to_uppercase());
+    let display_name_cstr: syn::Expr = cstr!(display_name);
+    let internal_name = format!("{proto_filter}_{}", heuristic.table.replace('.', "_"));
+    let internal_name_cstr: syn::Expr = cstr!(internal_name);
+
+    quote! {
+        {
+            unsafe extern "C" fn dissect_heuristic(
+                #WSDF_TVB: *mut wsdf::epan_sys::tvbuff,
+                #WSDF_PINFO: *mut wsdf::epan_sys::_packet_info,
+                #WSDF_PROTO_TREE_ROOT: *mut wsdf::epan_sys::_proto_node,
+                __wsdf_data: *mut std::ffi::c_void,
+            ) -> wsdf::epan_sys::gboolean {
+                // The check only reads the packet, so it borrows the TVB's bytes. They are copied once,
+                // by dissect_main, if the check passes.
+                let #WSDF_TVB_BUF = wsdf::tvb_captured_bytes(#WSDF_TVB);
+                let #WSDF_FIELDS_STORE = wsdf::FieldsStore::default();
+                let #WSDF_TAP_CTX = wsdf::tap::Context {
+                    field: (),
+                    fields: &#WSDF_FIELDS_STORE,
+                    pinfo: #WSDF_PINFO,
+                    packet: #WSDF_TVB_BUF,
+                    offset: 0,
+                };
+                if !wsdf::tap::handle_heuristic(&#WSDF_TAP_CTX, #check) {
+                    return 0;
+                }
+
+                <#input_ident as wsdf::Protocol>::dissect_main(
+                    #WSDF_TVB,
+                    #WSDF_PINFO,
+                    #WSDF_PROTO_TREE_ROOT,
+                    __wsdf_data,
+                );
+                1
+            }
+
+            wsdf::epan_sys::heur_dissector_add(
+                #table_cstr,
+                std::option::Option::Some(dissect_heuristic),
+                #display_name_cstr,
+                #internal_name_cstr,
+                *<#input_ident as wsdf::ProtocolField>::proto_id(),
+                wsdf::epan_sys::heuristic_enable_e_HEURISTIC_ENABLE,
+            );
+        }
+    }
+}
+
 /// Creates the code to initialize a Rust owned TVB slice.
+///
+/// Only the captured bytes are copied. The packet may have been cut short when it was captured,
+/// in which case the reported length is larger than what is actually available.
 fn init_tvb_buf() -> proc_macro2::TokenStream {
     const WSDF_TVB_BUF_SIZE: IdentHelper = IdentHelper("__wsdf_tvb_buf_size");
     quote! {
         let #WSDF_TVB_BUF_SIZE = unsafe {
-            wsdf::epan_sys::tvb_reported_length(#WSDF_TVB) as usize
+            wsdf::epan_sys::tvb_captured_length(#WSDF_TVB) as usize
         };
         let mut #WSDF_TVB_BUF = Vec::new();
         #WSDF_TVB_BUF.resize(#WSDF_TVB_BUF_SIZE, 0);
@@ -353,6 +557,59 @@ pub fn derive_protocol_field(input: TokenStream) -> TokenStream {
 fn derive_protocol_field_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let root = DataRoot::from_input(input, false)?;
 
+    let input_ident = &input.ident;
+    let lifetimes = elided_lifetimes(&input.generics)?;
+    let dissect_fn = root.dissection_fn();
+    let register_fn = root.registration_fn();
+    let tag_value_strings = root.tag_value_strings(&lifetimes);
+
+    let static_int_getters = static_int_getters();
+    let static_maps = static_map_fns();
+
+    let ret = quote! {
+        impl wsdf::ProtocolField for #input_ident #lifetimes {
+            #dissect_fn
+            #register_fn
+
+            #static_int_getters
+            #static_maps
+        }
+
+        #tag_value_strings
+    };
+
+    Ok(ret)
+}
+
+/// Declares a bitmask, where each field is a flag covering some of its bits. Each flag appears as
+/// its own item in Wireshark's tree, and can be used in display filters.
+///
+/// All fields must be of the same unsigned integer type, and each one must specify its bits with
+/// `#[wsdf(mask = ...)]`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(wsdf::Flags)]
+/// struct DnsFlags {
+///     #[wsdf(mask = 0x8000, rename = "Response")]
+///     qr: u16,
+///     #[wsdf(mask = 0x7800)]
+///     opcode: u16,
+///     #[wsdf(mask = 0x000f, rename = "Reply code")]
+///     rcode: u16,
+/// }
+/// ```
+#[proc_macro_derive(Flags, attributes(wsdf))]
+pub fn derive_flags(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    let ret = derive_flags_impl(&input).unwrap_or_else(|e| e.to_compile_error());
+    ret.into()
+}
+
+fn derive_flags_impl(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let root = FlagsRoot::from_input(input)?;
+
     let input_ident = &input.ident;
     let dissect_fn = root.dissection_fn();
     let register_fn = root.registration_fn();
@@ -373,6 +630,61 @@ fn derive_protocol_field_impl(input: &syn::DeriveInput) -> syn::Result<proc_macr
     Ok(ret)
 }
 
+/// Declares the options shown in a protocol's section of Wireshark's preferences dialog. Each field
+/// is a preference, whose kind is picked from its type: `bool`, `u32`, `String`,
+/// `wsdf::PrefRange`, or an enum deriving `ValueStrings`.
+///
+/// The struct is attached to the protocol with `#[wsdf(prefs = "...")]`, and read from taps with
+/// the `Prefs` parameter.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(wsdf::Preferences)]
+/// struct MyPrefs {
+///     /// Show prices as decimals rather than fixed point integers.
+///     decimal_prices: bool,
+///     #[wsdf(default = "9000-9010", decode_from = "udp.port")]
+///     ports: wsdf::PrefRange,
+/// }
+/// ```
+#[proc_macro_derive(Preferences, attributes(wsdf))]
+pub fn derive_preferences(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    let ret = PrefsStruct::from_input(&input)
+        .map(|prefs| prefs.impl_preferences())
+        .unwrap_or_else(|e| e.to_compile_error());
+    ret.into()
+}
+
+/// Declares a table of names for the values of an integer field. The enum must only have unit
+/// variants, and each variant names the value of its discriminant.
+///
+/// A variant may instead cover an inclusive range of values with `#[wsdf(range = (min, max))]`.
+/// The table is used by pointing a field at it with `#[wsdf(value_strings = "...")]`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(wsdf::ValueStrings)]
+/// #[repr(u16)]
+/// enum QType {
+///     A = 1,
+///     #[wsdf(rename = "CNAME")]
+///     Cname = 5,
+///     #[wsdf(range = (65280, 65534))]
+///     Private,
+/// }
+/// ```
+#[proc_macro_derive(ValueStrings, attributes(wsdf))]
+pub fn derive_value_strings(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    let ret = ValueStringsEnum::from_input(&input)
+        .map(|value_strings| value_strings.impl_value_strings())
+        .unwrap_or_else(|e| e.to_compile_error());
+    ret.into()
+}
+
 fn static_int_getters() -> proc_macro2::TokenStream {
     quote! {
         fn ett() -> std::ffi::c_int {
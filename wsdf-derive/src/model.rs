@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, ToTokens};
 use syn::{parse_quote, punctuated::Punctuated, spanned::Spanned};
 
 use crate::{attributes::*, types::*, util::*};
@@ -18,6 +18,9 @@ pub(crate) enum DataRoot<'a> {
     Enum {
         ident: &'a syn::Ident,
         variants: Vec<EnumVariant<'a>>,
+        /// Enums may read their own tag from the packet, instead of relying on a prior field to
+        /// pick the variant.
+        tag: Option<EnumTag>,
         cfg: DataRootConfig,
     },
 }
@@ -27,11 +30,39 @@ pub(crate) enum DataRoot<'a> {
 pub(crate) struct DataRootConfig {
     pre_dissect: Vec<syn::Path>,
     post_dissect: Vec<syn::Path>,
+    byte_order: Option<ByteOrder>,
+    /// Padding at the end of the type.
+    padding: Padding,
+    /// Whether the fields are packed bit by bit, rather than byte by byte.
+    bits: bool,
 }
 
 #[derive(Debug)]
 pub(crate) struct EnumVariant<'a> {
     data: DataTerminal<'a>,
+    /// For tagged enums only. The tag value which picks this variant.
+    tag_value: Option<u64>,
+}
+
+/// The integer tag at the start of a tagged enum, e.g.
+///
+/// ```ignore
+/// #[derive(ProtocolField)]
+/// #[wsdf(tag = u8)]
+/// enum Side {
+///     #[wsdf(tag_value = 0x42)]
+///     Bid,
+///     #[wsdf(tag_value = 0x41)]
+///     Ask,
+///     #[wsdf(fallback)]
+///     Unknown,
+/// }
+/// ```
+#[derive(Debug)]
+pub(crate) struct EnumTag {
+    typ: PrimitiveType,
+    /// Index of the variant to pick when the tag matches no other variant.
+    fallback: Option<usize>,
 }
 
 /// Every `DataTerminal` will have its own dissection function.
@@ -48,6 +79,8 @@ pub(crate) enum DataTerminal<'a> {
         ident: &'a syn::Ident,
         rename: Option<String>,
         fields: Vec<ProtoField<'a>>,
+        /// Format string for the Info column, which may reference the fields by name.
+        info: Option<String>,
     },
 }
 
@@ -60,7 +93,9 @@ impl DataRoot<'_> {
                 &cfg.pre_dissect,
                 &cfg.post_dissect,
             ),
-            DataRoot::Enum { variants, cfg, .. } => {
+            DataRoot::Enum {
+                variants, tag, cfg, ..
+            } => {
                 // Each variant gets its own local dissection function, and we'll store their
                 // pointers into an array. For instance...
                 //
@@ -113,11 +148,6 @@ impl DataRoot<'_> {
                 // variant to pick. We pretty much just pass all the parameters through,
                 // transparently. We do need to update the prefix and subtree labels.
                 let handle_dispatch_idx = quote! {
-                    debug_assert!(
-                        (0..#nr_variants).contains(&#WSDF_VARIANT_IDX),
-                        "dispatch index out of bounds",
-                    );
-
                     let #WSDF_PREFIX = #WSDF_PREFIX.to_owned() + "." + #WSDF_VARIANT_NAMES[#WSDF_VARIANT_IDX];
 
                     #WSDF_VARIANT_DISSECT_FNS[#WSDF_VARIANT_IDX](
@@ -131,14 +161,36 @@ impl DataRoot<'_> {
                         #WSDF_PINFO,
                         #WSDF_PROTO_TREE_ROOT,
                         #WSDF_FIELDS_STORE,
+                        #WSDF_BYTE_ORDER,
                     )
                 };
 
+                // If no valid variant can be picked, the packet is treated as malformed.
+                let proto_id = self.proto_id();
+                let invalid_dispatch = quote! {
+                    wsdf::expert::add_malformed(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *#proto_id,
+                        #WSDF_START,
+                        "unable to determine variant",
+                    );
+                    std::result::Result::Err(0)
+                };
+
+                // Tagged enums are not told which variant to pick. They read the tag themselves.
+                let read_tag = tag
+                    .as_ref()
+                    .map(|tag| self.read_tag(tag, variants, &handle_dispatch_idx));
+
+                let set_byte_order = self.set_byte_order();
+
                 parse_quote! {
                     #[allow(clippy::too_many_arguments, clippy::ptr_arg, clippy::int_plus_one)]
-                    fn dissect<'a>(#DISSECTION_PARAMS) -> std::ffi::c_int {
+                    fn dissect<'a>(#DISSECTION_PARAMS) -> std::result::Result<std::ffi::c_int, std::ffi::c_int> {
                         static #WSDF_VARIANT_DISSECT_FNS: [for<'a> fn ( #DISSECTION_PARAMS )
-                            -> std::ffi::c_int; #nr_variants] = [#(#func_idents),*];
+                            -> std::result::Result<std::ffi::c_int, std::ffi::c_int>; #nr_variants] = [#(#func_idents),*];
 
                         static #WSDF_VARIANT_NAMES: [&'static str; #nr_variants]
                             = [#(#variants_snake_cased,)*];
@@ -148,14 +200,17 @@ impl DataRoot<'_> {
 
                         #(#funcs)*
 
-                        let #WSDF_OFFSET = match #WSDF_DISPATCH {
-                            wsdf::VariantDispatch::None => unreachable!(), // @todo: handle this panic
-                            wsdf::VariantDispatch::Index(#WSDF_VARIANT_IDX) => {
+                        #set_byte_order
+
+                        match #WSDF_DISPATCH {
+                            wsdf::VariantDispatch::Index(#WSDF_VARIANT_IDX) if #WSDF_VARIANT_IDX < #nr_variants => {
                                 #handle_dispatch_idx
                             }
-                        };
-
-                        #WSDF_OFFSET
+                            #read_tag
+                            _ => {
+                                #invalid_dispatch
+                            }
+                        }
                     }
                 }
             }
@@ -165,7 +220,8 @@ impl DataRoot<'_> {
     pub(crate) fn registration_fn(&self) -> syn::ItemFn {
         let register_fields = match self {
             DataRoot::Struct { data, .. } => data.registration_instructions(self),
-            DataRoot::Enum { variants, .. } => {
+            DataRoot::Enum { variants, tag, .. } => {
+                let register_tag = tag.as_ref().map(|tag| self.register_tag(tag));
                 let instructions = variants
                     .iter()
                     .map(|variant| variant.data.registration_instructions(self));
@@ -173,7 +229,7 @@ impl DataRoot<'_> {
                     .iter()
                     .map(|variant| variant.data.ident().to_wsdf_snake_case());
 
-                instructions
+                let register_variants = instructions
                     .zip(variants_snake_cased)
                     .map(|(register_variant_fields, snake_cased)| {
                         quote! {
@@ -186,7 +242,12 @@ impl DataRoot<'_> {
                             }
                         }
                     })
-                    .streamify()
+                    .streamify();
+
+                quote! {
+                    #register_tag
+                    #register_variants
+                }
             }
         };
 
@@ -236,6 +297,29 @@ impl DataRoot<'_> {
         parse_quote! { <#ident as wsdf::ProtocolField>::proto_id() }
     }
 
+    /// For dissection. Overrides the byte order inherited from the parent, if this type has its
+    /// own.
+    fn set_byte_order(&self) -> Option<proc_macro2::TokenStream> {
+        let cfg = match self {
+            DataRoot::Struct { cfg, .. } | DataRoot::Enum { cfg, .. } => cfg,
+        };
+        let ws_enc = format_ws_enc(cfg.byte_order?.ws_enc());
+        Some(quote! { let #WSDF_BYTE_ORDER = #ws_enc; })
+    }
+
+    /// For dissection. Pads the end of this type, if it has an alignment.
+    fn trailing_padding(&self) -> Option<proc_macro2::TokenStream> {
+        let cfg = match self {
+            DataRoot::Struct { cfg, .. } | DataRoot::Enum { cfg, .. } => cfg,
+        };
+        let name = format!("padding of {}", self.ident().to_wsdf_title_case());
+        cfg.padding.skip_bytes(&name, self)
+    }
+
+    fn is_bits(&self) -> bool {
+        matches!(self, DataRoot::Struct { cfg, .. } if cfg.bits)
+    }
+
     fn is_unit_tuple(&self) -> bool {
         matches!(
             self,
@@ -255,6 +339,160 @@ impl DataRoot<'_> {
             Enum { .. } => false,
         }
     }
+
+    fn is_tagged(&self) -> bool {
+        matches!(self, DataRoot::Enum { tag: Some(_), .. })
+    }
+
+    /// For dissection. Builds the match arm which reads a tagged enum's tag, adds it to the tree,
+    /// and dissects the variant it picks. The tag is counted towards the bytes consumed.
+    fn read_tag(
+        &self,
+        tag: &EnumTag,
+        variants: &[EnumVariant],
+        handle_dispatch_idx: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let self_ident = self.ident();
+        let proto_id = self.proto_id();
+        let size = tag.typ.size_expr();
+        let ws_enc = WSDF_BYTE_ORDER;
+        let default_label = self_ident.to_wsdf_title_case();
+        let default_label_cstr: syn::Expr = cstr!(default_label);
+
+        let get_tag = match tag.typ {
+            PrimitiveType::U8 => quote! { wsdf::epan_sys::tvb_get_uint8(#WSDF_TVB, #WSDF_START) },
+            PrimitiveType::U16 => {
+                quote! { wsdf::epan_sys::tvb_get_uint16(#WSDF_TVB, #WSDF_START, #ws_enc) }
+            }
+            PrimitiveType::U32 => {
+                quote! { wsdf::epan_sys::tvb_get_uint32(#WSDF_TVB, #WSDF_START, #ws_enc) }
+            }
+            _ => quote! { wsdf::epan_sys::tvb_get_uint64(#WSDF_TVB, #WSDF_START, #ws_enc) },
+        };
+
+        let tag_arms = variants.iter().enumerate().filter_map(|(idx, variant)| {
+            variant
+                .tag_value
+                .map(|tag_value| quote! { #tag_value => #idx, })
+        });
+        let unknown_tag = match tag.fallback {
+            Some(idx) => quote! { #idx },
+            None => quote! {{
+                wsdf::expert::add_malformed(
+                    #WSDF_PINFO,
+                    #WSDF_PARENT_NODE,
+                    #WSDF_TVB,
+                    *#proto_id,
+                    #WSDF_START,
+                    &format!("unknown tag {}", #WSDF_TAG),
+                );
+                return std::result::Result::Err(#size);
+            }},
+        };
+
+        quote! {
+            wsdf::VariantDispatch::None => {
+                let #WSDF_FIELD_NAME = unsafe {
+                    std::ffi::CStr::from_ptr(#WSDF_SUBTREE_LABEL.unwrap_or(#default_label_cstr))
+                };
+                if !wsdf::expert::check_bounds(
+                    #WSDF_PINFO,
+                    #WSDF_PARENT_NODE,
+                    #WSDF_TVB,
+                    *#proto_id,
+                    #WSDF_TVB_BUF.len(),
+                    #WSDF_START,
+                    #size,
+                    &#WSDF_FIELD_NAME.to_string_lossy(),
+                ) {
+                    return std::result::Result::Err(0);
+                }
+
+                let #WSDF_HF = <#self_ident as wsdf::ProtocolField>::hf_map(
+                    wsdf::HfMapOp::Get(#WSDF_PREFIX),
+                ).unwrap_or_else(|| panic!("expected hf for {} to exist", #WSDF_PREFIX));
+                let #WSDF_TAG = unsafe {
+                    wsdf::epan_sys::proto_tree_add_item(
+                        #WSDF_PARENT_NODE,
+                        #WSDF_HF,
+                        #WSDF_TVB,
+                        #WSDF_START,
+                        #size,
+                        #ws_enc,
+                    );
+                    #get_tag as u64
+                };
+
+                let #WSDF_VARIANT_IDX = match #WSDF_TAG {
+                    #(#tag_arms)*
+                    _ => #unknown_tag,
+                };
+
+                let #WSDF_START = #WSDF_START + #size;
+                match { #handle_dispatch_idx } {
+                    std::result::Result::Ok(n) => std::result::Result::Ok(n + #size),
+                    std::result::Result::Err(n) => std::result::Result::Err(n + #size),
+                }
+            }
+        }
+    }
+
+    /// For registration. Registers the hf of a tagged enum's tag, under the enum's own prefix.
+    /// The variants' names are used as the tag's value strings.
+    fn register_tag(&self, tag: &EnumTag) -> proc_macro2::TokenStream {
+        let self_ident = self.ident();
+        let self_path: syn::Path = parse_quote! { #self_ident };
+        let create_hf = DataType::create_hf_impl(
+            true,
+            self_ident,
+            &self_ident.to_wsdf_title_case(),
+            &None,
+            &format_ws_type(tag.typ.default_ws_type()),
+            &FieldDisplayPair::new(("BASE_DEC", None)).to_expr(),
+            Some(HfStrings::ValueStrings(&self_path)),
+            0,
+        );
+        quote! {
+            let #WSDF_PREFIX_NEXT = #WSDF_PREFIX.to_owned();
+            #create_hf
+        }
+    }
+
+    /// For tagged enums only. Implements `wsdf::ValueStrings` for the enum, mapping each tag
+    /// value to the name of its variant.
+    pub(crate) fn tag_value_strings(
+        &self,
+        lifetimes: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let variants = match self {
+            DataRoot::Enum {
+                variants,
+                tag: Some(_),
+                ..
+            } => variants,
+            _ => return None,
+        };
+        let self_ident = self.ident();
+        let entries = variants.iter().filter_map(|variant| {
+            let tag_value = variant.tag_value?;
+            let name = variant
+                .data
+                .renamed()
+                .clone()
+                .unwrap_or_else(|| variant.data.ident().to_wsdf_title_case());
+            let name_cstr: syn::Expr = cstr!(name);
+            Some(quote! { (#tag_value, #tag_value, #name_cstr) })
+        });
+        Some(quote! {
+            impl wsdf::ValueStrings for #self_ident #lifetimes {
+                fn strings(
+                    ws_type: wsdf::epan_sys::ftenum,
+                ) -> (*const std::ffi::c_void, std::ffi::c_int) {
+                    wsdf::make_value_strings(&[#(#entries),*], ws_type)
+                }
+            }
+        })
+    }
 }
 
 impl<'a> DataRoot<'a> {
@@ -263,30 +501,107 @@ impl<'a> DataRoot<'a> {
         let cfg = DataRootConfig {
             pre_dissect: opts.pre_dissect,
             post_dissect: opts.post_dissect,
+            byte_order: opts.byte_order,
+            padding: Padding {
+                skip: None,
+                align: opts.align,
+                show: opts.show_padding.unwrap_or(false),
+            },
+            bits: opts.bits.unwrap_or(false),
         };
 
         match &input.data {
-            syn::Data::Struct(data) => Ok(DataRoot::Struct {
-                is_top_level,
-                data: DataTerminal::from_struct(&input.ident, data, &input.attrs)?,
-                cfg,
-            }),
+            syn::Data::Struct(_) | syn::Data::Union(_) if opts.tag.is_some() => {
+                make_err(&opts.tag, "only enums can have a tag")
+            }
+            syn::Data::Struct(data) => {
+                let data = DataTerminal::from_struct(&input.ident, data, opts.info)?;
+                data.check_bits(cfg.bits)?;
+                Ok(DataRoot::Struct {
+                    is_top_level,
+                    data,
+                    cfg,
+                })
+            }
 
             syn::Data::Enum(data) => {
                 if data.variants.is_empty() {
                     return make_err(&input.ident, "expected at least one variant");
                 }
+                if opts.info.is_some() {
+                    return make_err(
+                        &input.ident,
+                        "info must be given on the enum's variants instead",
+                    );
+                }
+                if cfg.bits {
+                    return make_err(&input.ident, "only structs can be marked bits");
+                }
+
+                let tag_typ = match &opts.tag {
+                    None => None,
+                    Some(ident) => match PrimitiveType::from_unsigned_ident(ident) {
+                        Some(typ) => Some(typ),
+                        None => {
+                            return make_err(ident, "expected tag to be an unsigned integer type")
+                        }
+                    },
+                };
 
                 let mut variants = Vec::new();
+                let mut fallback = None;
+                let mut tag_values = HashSet::new();
+
+                for (idx, variant) in data.variants.iter().enumerate() {
+                    let variant_opts = init_options::<VariantOptions>(&variant.attrs)?;
+                    let is_fallback = variant_opts.fallback.unwrap_or(false);
+                    let tag_value = variant_opts.tag_value;
+
+                    match &tag_typ {
+                        None if tag_value.is_some() || is_fallback => {
+                            return make_err(
+                                &variant.ident,
+                                "tag_value and fallback require a tag on the enum",
+                            );
+                        }
+                        None => (),
+                        Some(typ) => {
+                            match tag_value {
+                                None if !is_fallback => {
+                                    return make_err(
+                                        &variant.ident,
+                                        "expected a tag_value for this variant",
+                                    );
+                                }
+                                Some(value) if value > typ.unsigned_max() => {
+                                    return make_err(
+                                        &variant.ident,
+                                        "tag value does not fit in the tag's type",
+                                    );
+                                }
+                                Some(value) if !tag_values.insert(value) => {
+                                    return make_err(&variant.ident, "duplicate tag value");
+                                }
+                                _ => (),
+                            }
+                            if is_fallback && fallback.replace(idx).is_some() {
+                                return make_err(
+                                    &variant.ident,
+                                    "only one variant can be the fallback",
+                                );
+                            }
+                        }
+                    }
 
-                for variant in &data.variants {
-                    let data = DataTerminal::from_variant(variant)?;
-                    variants.push(EnumVariant { data });
+                    let data = DataTerminal::from_variant(variant, variant_opts)?;
+                    data.check_bits(false)?;
+                    variants.push(EnumVariant { data, tag_value });
                 }
 
                 Ok(DataRoot::Enum {
                     ident: &input.ident,
                     variants,
+                    tag: tag_typ.map(|typ| EnumTag { typ, fallback }),
                     cfg,
                 })
             }
@@ -299,8 +614,7 @@ impl<'a> DataRoot<'a> {
 }
 
 impl<'a> DataTerminal<'a> {
-    fn from_variant(variant: &'a syn::Variant) -> syn::Result<Self> {
-        let opts = init_options::<VariantOptions>(&variant.attrs)?;
+    fn from_variant(variant: &'a syn::Variant, opts: VariantOptions) -> syn::Result<Self> {
         match &variant.fields {
             syn::Fields::Named(data) => Self::from_named_fields(&variant.ident, &data.named, opts),
             syn::Fields::Unnamed(data) => {
@@ -315,15 +629,15 @@ impl<'a> DataTerminal<'a> {
     fn from_struct(
         ident: &'a syn::Ident,
         data: &'a syn::DataStruct,
-        _attrs: &'a [syn::Attribute],
+        info: Option<String>,
     ) -> syn::Result<Self> {
+        let opts = VariantOptions {
+            info,
+            ..Default::default()
+        };
         match &data.fields {
-            syn::Fields::Named(data) => {
-                Self::from_named_fields(ident, &data.named, VariantOptions::default())
-            }
-            syn::Fields::Unnamed(data) => {
-                Self::from_unit_tuple(ident, &data.unnamed, VariantOptions::default())
-            }
+            syn::Fields::Named(data) => Self::from_named_fields(ident, &data.named, opts),
+            syn::Fields::Unnamed(data) => Self::from_unit_tuple(ident, &data.unnamed, opts),
             syn::Fields::Unit => {
                 make_err(&data.fields, "expected struct to have at least one field")
             }
@@ -341,11 +655,15 @@ impl<'a> DataTerminal<'a> {
         }
 
         check_fields(&mut proto_fields)?;
+        if let Some(info) = &opts.info {
+            check_info(ident, info, &mut proto_fields)?;
+        }
 
         Ok(DataTerminal::DataClass {
             ident,
             rename: opts.rename,
             fields: proto_fields,
+            info: opts.info,
         })
     }
 
@@ -357,9 +675,15 @@ impl<'a> DataTerminal<'a> {
         if fields.len() != 1 {
             return make_err(fields, "expected exactly one element in tuple type");
         }
+        if opts.info.is_some() {
+            return make_err(ident, "info can only be used with named fields");
+        }
 
         let field = fields.last().unwrap(); // safe to unwrap
         let mut inner = [ProtoField::from_field_with_ident(field, ident)?];
+        if inner[0].checksum.is_some() {
+            return make_err(field, "checksum can only be used on named fields");
+        }
 
         check_fields(&mut inner)?;
 
@@ -377,6 +701,30 @@ impl<'a> DataTerminal<'a> {
         }
     }
 
+    /// Checks that either all of the fields, or none of them, are bit fields.
+    fn check_bits(&self, is_bits: bool) -> syn::Result<()> {
+        let fields = match self {
+            DataTerminal::UnitTuple { inner, .. } => std::slice::from_ref(inner),
+            DataTerminal::DataClass { fields, .. } => fields.as_slice(),
+        };
+        for field in fields {
+            let typ = &field.field.ty;
+            match (is_bits, field.typ.bits()) {
+                (true, None) => {
+                    return make_err(typ, "fields of a bits struct must have a bits attribute")
+                }
+                (true, Some(_)) if !field.padding.is_empty() => {
+                    return make_err(typ, "padding cannot be used in a bits struct")
+                }
+                (false, Some(_)) => {
+                    return make_err(typ, "bits can only be used in a struct marked bits")
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
     fn dissection_instructions(&self, parent: &DataRoot) -> proc_macro2::TokenStream {
         use DataTerminal::*;
 
@@ -401,13 +749,13 @@ impl<'a> DataTerminal<'a> {
                     #dissect_field
                 }
             }
-            DataClass { fields, .. } => {
+            DataClass { fields, info, .. } => {
                 let dissect_fields = fields
                     .iter()
                     .map(|field| field.dissection_instructions(parent));
                 let fields_snake_cased =
                     fields.iter().map(|field| field.ident.to_wsdf_snake_case());
-                dissect_fields
+                let mut instructions: Vec<_> = dissect_fields
                     .zip(fields_snake_cased)
                     .map(|(dissect_field, snake_cased)| {
                         // It is important that we do not enclose this code into its own block,
@@ -422,11 +770,44 @@ impl<'a> DataTerminal<'a> {
                             #dissect_field
                         }
                     })
-                    .streamify()
+                    .collect();
+                let mut extra: Vec<_> = fields
+                    .iter()
+                    .filter_map(|field| field.verify_checksum(fields, parent))
+                    .collect();
+                if let Some(info) = info {
+                    extra.push(Self::append_info(info, fields));
+                }
+                // Insert from the back, so that the positions of the rest stay the same.
+                extra.sort_by_key(|(pos, _)| std::cmp::Reverse(*pos));
+                for (pos, code) in extra {
+                    instructions.insert(pos, code);
+                }
+                instructions.into_iter().streamify()
             }
         }
     }
 
+    /// For dissection. Builds the code which appends to the Info column, and the position among
+    /// the fields' instructions to place it. The text is written as soon as the last field it
+    /// references has been dissected, so that the outer types come before the inner ones.
+    fn append_info(info: &str, fields: &[ProtoField]) -> (usize, proc_macro2::TokenStream) {
+        let names = format_arg_names(info).unwrap_or_default(); // already checked
+        let pos = fields
+            .iter()
+            .rposition(|field| names.contains(&field.ident.to_string()))
+            .map_or(0, |idx| idx + 1);
+        let args = names.iter().map(|name| {
+            let name_ident = format_ident!("{}", name);
+            let var_ident = format_ident!("{}", name_ident.to_wsdf_snake_case());
+            quote! { #name_ident = #var_ident }
+        });
+        let append_info = quote! {
+            wsdf::tap::Columns::new(#WSDF_PINFO).append_info(format!(#info, #(#args),*));
+        };
+        (pos, append_info)
+    }
+
     fn registration_instructions(&self, parent: &DataRoot) -> proc_macro2::TokenStream {
         use DataTerminal::*;
         match self {
@@ -457,6 +838,34 @@ impl<'a> DataTerminal<'a> {
     }
 }
 
+/// Checks that an Info column format string only references fields which can be displayed, and
+/// marks those fields to be emitted so that their values are available when formatting.
+fn check_info(ident: &syn::Ident, info: &str, fields: &mut [ProtoField]) -> syn::Result<()> {
+    let names = match format_arg_names(info) {
+        Ok(names) => names,
+        Err(e) => return make_err(ident, &format!("invalid info: {e}")),
+    };
+    for name in names {
+        let field = match fields.iter_mut().find(|field| *field.ident == name) {
+            Some(field) => field,
+            None => return make_err(ident, &format!("info references unknown field `{name}`")),
+        };
+        if field.presence.is_some() {
+            return make_err(field.field, "info cannot reference Option fields");
+        }
+        match field.typ.as_mut() {
+            DataType::Primitive(data) if data.typ.is_display() => data.is_used_later = true,
+            _ => {
+                return make_err(
+                    field.field,
+                    "info can only reference numbers, strings and addresses, not times, bytes or nested types",
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Checks and processes fields to see if there is any invalid option. Not exhaustive.
 ///
 /// We need a mutable slice because we may also initialize or adjust some attributes on the fields.
@@ -473,19 +882,9 @@ fn check_fields(fields: &mut [ProtoField]) -> syn::Result<()> {
 
         for field in fields {
             if len_providers.contains(field.ident) {
-                use PrimitiveType::*;
-
                 match field.typ.as_mut() {
-                    DataType::Primitive(data) => match data.typ {
-                        U8 | U16 | U32 | U64 | I8 | I16 | I32 | I64 => data.is_used_later = true,
-                        ByteArray { .. } => {
-                            return make_err(
-                                &field.field.ident,
-                                "this field cannot be used to indicate length",
-                            )
-                        }
-                    },
-                    DataType::Collection(_) => {
+                    DataType::Primitive(data) if data.typ.is_integer() => data.is_used_later = true,
+                    _ => {
                         return make_err(
                             &field.field.ident,
                             "this field cannot be used to indicate length",
@@ -498,6 +897,67 @@ fn check_fields(fields: &mut [ProtoField]) -> syn::Result<()> {
         Ok(())
     }
 
+    /// Checks that `len` expressions only reference prior integer fields.
+    fn check_len_exprs(fields: &mut [ProtoField]) -> syn::Result<()> {
+        for i in 0..fields.len() {
+            let exprs = [
+                ("len", fields[i].typ.get_len_expr()),
+                ("skip_expr", fields[i].padding.len_expr()),
+            ];
+            let names: Vec<(&str, syn::Ident)> = exprs
+                .into_iter()
+                .filter_map(|(attr, len)| Some((attr, len?)))
+                .flat_map(|(attr, len)| len.fields().into_iter().map(move |x| (attr, x.clone())))
+                .collect();
+            let (prior, rest) = fields.split_at_mut(i);
+            for (attr, name) in names {
+                let field = match prior.iter_mut().find(|field| *field.ident == name) {
+                    Some(field) => field,
+                    None => {
+                        return make_err(
+                            rest[0].field,
+                            &format!("{attr} references unknown field `{name}`"),
+                        )
+                    }
+                };
+                match field.typ.as_mut() {
+                    DataType::Primitive(data) if data.typ.is_integer() => data.is_used_later = true,
+                    _ => {
+                        return make_err(
+                            rest[0].field,
+                            &format!("{attr} can only reference integer fields"),
+                        )
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that all fields passed to `enc_fn`s are valid.
+    fn check_enc_providers(fields: &mut [ProtoField]) -> syn::Result<()> {
+        let enc_providers: HashSet<_> = fields
+            .iter()
+            .filter_map(|field| field.typ.get_enc_field().cloned())
+            .collect();
+
+        for field in fields {
+            if enc_providers.contains(field.ident) {
+                match field.typ.as_mut() {
+                    DataType::Primitive(data) => data.is_used_later = true,
+                    DataType::Collection(_) => {
+                        return make_err(
+                            &field.field.ident,
+                            "this field cannot be used to pick an encoding",
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn check_dispatch_providers(fields: &mut [ProtoField]) -> syn::Result<()> {
         let mut dispatch_providers = HashSet::new();
 
@@ -573,7 +1033,7 @@ fn check_fields(fields: &mut [ProtoField]) -> syn::Result<()> {
                             field_data.is_used_later = true;
 
                             match &field_data.typ {
-                            U8 | U16 | U32 | U64 | I8 | I16 | I32 | I64 => match target_typ {
+                            typ if typ.is_integer() => match target_typ {
                                 Uint { .. } => (),
                                 Str => {
                                     return make_err(
@@ -596,6 +1056,12 @@ fn check_fields(fields: &mut [ProtoField]) -> syn::Result<()> {
                                 Str => (),
                                 Unknown => *target_typ = Str,
                             },
+                            _ => {
+                                return make_err(
+                                    field.field,
+                                    "only integer and byte fields can be used to call subdissectors",
+                                )
+                            }
                         }
                         }
                     }
@@ -607,13 +1073,108 @@ fn check_fields(fields: &mut [ProtoField]) -> syn::Result<()> {
     }
 
     check_len_providers(fields)?;
+    check_len_exprs(fields)?;
     check_dispatch_providers(fields)?;
+    check_enc_providers(fields)?;
     check_subdissectors(fields)?;
+    check_presence(fields)?;
+    check_checksums(fields)?;
+
+    Ok(())
+}
+
+/// Resolves the range of fields covered by each checksum, and marks the fields where the ranges
+/// start and end so that their offsets are kept.
+fn check_checksums(fields: &mut [ProtoField]) -> syn::Result<()> {
+    for i in 0..fields.len() {
+        let over = match &fields[i].checksum {
+            None => continue,
+            Some(checksum) => checksum.over.clone(),
+        };
+        if fields[i].presence.is_some() {
+            return make_err(fields[i].field, "checksum fields cannot be Option fields");
+        }
+        let (first, last) = match over {
+            Some(over) => over,
+            None if i == 0 => {
+                return make_err(
+                    fields[i].field,
+                    "expected over, since no fields come before the checksum",
+                )
+            }
+            None => (fields[0].ident.clone(), fields[i - 1].ident.clone()),
+        };
+
+        let position =
+            |ident: &syn::Ident| match fields.iter().position(|field| field.ident == ident) {
+                None => make_err(ident, &format!("over references unknown field `{ident}`")),
+                Some(pos) if fields[pos].presence.is_some() => make_err(
+                    ident,
+                    "a checksum's range cannot start or end at an Option field",
+                ),
+                Some(pos) => Ok(pos),
+            };
+        let start = position(&first)?;
+        let end = position(&last)?;
+        if start > end {
+            return make_err(
+                &first,
+                "expected the first field of over to come before the last",
+            );
+        }
+
+        fields[start].starts_checksum = true;
+        fields[end].ends_checksum = true;
+        if let Some(checksum) = &mut fields[i].checksum {
+            checksum.over = Some((first, last));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the prior fields referenced by `if` expressions, and marks them to be emitted so that
+/// their values are available. Since an optional field may not exist, nothing else may depend on
+/// its value.
+fn check_presence(fields: &mut [ProtoField]) -> syn::Result<()> {
+    for i in 0..fields.len() {
+        let (prior, rest) = fields.split_at_mut(i);
+        let current = &mut rest[0];
+        let (expr, referenced) = match &mut current.presence {
+            Some(Presence::If { expr, fields }) => (expr, fields),
+            _ => continue,
+        };
+        let mut error = None;
+        map_field_names(expr.to_token_stream(), &mut |ident| {
+            if let Some(field) = prior.iter_mut().find(|field| field.ident == ident) {
+                match field.typ.as_mut() {
+                    DataType::Primitive(data) => data.is_used_later = true,
+                    DataType::Collection(_) => error = Some(field.field),
+                }
+                referenced.push(ident.clone());
+            }
+            None
+        });
+        if let Some(field) = error {
+            return make_err(field, "this field cannot be used in an if condition");
+        }
+    }
+
+    for field in fields {
+        if let (Some(_), DataType::Primitive(data)) = (&field.presence, field.typ.as_ref()) {
+            if data.is_used_later {
+                return make_err(field.field, "Option fields cannot be used by other fields");
+            }
+        }
+    }
 
     Ok(())
 }
 
 impl DataTerminal<'_> {
+    fn is_empty(&self) -> bool {
+        matches!(self, DataTerminal::DataClass { fields, .. } if fields.is_empty())
+    }
+
     fn renamed(&self) -> &Option<String> {
         match self {
             DataTerminal::UnitTuple { rename, .. } | DataTerminal::DataClass { rename, .. } => {
@@ -729,9 +1290,30 @@ impl DataTerminal<'_> {
         let pre_dissect = Self::call_hooks(pre_dissect);
         let post_dissect = Self::call_hooks(post_dissect);
 
-        let update_parent = self.update_parent_node(root).streamify();
+        // The tag already tells which variant was picked, so variants of tagged enums without
+        // any fields do not get an (empty) subtree.
+        let has_subtree = !(root.is_tagged() && self.is_empty());
 
+        let update_parent = self
+            .update_parent_node(root)
+            .filter(|_| has_subtree)
+            .streamify();
+
+        let set_byte_order = root.set_byte_order();
         let dissect_stuff = self.dissection_instructions(root); // the actual code which dissects stuff
+        let trailing_padding = root.trailing_padding();
+
+        // The fields of a bits struct move a bit cursor along. Once they are done, the offset is
+        // rounded up to the next whole byte.
+        let (init_bit_offset, rejoin_bytes) = root
+            .is_bits()
+            .then(|| {
+                (
+                    quote! { let mut #WSDF_BIT_OFFSET: usize = 0; },
+                    quote! { #WSDF_OFFSET = #WSDF_BIT_OFFSET.div_ceil(8) as std::ffi::c_int; },
+                )
+            })
+            .unzip();
 
         // Recall that we create new subtrees with size -1, because we may or may not know the
         // size of all its fields. After dissecting all the fields, the WSDF_OFFSET variable
@@ -739,27 +1321,42 @@ impl DataTerminal<'_> {
         //
         // Wireshark exposes the `proto_item_set_len` function for manually configuring the size
         // of a proto_item.
-        let update_subtree_size = quote! {
-            unsafe {
-                wsdf::epan_sys::proto_item_set_len(#WSDF_PARENT_NODE, #WSDF_OFFSET);
+        let update_subtree_size = has_subtree.then(|| {
+            quote! {
+                unsafe {
+                    wsdf::epan_sys::proto_item_set_len(#WSDF_PARENT_NODE, #WSDF_OFFSET);
+                }
             }
-        };
+        });
 
+        // The fields are dissected inside a labeled block, which we break out of as soon as the
+        // packet turns out to be malformed. Whatever has been dissected so far stays in the tree.
         parse_quote! {
-            #[allow(clippy::too_many_arguments, clippy::ptr_arg, clippy::int_plus_one)]
-            fn #fn_ident<'a>(#DISSECTION_PARAMS) -> std::ffi::c_int {
+            #[allow(clippy::too_many_arguments, clippy::ptr_arg, clippy::int_plus_one, unused_labels)]
+            fn #fn_ident<'a>(#DISSECTION_PARAMS) -> std::result::Result<std::ffi::c_int, std::ffi::c_int> {
+                #set_byte_order
                 #update_parent
                 let mut #WSDF_OFFSET = 0;
+                #init_bit_offset
 
                 #pre_dissect
 
-                #dissect_stuff
+                let #WSDF_IS_OK = #WSDF_DISSECT_BLOCK: {
+                    #dissect_stuff
+                    #rejoin_bytes
+                    #trailing_padding
+                    true
+                };
 
                 #update_subtree_size
 
+                if !#WSDF_IS_OK {
+                    return std::result::Result::Err(#WSDF_OFFSET);
+                }
+
                 #post_dissect
 
-                #WSDF_OFFSET // return the no. of bytes consumed
+                std::result::Result::Ok(#WSDF_OFFSET) // return the no. of bytes consumed
             }
         }
     }
@@ -773,6 +1370,286 @@ pub(crate) struct ProtoField<'a> {
 
     field: &'a syn::Field,
     typ: Box<DataType>,
+    /// For `Option` fields only. Decides whether the field is dissected at all.
+    presence: Option<Presence>,
+    /// Bytes skipped before the field.
+    padding: Padding,
+    /// The checksum which this field holds, if any.
+    checksum: Option<Checksum>,
+    /// Whether a checksum's range starts at this field.
+    starts_checksum: bool,
+    /// Whether a checksum's range ends at this field.
+    ends_checksum: bool,
+}
+
+/// A checksum over a range of fields in the same type. It is verified, and the field holding it is
+/// added to the tree, once both the range and the field have been dissected.
+#[derive(Debug, Clone)]
+struct Checksum {
+    compute: ComputeChecksum,
+    /// The first and last fields covered. If not given, this defaults to all of the fields before
+    /// the checksum when the fields are checked.
+    over: Option<(syn::Ident, syn::Ident)>,
+}
+
+#[derive(Debug, Clone)]
+enum ComputeChecksum {
+    /// One of the functions in `wsdf::checksum`.
+    Builtin(syn::Ident),
+    /// A function which takes the same parameters as a tap.
+    Fn(syn::Path),
+}
+
+impl Checksum {
+    fn new(opts: &FieldOptions) -> Option<Self> {
+        let compute = match (&opts.checksum, &opts.checksum_fn) {
+            (Some(name), _) => ComputeChecksum::Builtin(format_ident!("{}", name)),
+            (None, Some(path)) => ComputeChecksum::Fn(path.clone()),
+            (None, None) => return None,
+        };
+        Some(Self {
+            compute,
+            over: opts.over.clone(),
+        })
+    }
+}
+
+/// The name of a variable which holds an offset used by a checksum, e.g. where its range starts.
+fn checksum_var(kind: &str, ident: &syn::Ident) -> syn::Ident {
+    format_ident!("__wsdf_checksum_{}_{}", kind, ident.to_wsdf_snake_case())
+}
+
+/// Bytes which are skipped over, either before a field or at the end of a type.
+#[derive(Debug, Clone)]
+struct Padding {
+    skip: Option<Skip>,
+    /// Skips ahead to a multiple of this many bytes, counted from the start of the enclosing type,
+    /// e.g. a record in a list. This happens after `skip`.
+    align: Option<usize>,
+    /// Whether the skipped bytes are shown in the tree.
+    show: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Skip {
+    Static(usize),
+    Expr(LenExpr),
+}
+
+impl Padding {
+    fn new(field: &syn::Field, opts: &FieldOptions) -> syn::Result<Self> {
+        let skip = match (opts.skip, &opts.skip_expr) {
+            (Some(_), Some(_)) => {
+                return make_err(&field.ty, "skip and skip_expr cannot be combined")
+            }
+            (Some(n), None) => Some(Skip::Static(n)),
+            (None, Some(expr)) => Some(Skip::Expr(LenExpr::new(expr)?)),
+            (None, None) => None,
+        };
+        Ok(Self {
+            skip,
+            align: opts.align,
+            show: opts.show_padding.unwrap_or(false),
+        })
+    }
+
+    fn len_expr(&self) -> Option<&LenExpr> {
+        match &self.skip {
+            Some(Skip::Expr(len)) => Some(len),
+            _ => None,
+        }
+    }
+
+    /// Whether there is no padding at all.
+    fn is_empty(&self) -> bool {
+        self.skip.is_none() && self.align.is_none()
+    }
+
+    /// For dissection. Builds the code which moves past the padding. `name` describes the padding
+    /// in expert items.
+    fn skip_bytes(&self, name: &str, root: &DataRoot) -> Option<proc_macro2::TokenStream> {
+        if self.is_empty() {
+            return None;
+        }
+        let proto_id = root.proto_id();
+        let pos: syn::Expr = parse_quote! { (#WSDF_START + #WSDF_OFFSET) as usize };
+
+        let skip_padding = |padding: proc_macro2::TokenStream| {
+            let show = self.show.then(|| {
+                quote! {
+                    wsdf::expert::add_padding(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *#proto_id,
+                        #WSDF_START + #WSDF_OFFSET,
+                        &#WSDF_TVB_BUF[#pos..#pos + #WSDF_PADDING],
+                    );
+                }
+            });
+            quote! {
+                let #WSDF_PADDING: usize = #padding;
+                if !wsdf::expert::check_bounds(
+                    #WSDF_PINFO,
+                    #WSDF_PARENT_NODE,
+                    #WSDF_TVB,
+                    *#proto_id,
+                    #WSDF_TVB_BUF.len(),
+                    #WSDF_START + #WSDF_OFFSET,
+                    #WSDF_PADDING,
+                    #name,
+                ) {
+                    break #WSDF_DISSECT_BLOCK false;
+                }
+                #show
+                #WSDF_OFFSET += #WSDF_PADDING as std::ffi::c_int;
+            }
+        };
+
+        let skip = self.skip.as_ref().map(|skip| match skip {
+            Skip::Static(n) => skip_padding(quote! { #n }),
+            Skip::Expr(len) => {
+                let len = len.checked();
+                let msg = format!("{name} is out of range");
+                skip_padding(quote! {
+                    match #len.and_then(|n| usize::try_from(n).ok()) {
+                        Some(n) => n,
+                        None => {
+                            wsdf::expert::add_malformed(
+                                #WSDF_PINFO,
+                                #WSDF_PARENT_NODE,
+                                #WSDF_TVB,
+                                *#proto_id,
+                                #WSDF_START + #WSDF_OFFSET,
+                                #msg,
+                            );
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
+                    }
+                })
+            }
+        });
+        // WSDF_OFFSET counts from the start of the type being dissected, rather than the packet.
+        let align = self
+            .align
+            .map(|n| skip_padding(quote! { (#n - #WSDF_OFFSET as usize % #n) % #n }));
+
+        Some(quote! {
+            #skip
+            #align
+        })
+    }
+}
+
+/// How to tell whether an optional field is present.
+#[derive(Debug, Clone)]
+enum Presence {
+    /// An expression over prior fields, e.g. `flags & 0x80 != 0`. The prior fields it references
+    /// are filled in when the fields are checked.
+    If {
+        expr: Box<syn::Expr>,
+        fields: Vec<syn::Ident>,
+    },
+    /// A function which takes the same parameters as a tap, and returns a bool.
+    Fn(syn::Path),
+}
+
+impl Presence {
+    /// Unwraps the `T` out of an `Option<T>` field, along with the condition for it to be
+    /// present. Other types are returned as is.
+    fn extract<'a>(
+        field: &'a syn::Field,
+        opts: &FieldOptions,
+    ) -> syn::Result<(&'a syn::Type, Option<Self>)> {
+        let segment = match &field.ty {
+            syn::Type::Path(path) => path.path.segments.last(),
+            _ => None,
+        };
+        let inner_type = match segment {
+            Some(segment) if segment.ident == "Option" => generic_arg(segment)?,
+            _ => {
+                if opts.if_expr.is_some() || opts.present_if.is_some() {
+                    return make_err(
+                        &field.ty,
+                        "if and present_if can only be used on Option fields",
+                    );
+                }
+                return Ok((&field.ty, None));
+            }
+        };
+        let presence = match (&opts.if_expr, &opts.present_if) {
+            (Some(expr), None) => Presence::If {
+                expr: Box::new(expr.clone()),
+                fields: Vec::new(),
+            },
+            (None, Some(path)) => Presence::Fn(path.clone()),
+            (Some(_), Some(_)) => {
+                return make_err(&field.ty, "if and present_if cannot be combined");
+            }
+            (None, None) => {
+                return make_err(
+                    &field.ty,
+                    "Option fields require an if or present_if attribute",
+                );
+            }
+        };
+        Ok((inner_type, Some(presence)))
+    }
+
+    /// For dissection. Builds the code which decides whether the field is present.
+    fn condition(&self) -> proc_macro2::TokenStream {
+        match self {
+            Presence::If { expr, fields } => {
+                let expr = map_field_names(expr.to_token_stream(), &mut |ident| {
+                    fields
+                        .contains(ident)
+                        .then(|| format_ident!("{}", ident.to_wsdf_snake_case()))
+                });
+                quote! {
+                    let #WSDF_PRESENT: bool = #expr;
+                }
+            }
+            Presence::Fn(present_if) => {
+                let create_ctx = DataType::create_ctx_with_field(&parse_quote! { () });
+                quote! {
+                    let #WSDF_PRESENT = {
+                        #create_ctx
+                        wsdf::tap::handle_present_if(&#WSDF_TAP_CTX, #present_if)
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Walks the identifiers in some tokens which could name a field, i.e. those which do not follow
+/// a `.` or `::`, and replaces them by the result of `f` if there is one.
+fn map_field_names(
+    tokens: proc_macro2::TokenStream,
+    f: &mut impl FnMut(&syn::Ident) -> Option<syn::Ident>,
+) -> proc_macro2::TokenStream {
+    use proc_macro2::{Group, TokenTree};
+
+    let mut mapped = proc_macro2::TokenStream::new();
+    let mut after_punct = false;
+    for tt in tokens {
+        let tt = match tt {
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), map_field_names(group.stream(), f));
+                new_group.set_span(group.span());
+                TokenTree::Group(new_group)
+            }
+            TokenTree::Ident(ident) if !after_punct => match f(&ident) {
+                Some(new_ident) => TokenTree::Ident(new_ident),
+                None => TokenTree::Ident(ident),
+            },
+            tt => tt,
+        };
+        after_punct = matches!(&tt, TokenTree::Punct(p) if matches!(p.as_char(), '.' | ':'));
+        mapped.extend([tt]);
+    }
+    mapped
 }
 
 impl<'a> ProtoField<'a> {
@@ -789,12 +1666,18 @@ impl<'a> ProtoField<'a> {
     /// is required.
     fn from_field_with_ident(field: &'a syn::Field, ident: &'a syn::Ident) -> syn::Result<Self> {
         let options = init_options::<FieldOptions>(&field.attrs)?;
-        let typ = DataType::from_syn_type(&field.ty, &options)?;
+        let (typ, presence) = Presence::extract(field, &options)?;
+        let typ = DataType::from_syn_type(typ, &options)?;
         Ok(Self {
             ident,
-            rename: options.rename,
+            rename: options.rename.clone(),
             field,
             typ: Box::new(typ),
+            presence,
+            padding: Padding::new(field, &options)?,
+            checksum: Checksum::new(&options),
+            starts_checksum: false,
+            ends_checksum: false,
         })
     }
 }
@@ -803,22 +1686,68 @@ impl ProtoField<'_> {
     fn dissection_instructions(&self, root: &DataRoot) -> proc_macro2::TokenStream {
         let field_ident = format_ident!("{}", self.ident.to_wsdf_snake_case());
 
+        let skip_padding = self
+            .padding
+            .skip_bytes(&format!("padding before {}", self.name()), root);
         let retrieve_hf = self.typ.retrieve_hf(root.ident());
-        let emit_expr = self.typ.emit_field(&field_ident);
+        let compute_len = self.typ.compute_len(&self.name(), root.ident());
+        let check_bounds = self.typ.check_bounds(&self.name(), root.ident());
+        let emit_expr = self
+            .typ
+            .emit_field(&field_ident, &self.name(), root.ident());
         let add_to_fields_store = self.typ.add_to_fields_store(&field_ident);
         let create_ctx = self.typ.create_ctx(&field_ident);
         let call_taps = self.typ.call_taps().streamify();
-        let add_to_tree = self
-            .typ
-            .add_to_tree(&field_ident, &self.name(), root.ident());
+        let add_to_tree = match &self.checksum {
+            None => self
+                .typ
+                .add_to_tree(&field_ident, &self.name(), root.ident()),
+            // The checksum is added to the tree once it has been verified, so for now we only
+            // note where it is.
+            Some(_) => {
+                let at = checksum_var("at", self.ident);
+                let size = self.typ.primitive().map(|data| data.typ.size_expr());
+                quote! {
+                    let #at = #WSDF_START + #WSDF_OFFSET;
+                    #WSDF_OFFSET += #size as std::ffi::c_int;
+                }
+            }
+        };
+        let mark_start = self.starts_checksum.then(|| {
+            let start = checksum_var("start", self.ident);
+            quote! { let #start = (#WSDF_START + #WSDF_OFFSET) as usize; }
+        });
+        let mark_end = self.ends_checksum.then(|| {
+            let end = checksum_var("end", self.ident);
+            quote! { let #end = (#WSDF_START + #WSDF_OFFSET) as usize; }
+        });
 
-        quote! {
+        let instructions = quote! {
+            #skip_padding
+            #mark_start
             #retrieve_hf
+            #compute_len
+            #check_bounds
             #emit_expr
             #add_to_fields_store
             #create_ctx
             #call_taps
             #add_to_tree
+            #mark_end
+        };
+
+        // An absent field is skipped entirely, so it takes up no bytes and shows up nowhere.
+        match &self.presence {
+            None => instructions,
+            Some(presence) => {
+                let condition = presence.condition();
+                quote! {
+                    #condition
+                    if #WSDF_PRESENT {
+                        #instructions
+                    }
+                }
+            }
         }
     }
 
@@ -835,14 +1764,116 @@ impl ProtoField<'_> {
             .typ
             .create_hf(root.is_unit_tuple(), root.ident(), &field_name, &docs);
         let reg_subdissector = self.typ.register_subdissector(root.ident());
+        let create_status_hf = self.checksum.as_ref().map(|_| {
+            let create_hf = DataType::create_hf_impl(
+                false,
+                root.ident(),
+                &format!("{field_name} Status"),
+                &None,
+                &format_ws_type("FT_UINT8"),
+                &FieldDisplayPair::new(("BASE_NONE", None)).to_expr(),
+                Some(HfStrings::ChecksumStatus),
+                0,
+            );
+            quote! {
+                {
+                    let #WSDF_PREFIX_NEXT = #WSDF_PREFIX_NEXT.clone() + ".status";
+                    #create_hf
+                }
+            }
+        });
 
         quote! {
             #call_subroutine
+            #create_status_hf
             #create_hf
             #reg_subdissector
         }
     }
 
+    /// For dissection. Builds the code which computes this field's checksum and adds the field to
+    /// the tree along with the result, and the position among the fields' instructions to place
+    /// it, i.e. after both the field and the range it covers.
+    fn verify_checksum(
+        &self,
+        fields: &[ProtoField],
+        root: &DataRoot,
+    ) -> Option<(usize, proc_macro2::TokenStream)> {
+        let checksum = self.checksum.as_ref()?;
+        let (first, last) = checksum.over.as_ref()?; // resolved when the fields were checked
+        let position = |ident: &syn::Ident| fields.iter().position(|field| field.ident == ident);
+        let (idx, start_idx, end_idx) = (position(self.ident)?, position(first)?, position(last)?);
+
+        let start = checksum_var("start", first);
+        let end = checksum_var("end", last);
+        let at = checksum_var("at", self.ident);
+        let compute = match &checksum.compute {
+            ComputeChecksum::Builtin(name) => {
+                quote! { Some(wsdf::checksum::#name(#WSDF_CHECKSUM_DATA)) }
+            }
+            ComputeChecksum::Fn(path) => quote! {
+                wsdf::tap::handle_checksum_fn(
+                    &wsdf::tap::Context {
+                        field: #WSDF_CHECKSUM_DATA,
+                        fields: #WSDF_FIELDS_STORE,
+                        pinfo: #WSDF_PINFO,
+                        packet: #WSDF_TVB_BUF,
+                        offset: #start,
+                    },
+                    #path,
+                )
+            },
+        };
+        // An Internet checksum summed over itself is zero, which is how Wireshark checks it.
+        let in_cksum = matches!(&checksum.compute, ComputeChecksum::Builtin(name) if name == "internet")
+            && (start_idx..=end_idx).contains(&idx);
+        let verify_flags = match in_cksum {
+            true => {
+                quote! { wsdf::epan_sys::PROTO_CHECKSUM_VERIFY | wsdf::epan_sys::PROTO_CHECKSUM_IN_CKSUM }
+            }
+            false => quote! { wsdf::epan_sys::PROTO_CHECKSUM_VERIFY },
+        };
+
+        let root_ident = root.ident();
+        let proto_id = root.proto_id();
+        let snake_cased = self.ident.to_wsdf_snake_case();
+        let ws_enc = self.typ.primitive()?.ws_enc();
+        let get_hf = |suffix: &str| {
+            quote! {
+                <#root_ident as wsdf::ProtocolField>::hf_map(wsdf::HfMapOp::Get(&(#WSDF_CHECKSUM_PREFIX.clone() + #suffix)))
+                    .unwrap_or_else(|| panic!("expected hf for {}{} to exist", #WSDF_CHECKSUM_PREFIX, #suffix))
+            }
+        };
+        let (hf, hf_status) = (get_hf(""), get_hf(".status"));
+
+        // A checksum which the user's function could not compute is shown as unverified.
+        let verify = quote! {
+            {
+                let #WSDF_CHECKSUM_DATA = &#WSDF_TVB_BUF[#start..#end];
+                let #WSDF_CHECKSUM: Option<u32> = #compute;
+                let #WSDF_CHECKSUM_PREFIX = #WSDF_PREFIX.to_owned() + "." + #snake_cased;
+                unsafe {
+                    wsdf::epan_sys::proto_tree_add_checksum(
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        #at as u32,
+                        #hf,
+                        #hf_status,
+                        wsdf::expert::bad_checksum(*#proto_id),
+                        #WSDF_PINFO,
+                        #WSDF_CHECKSUM.unwrap_or(0),
+                        #ws_enc,
+                        match #WSDF_CHECKSUM {
+                            Some(_) => #verify_flags,
+                            None => wsdf::epan_sys::PROTO_CHECKSUM_NO_FLAGS,
+                        },
+                    );
+                }
+            }
+        };
+        Some((idx.max(end_idx) + 1, verify))
+    }
+
     fn name(&self) -> String {
         self.rename
             .clone()
@@ -854,3 +1885,12 @@ const WSDF_VARIANT_NAMES: IdentHelper = IdentHelper("__WSDF_VARIANT_NAMES");
 const WSDF_VARIANT_DISSECT_FNS: IdentHelper = IdentHelper("__WSDF_VARIANT_DISSECT_FNS");
 const WSDF_VARIANT_SUBTREE_LABELS: IdentHelper = IdentHelper("__WSDF_VARIANT_SUBTREE_LABELS");
 const WSDF_VARIANT_IDX: IdentHelper = IdentHelper("__wsdf_variant_idx");
+const WSDF_IS_OK: IdentHelper = IdentHelper("__wsdf_is_ok");
+const WSDF_TAG: IdentHelper = IdentHelper("__wsdf_tag");
+const WSDF_HF: IdentHelper = IdentHelper("__wsdf_hf");
+const WSDF_FIELD_NAME: IdentHelper = IdentHelper("__wsdf_field_name");
+const WSDF_PRESENT: IdentHelper = IdentHelper("__wsdf_present");
+const WSDF_PADDING: IdentHelper = IdentHelper("__wsdf_padding");
+const WSDF_CHECKSUM: IdentHelper = IdentHelper("__wsdf_checksum");
+const WSDF_CHECKSUM_DATA: IdentHelper = IdentHelper("__wsdf_checksum_data");
+const WSDF_CHECKSUM_PREFIX: IdentHelper = IdentHelper("__wsdf_checksum_prefix");
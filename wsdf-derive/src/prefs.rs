@@ -0,0 +1,450 @@
+use quote::{format_ident, quote};
+
+use crate::attributes::*;
+use crate::util::*;
+
+/// A struct which derives Preferences. Each field is an option in the protocol's section of
+/// Wireshark's preferences dialog.
+///
+/// ```ignore
+/// #[derive(Preferences)]
+/// struct MyPrefs {
+///     /// Show prices as decimals rather than fixed point integers.
+///     decimal_prices: bool,
+///     #[wsdf(default = "9000-9010", decode_from = "udp.port")]
+///     ports: wsdf::PrefRange,
+/// }
+/// ```
+#[derive(Debug)]
+pub(crate) struct PrefsStruct<'a> {
+    ident: &'a syn::Ident,
+    prefs: Vec<Pref<'a>>,
+}
+
+#[derive(Debug)]
+struct Pref<'a> {
+    ident: &'a syn::Ident,
+    ty: &'a syn::Type,
+    kind: PrefKind,
+    docs: Option<String>,
+    opts: PreferenceOptions,
+}
+
+/// The kind of preference a field is registered as, which is picked from its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrefKind {
+    Bool,
+    Uint,
+    Str,
+    Range,
+    /// Any other type, which must implement `wsdf::PrefEnum`.
+    Enum,
+}
+
+impl PrefKind {
+    fn from_type(ty: &syn::Type) -> syn::Result<Self> {
+        let ident = match ty {
+            syn::Type::Path(path) if path.qself.is_none() => match path.path.segments.last() {
+                Some(segment) => &segment.ident,
+                None => return make_err(ty, "expected a path"),
+            },
+            _ => {
+                return make_err(
+                    ty,
+                    "expected bool, u32, String, wsdf::PrefRange or an enum implementing wsdf::PrefEnum",
+                )
+            }
+        };
+        let kind = match ident.to_string().as_str() {
+            "bool" => PrefKind::Bool,
+            "u32" => PrefKind::Uint,
+            "String" => PrefKind::Str,
+            "PrefRange" => PrefKind::Range,
+            _ => PrefKind::Enum,
+        };
+        Ok(kind)
+    }
+}
+
+/// The default range preference allows any port number.
+const DEFAULT_RANGE_MAX: u32 = 65535;
+
+const WSDF_PREFS_MODULE: IdentHelper = IdentHelper("__wsdf_prefs_module");
+const WSDF_DISSECTOR_HANDLE: IdentHelper = IdentHelper("__wsdf_dissector_handle");
+
+impl<'a> PrefsStruct<'a> {
+    pub(crate) fn from_input(input: &'a syn::DeriveInput) -> syn::Result<Self> {
+        let fields = match &input.data {
+            syn::Data::Struct(syn::DataStruct {
+                fields: syn::Fields::Named(fields),
+                ..
+            }) => &fields.named,
+            _ => {
+                return make_err(
+                    &input.ident,
+                    "only structs with named fields can derive Preferences",
+                )
+            }
+        };
+
+        let mut prefs = Vec::with_capacity(fields.len());
+        for field in fields {
+            // Safe to unwrap, since these are named fields.
+            let ident = field.ident.as_ref().unwrap();
+            let pref = Pref {
+                ident,
+                ty: &field.ty,
+                kind: PrefKind::from_type(&field.ty)?,
+                docs: field.attrs.iter().find_map(get_docs),
+                opts: init_options::<PreferenceOptions>(&field.attrs)?,
+            };
+            pref.check()?;
+            prefs.push(pref);
+        }
+
+        Ok(Self {
+            ident: &input.ident,
+            prefs,
+        })
+    }
+
+    pub(crate) fn impl_preferences(&self) -> proc_macro2::TokenStream {
+        let self_ident = self.ident;
+        let statics = self.prefs.iter().map(Pref::statics);
+        let register = self.prefs.iter().map(Pref::register);
+        let bind = self.prefs.iter().map(Pref::bind);
+        let current = self.prefs.iter().map(Pref::current);
+        let binds_table = self
+            .prefs
+            .iter()
+            .any(|pref| pref.opts.decode_from.is_some());
+
+        quote! {
+            const _: () = {
+                #(#statics)*
+
+                impl wsdf::Preferences for #self_ident {
+                    const BINDS_TABLE: bool = #binds_table;
+
+                    fn register(#WSDF_PREFS_MODULE: *mut wsdf::epan_sys::module_t) {
+                        unsafe {
+                            #(#register)*
+                        }
+                    }
+
+                    fn bind(#WSDF_DISSECTOR_HANDLE: wsdf::epan_sys::dissector_handle_t) {
+                        unsafe {
+                            #(#bind)*
+                        }
+                    }
+
+                    fn current() -> Self {
+                        unsafe {
+                            Self {
+                                #(#current),*
+                            }
+                        }
+                    }
+                }
+            };
+        }
+    }
+}
+
+impl Pref<'_> {
+    fn check(&self) -> syn::Result<()> {
+        if self.kind != PrefKind::Range
+            && (self.opts.decode_from.is_some() || self.opts.max.is_some())
+        {
+            return make_err(
+                self.ident,
+                "decode_from and max can only be used on wsdf::PrefRange preferences",
+            );
+        }
+        if self.kind == PrefKind::Enum && self.opts.default.is_none() {
+            return make_err(self.ident, "enum preferences must have a default");
+        }
+        // Parse the default now, so that a literal of the wrong type is reported here.
+        self.default_value().map(|_| ())
+    }
+
+    /// The Wireshark variable backing the preference.
+    fn var(&self) -> syn::Ident {
+        format_ident!("__WSDF_PREF_{}", self.ident.to_string().to_uppercase())
+    }
+
+    /// For range preferences with a dissector table only. The copy of the range which the
+    /// protocol is currently bound to.
+    fn bound_var(&self) -> syn::Ident {
+        format_ident!("__WSDF_BOUND_{}", self.ident.to_string().to_uppercase())
+    }
+
+    fn default_value(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let default = match &self.opts.default {
+            Some(default) => default,
+            None => {
+                return Ok(match self.kind {
+                    PrefKind::Bool => quote!(false),
+                    PrefKind::Uint => quote!(0u32),
+                    PrefKind::Str | PrefKind::Range => quote!(""),
+                    // Rejected by check().
+                    PrefKind::Enum => unreachable!("enum preferences must have a default"),
+                });
+            }
+        };
+        let ret = match self.kind {
+            PrefKind::Bool => {
+                let lit = get_lit_bool(default)?;
+                quote!(#lit)
+            }
+            PrefKind::Uint => {
+                let value: u32 = get_lit_int(default)?.base10_parse()?;
+                quote!(#value)
+            }
+            PrefKind::Str | PrefKind::Range => {
+                let lit = get_lit_str(default)?;
+                quote!(#lit)
+            }
+            PrefKind::Enum => {
+                let variant = get_lit_str(default)?.parse::<syn::Expr>()?;
+                quote!(#variant)
+            }
+        };
+        Ok(ret)
+    }
+
+    fn statics(&self) -> proc_macro2::TokenStream {
+        let var = self.var();
+        let var_decl = match self.kind {
+            PrefKind::Bool => quote! {
+                static mut #var: wsdf::epan_sys::gboolean = 0;
+            },
+            PrefKind::Uint => quote! {
+                static mut #var: std::ffi::c_uint = 0;
+            },
+            PrefKind::Str => quote! {
+                static mut #var: *const std::ffi::c_char = std::ptr::null();
+            },
+            PrefKind::Range => quote! {
+                static mut #var: *mut wsdf::epan_sys::range_t = std::ptr::null_mut();
+            },
+            PrefKind::Enum => quote! {
+                static mut #var: std::ffi::c_int = 0;
+            },
+        };
+        let bound_decl = self.opts.decode_from.as_ref().map(|_| {
+            let bound_var = self.bound_var();
+            quote! {
+                static mut #bound_var: *mut wsdf::epan_sys::range_t = std::ptr::null_mut();
+            }
+        });
+        quote! {
+            #var_decl
+            #bound_decl
+        }
+    }
+
+    fn register(&self) -> proc_macro2::TokenStream {
+        let var = self.var();
+        let ty = self.ty;
+        // Safe to unwrap, since the default has been checked.
+        let default = self.default_value().unwrap();
+
+        let title = match &self.opts.rename {
+            Some(rename) => rename.clone(),
+            None => self.ident.to_wsdf_title_case(),
+        };
+        let desc = self.docs.clone().unwrap_or_else(|| title.clone());
+        let name = self.ident.to_string();
+        let name_cstr: syn::Expr = cstr!(name);
+        let title_cstr: syn::Expr = cstr!(title);
+        let desc_cstr: syn::Expr = cstr!(desc);
+
+        match self.kind {
+            PrefKind::Bool => quote! {
+                #var = #default as wsdf::epan_sys::gboolean;
+                wsdf::epan_sys::prefs_register_bool_preference(
+                    #WSDF_PREFS_MODULE,
+                    #name_cstr,
+                    #title_cstr,
+                    #desc_cstr,
+                    std::ptr::addr_of_mut!(#var),
+                );
+            },
+            PrefKind::Uint => quote! {
+                #var = #default;
+                wsdf::epan_sys::prefs_register_uint_preference(
+                    #WSDF_PREFS_MODULE,
+                    #name_cstr,
+                    #title_cstr,
+                    #desc_cstr,
+                    10,
+                    std::ptr::addr_of_mut!(#var),
+                );
+            },
+            PrefKind::Str => {
+                let default_cstr: syn::Expr = cstr!(default);
+                quote! {
+                    // Wireshark frees the old string whenever the preference changes, so the
+                    // default must come from its allocator too.
+                    #var = wsdf::epan_sys::wmem_strdup(
+                        wsdf::epan_sys::wmem_epan_scope(),
+                        #default_cstr,
+                    );
+                    wsdf::epan_sys::prefs_register_string_preference(
+                        #WSDF_PREFS_MODULE,
+                        #name_cstr,
+                        #title_cstr,
+                        #desc_cstr,
+                        std::ptr::addr_of_mut!(#var),
+                    );
+                }
+            }
+            PrefKind::Range => {
+                let default_cstr: syn::Expr = cstr!(default);
+                let max = self.opts.max.unwrap_or(DEFAULT_RANGE_MAX);
+                quote! {
+                    wsdf::epan_sys::range_convert_str(
+                        wsdf::epan_sys::wmem_epan_scope(),
+                        std::ptr::addr_of_mut!(#var),
+                        #default_cstr,
+                        #max,
+                    );
+                    wsdf::epan_sys::prefs_register_range_preference(
+                        #WSDF_PREFS_MODULE,
+                        #name_cstr,
+                        #title_cstr,
+                        #desc_cstr,
+                        std::ptr::addr_of_mut!(#var),
+                        #max,
+                    );
+                }
+            }
+            PrefKind::Enum => quote! {
+                #var = <#ty as wsdf::PrefEnum>::to_pref(&#default);
+                wsdf::epan_sys::prefs_register_enum_preference(
+                    #WSDF_PREFS_MODULE,
+                    #name_cstr,
+                    #title_cstr,
+                    #desc_cstr,
+                    std::ptr::addr_of_mut!(#var),
+                    <#ty as wsdf::PrefEnum>::enum_vals(),
+                    0,
+                );
+            },
+        }
+    }
+
+    fn bind(&self) -> Option<proc_macro2::TokenStream> {
+        let table = self.opts.decode_from.as_ref()?;
+        let table_cstr: syn::Expr = cstr!(table);
+        let var = self.var();
+        let bound_var = self.bound_var();
+        Some(quote! {
+            if !#bound_var.is_null() {
+                wsdf::epan_sys::dissector_delete_uint_range(
+                    #table_cstr,
+                    #bound_var,
+                    #WSDF_DISSECTOR_HANDLE,
+                );
+                wsdf::epan_sys::wmem_free(
+                    wsdf::epan_sys::wmem_epan_scope(),
+                    #bound_var as *mut std::ffi::c_void,
+                );
+                #bound_var = std::ptr::null_mut();
+            }
+            // Wireshark frees the preference's range when it changes, so we keep our own copy of
+            // the values we bound to, in order to unbind them later.
+            if !#var.is_null() {
+                #bound_var = wsdf::epan_sys::range_copy(wsdf::epan_sys::wmem_epan_scope(), #var);
+                wsdf::epan_sys::dissector_add_uint_range(
+                    #table_cstr,
+                    #bound_var,
+                    #WSDF_DISSECTOR_HANDLE,
+                );
+            }
+        })
+    }
+
+    fn current(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        let var = self.var();
+        let ty = self.ty;
+        match self.kind {
+            PrefKind::Bool => quote!(#ident: #var != 0),
+            PrefKind::Uint => quote!(#ident: #var),
+            PrefKind::Str => quote!(#ident: wsdf::pref_string(#var)),
+            PrefKind::Range => quote!(#ident: wsdf::PrefRange::from_raw(#var)),
+            PrefKind::Enum => {
+                // Safe to unwrap, since the default has been checked.
+                let default = self.default_value().unwrap();
+                quote!(#ident: <#ty as wsdf::PrefEnum>::from_pref(#var).unwrap_or(#default))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_prefs_struct {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn kind_is_picked_from_the_type() -> syn::Result<()> {
+        let input: syn::DeriveInput = parse_quote! {
+            struct MyPrefs {
+                a: bool,
+                b: u32,
+                c: String,
+                #[wsdf(decode_from = "udp.port")]
+                d: wsdf::PrefRange,
+                #[wsdf(default = "Mode::Fast")]
+                e: Mode,
+            }
+        };
+        let prefs = PrefsStruct::from_input(&input)?;
+        let kinds = prefs.prefs.iter().map(|pref| pref.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                PrefKind::Bool,
+                PrefKind::Uint,
+                PrefKind::Str,
+                PrefKind::Range,
+                PrefKind::Enum,
+            ],
+        );
+        assert!(prefs.prefs[3].bind().is_some());
+        assert!(prefs.prefs[2].bind().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        let inputs: [syn::DeriveInput; 4] = [
+            parse_quote! {
+                struct MyPrefs {
+                    mode: Mode,
+                }
+            },
+            parse_quote! {
+                struct MyPrefs {
+                    #[wsdf(default = "yes")]
+                    enabled: bool,
+                }
+            },
+            parse_quote! {
+                struct MyPrefs {
+                    #[wsdf(decode_from = "udp.port")]
+                    port: u32,
+                }
+            },
+            parse_quote! {
+                struct MyPrefs(bool);
+            },
+        ];
+        for input in &inputs {
+            assert!(PrefsStruct::from_input(input).is_err());
+        }
+    }
+}
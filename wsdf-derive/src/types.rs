@@ -1,8 +1,11 @@
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, ToTokens};
 use syn::parse_quote;
 
 use crate::{attributes::*, util::*};
 
+// Primitives carry all their field options around, so they are much larger than collections. This
+// only lives for the duration of the macro expansion, so we don't bother boxing it.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum DataType {
     /// Primitive types can be unambiguously mapped to a Wireshark type. The converse may not be
@@ -32,6 +35,30 @@ pub(crate) struct Primitive {
     ws_type: Option<String>,
     ws_enc: Option<String>,
     ws_display: Option<FieldDisplayPair>,
+    value_strings: Option<syn::Path>,
+    tfs: Option<(String, String)>,
+    /// For strings only. Whether white space is trimmed from the value.
+    trim: bool,
+    /// For byte arrays only. Whether the bytes are shown as an ASCII string.
+    ascii: bool,
+    enc_fn: Option<EncFn>,
+    /// Whether the field is read without moving past it.
+    is_virtual: bool,
+    /// Whether the field is marked as generated in the UI.
+    generated: bool,
+    /// For fields of a bits struct only. The number of bits the field takes up.
+    pub(crate) bits: Option<u32>,
+}
+
+/// A table of strings which Wireshark uses to display a field's values.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum HfStrings<'a> {
+    /// Path to a type implementing `wsdf::ValueStrings`.
+    ValueStrings(&'a syn::Path),
+    /// Labels for true and false.
+    Tfs(&'a str, &'a str),
+    /// Wireshark's names for the status of a checksum, i.e. good, bad and so on.
+    ChecksumStatus,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,18 +67,84 @@ pub(crate) struct Collection {
 
     pub(crate) hidden: bool,
     taps: Vec<syn::Path>,
+    /// For nested types only. Overrides the default byte order of the nested type's fields.
+    byte_order: Option<syn::Expr>,
+    enc_fn: Option<EncFn>,
+}
+
+/// An encoding which is picked at dissection time, by passing a previous field to a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EncFn {
+    pub(crate) field: syn::Ident,
+    func: syn::Path,
+}
+
+impl EncFn {
+    fn new(opts: &FieldOptions) -> Option<Self> {
+        Some(Self {
+            field: opts.enc_field.clone()?,
+            func: opts.enc_fn.clone()?,
+        })
+    }
+
+    fn call(&self) -> syn::Expr {
+        let func = &self.func;
+        let field = format_ident!("{}", self.field.to_wsdf_snake_case());
+        parse_quote! { #func(&#field) }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum PrimitiveType {
     U8,
     U16,
+    U24,
     U32,
+    U40,
+    U48,
+    U56,
     U64,
     I8,
     I16,
+    I24,
     I32,
+    I40,
+    I48,
+    I56,
     I64,
+    /// Wireshark has no 128-bit integers, so these are shown as bytes.
+    U128,
+    I128,
+    /// An integer encoded in a variable number of bytes, which is decoded into `typ`. Wireshark
+    /// decodes it according to the field's encoding, unless it is an SDNV, which we decode
+    /// ourselves.
+    Varint {
+        typ: Box<PrimitiveType>,
+        sdnv: bool,
+    },
+    F32,
+    F64,
+    /// A boolean held in an unsigned integer of `size` bytes. It is true iff any of the bits in
+    /// `mask` are set, or, if the mask is zero, iff the integer is not zero.
+    Bool {
+        size: usize,
+        mask: u64,
+    },
+    Ipv4,
+    Ipv6,
+    Ether,
+    Eui64,
+    Guid,
+    /// A point in time, counted in some unit since the Unix epoch.
+    AbsoluteTime(TimeUnit),
+    /// A point in time as an NTP timestamp, i.e. seconds since 1900 and a binary fraction.
+    NtpTime,
+    /// A time interval, counted in some unit.
+    RelativeTime(TimeUnit),
+    /// Text, which Wireshark decodes with some character encoding.
+    String {
+        size: StringSize,
+    },
     /// Bytes, where the size will be known before we need to decode the field. Thus, its size
     /// might be either known statically (via a byte array), or its size might be determined by a
     /// prior field in the protocol.
@@ -63,6 +156,58 @@ pub(crate) enum PrimitiveType {
     },
 }
 
+/// The unit in which a timestamp or interval is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TimeUnit {
+    Secs,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimeUnit {
+    fn name(&self) -> &'static str {
+        match self {
+            TimeUnit::Secs => "secs",
+            TimeUnit::Millis => "millis",
+            TimeUnit::Micros => "micros",
+            TimeUnit::Nanos => "nanos",
+        }
+    }
+
+    fn ws_enc(&self) -> &'static str {
+        match self {
+            TimeUnit::Secs => "ENC_TIME_SECS",
+            TimeUnit::Millis => "ENC_TIME_MSECS",
+            TimeUnit::Micros => "ENC_TIME_USECS",
+            TimeUnit::Nanos => "ENC_TIME_NSECS",
+        }
+    }
+
+    /// Seconds are kept in 32 bits, and everything else in 64 bits.
+    fn size(&self) -> usize {
+        match self {
+            TimeUnit::Secs => 4,
+            _ => 8,
+        }
+    }
+}
+
+/// How the size of a string is determined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum StringSize {
+    /// A fixed number of bytes, which may be padded with NULs or spaces.
+    Static(usize),
+    /// The number of bytes is given by a prior field.
+    Field(syn::Ident),
+    /// The number of bytes is computed from prior fields.
+    Expr(LenExpr),
+    /// The string runs up to, and including, a NUL byte.
+    NulTerminated,
+    /// The string is preceded by its length, an unsigned integer of this many bytes.
+    Prefixed(usize),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum CollectionType {
     List {
@@ -86,6 +231,53 @@ pub(crate) enum CollectionType {
     },
 }
 
+/// Extracts `T` out of a type like `Vec<T>`.
+pub(crate) fn generic_arg(segment: &syn::PathSegment) -> syn::Result<&syn::Type> {
+    let arg = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
+            args, ..
+        }) if args.len() == 1 => args.last().unwrap(),
+        _ => return make_err(&segment.arguments, "expected exactly one generic argument"),
+    };
+    match arg {
+        syn::GenericArgument::Type(inner_type) => Ok(inner_type),
+        _ => make_err(arg, "expected type argument"),
+    }
+}
+
+/// Whether a type path points into wsdf, i.e. ends in `wsdf::U24` or `wsdf::types::U24`. wsdf's
+/// own types are only recognized this way, since a bare `U24`, or a `types::U24` from some other
+/// module, may just as well be a user type of the same name.
+fn is_wsdf_path(path: &syn::Path) -> bool {
+    let mut modules = path
+        .segments
+        .iter()
+        .rev()
+        .skip(1)
+        .map(|segment| &segment.ident);
+    match (modules.next(), modules.next()) {
+        (Some(parent), _) if parent == "wsdf" => true,
+        (Some(parent), Some(grandparent)) => parent == "types" && grandparent == "wsdf",
+        _ => false,
+    }
+}
+
+/// Replaces the lifetimes in a user type, like the `'a` in `Foo<'a>`, with `'_`. The generated code
+/// names the type where those lifetimes are not declared.
+fn erase_lifetimes(path: &syn::TypePath) -> syn::TypePath {
+    let mut path = path.clone();
+    for segment in path.path.segments.iter_mut() {
+        if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            for arg in args.args.iter_mut() {
+                if let syn::GenericArgument::Lifetime(lifetime) = arg {
+                    *lifetime = syn::Lifetime::new("'_", lifetime.span());
+                }
+            }
+        }
+    }
+    path
+}
+
 impl DataType {
     fn new_primitive(typ: PrimitiveType, opts: &FieldOptions) -> Self {
         Self::Primitive(Primitive::new(typ, opts))
@@ -127,6 +319,10 @@ impl DataType {
     pub(crate) fn from_syn_type(typ: &syn::Type, opts: &FieldOptions) -> syn::Result<Self> {
         let ret = match typ {
             syn::Type::Array(array) => Self::from_array_type(array, opts)?,
+            // A &str is decoded the same way as a String.
+            syn::Type::Reference(reference) if matches!(&*reference.elem, syn::Type::Path(path) if path.path.is_ident("str")) => {
+                Self::new_primitive(PrimitiveType::new_string(typ, opts)?, opts)
+            }
             syn::Type::Path(path) if path.path.segments.empty_or_trailing() => {
                 // This shouldn't even be possible, but we'll handle it anyway.
                 return make_err(path, "unexpected end of type");
@@ -136,6 +332,7 @@ impl DataType {
 
                 let segment = path.path.segments.last().unwrap(); // we know the path is not empty, so safe unwrap on the last segment
                 let segment_ident = segment.ident.to_string();
+                let is_wsdf = is_wsdf_path(&path.path);
 
                 match segment_ident.as_str() {
                     "u8" => Self::new_primitive(U8, opts),
@@ -146,6 +343,59 @@ impl DataType {
                     "i16" => Self::new_primitive(I16, opts),
                     "i32" => Self::new_primitive(I32, opts),
                     "i64" => Self::new_primitive(I64, opts),
+                    "u128" => Self::new_primitive(U128, opts),
+                    "i128" => Self::new_primitive(I128, opts),
+                    "U24" if is_wsdf => Self::new_primitive(U24, opts),
+                    "U40" if is_wsdf => Self::new_primitive(U40, opts),
+                    "U48" if is_wsdf => Self::new_primitive(U48, opts),
+                    "U56" if is_wsdf => Self::new_primitive(U56, opts),
+                    "I24" if is_wsdf => Self::new_primitive(I24, opts),
+                    "I40" if is_wsdf => Self::new_primitive(I40, opts),
+                    "I48" if is_wsdf => Self::new_primitive(I48, opts),
+                    "I56" if is_wsdf => Self::new_primitive(I56, opts),
+                    "f32" => Self::new_primitive(F32, opts),
+                    "f64" => Self::new_primitive(F64, opts),
+                    "bool" => Self::new_primitive(PrimitiveType::new_bool(typ, opts)?, opts),
+                    "String" => Self::new_primitive(PrimitiveType::new_string(typ, opts)?, opts),
+                    "Ipv4Addr" => Self::new_primitive(Ipv4, opts),
+                    "Ipv6Addr" => Self::new_primitive(Ipv6, opts),
+                    "Ipv4" if is_wsdf => Self::new_primitive(Ipv4, opts),
+                    "Ipv6" if is_wsdf => Self::new_primitive(Ipv6, opts),
+                    "Ether" if is_wsdf => Self::new_primitive(Ether, opts),
+                    "Eui64" if is_wsdf => Self::new_primitive(Eui64, opts),
+                    "Guid" if is_wsdf => Self::new_primitive(Guid, opts),
+                    "EpochSecs" if is_wsdf => {
+                        Self::new_primitive(AbsoluteTime(TimeUnit::Secs), opts)
+                    }
+                    "EpochMillis" if is_wsdf => {
+                        Self::new_primitive(AbsoluteTime(TimeUnit::Millis), opts)
+                    }
+                    "EpochMicros" if is_wsdf => {
+                        Self::new_primitive(AbsoluteTime(TimeUnit::Micros), opts)
+                    }
+                    "EpochNanos" if is_wsdf => {
+                        Self::new_primitive(AbsoluteTime(TimeUnit::Nanos), opts)
+                    }
+                    "NtpTime" if is_wsdf => Self::new_primitive(NtpTime, opts),
+                    "DurationSecs" if is_wsdf => {
+                        Self::new_primitive(RelativeTime(TimeUnit::Secs), opts)
+                    }
+                    "DurationMillis" if is_wsdf => {
+                        Self::new_primitive(RelativeTime(TimeUnit::Millis), opts)
+                    }
+                    "DurationMicros" if is_wsdf => {
+                        Self::new_primitive(RelativeTime(TimeUnit::Micros), opts)
+                    }
+                    "DurationNanos" if is_wsdf => {
+                        Self::new_primitive(RelativeTime(TimeUnit::Nanos), opts)
+                    }
+
+                    "Varint" if is_wsdf => {
+                        Self::new_primitive(PrimitiveType::new_varint(segment, false)?, opts)
+                    }
+                    "Sdnv" if is_wsdf => {
+                        Self::new_primitive(PrimitiveType::new_varint(segment, true)?, opts)
+                    }
 
                     // The AST for Vecs is horrible, so we'll do it in another function.
                     "Vec" => return Self::from_vec_type(segment, opts),
@@ -156,14 +406,14 @@ impl DataType {
                     _ => match &opts.dispatch {
                         Some(dispatch) => Self::new_collection(
                             CollectionType::Enum {
-                                ident: path.clone(),
+                                ident: erase_lifetimes(path),
                                 dispatch: dispatch.clone(),
                             },
                             opts,
                         ),
                         None => Self::new_collection(
                             CollectionType::Struct {
-                                ident: path.clone(),
+                                ident: erase_lifetimes(path),
                             },
                             opts,
                         ),
@@ -173,9 +423,212 @@ impl DataType {
             _ => return make_err(typ, "unexpected type"),
         };
 
+        // For lists, the options apply to each element.
+        let elem = ret.innermost_elem();
+        if opts.value_strings.is_some() && !elem.is_integer() {
+            return make_err(typ, "value_strings can only be used on integer fields");
+        }
+        if (opts.width.is_some() || opts.mask.is_some() || opts.tfs.is_some())
+            && !matches!(
+                elem,
+                DataType::Primitive(Primitive {
+                    typ: PrimitiveType::Bool { .. },
+                    ..
+                })
+            )
+        {
+            return make_err(typ, "width, mask and tfs can only be used on bool fields");
+        }
+        if opts.byte_order.is_some() && opts.ws_enc.is_some() {
+            return make_err(typ, "le and be cannot be combined with enc");
+        }
+        if opts.enc_field.is_some() != opts.enc_fn.is_some() {
+            return make_err(typ, "enc_field and enc_fn must be given together");
+        }
+        if opts.enc_fn.is_some() && (opts.ws_enc.is_some() || opts.byte_order.is_some()) {
+            return make_err(typ, "enc_fn cannot be combined with enc, le or be");
+        }
+        // The u8s of a byte array come through here with the same options, so a single u8 is
+        // allowed as well, and is shown as a single character.
+        if opts.ascii.is_some()
+            && !matches!(
+                elem,
+                DataType::Primitive(Primitive {
+                    typ: PrimitiveType::U8
+                        | PrimitiveType::ByteArray {
+                            subdissector: None,
+                            ..
+                        },
+                    ..
+                })
+            )
+        {
+            return make_err(typ, "ascii can only be used on byte arrays");
+        }
+        if opts.ascii.is_some()
+            && (opts.ws_type.is_some() || opts.ws_enc.is_some() || opts.decode_with.is_some())
+        {
+            return make_err(typ, "ascii cannot be combined with typ, enc or decode_with");
+        }
+        // Wireshark only takes the value of a boolean with a custom format as 32 bits, which
+        // cannot hold the bits of a wider mask.
+        if opts.decode_with.is_some()
+            && matches!(
+                elem,
+                DataType::Primitive(Primitive {
+                    typ: PrimitiveType::Bool { mask, .. },
+                    ..
+                }) if *mask > u32::MAX as u64
+            )
+        {
+            return make_err(
+                typ,
+                "decode_with cannot be used on a bool with a mask above 32 bits",
+            );
+        }
+        if opts.is_virtual == Some(true) {
+            match &ret {
+                DataType::Primitive(Primitive {
+                    typ:
+                        PrimitiveType::ByteArray {
+                            subdissector: Some(_),
+                            ..
+                        },
+                    ..
+                }) => return make_err(typ, "virtual cannot be combined with subdissector"),
+                DataType::Primitive(_) => (),
+                DataType::Collection(_) => {
+                    return make_err(typ, "virtual can only be used on primitive fields")
+                }
+            }
+        }
+        if let Some(bits) = opts.bits {
+            Self::check_bits(typ, &ret, bits)?;
+        }
+        if opts.checksum.is_some() || opts.checksum_fn.is_some() {
+            Self::check_checksum(typ, &ret, opts)?;
+        } else if opts.over.is_some() {
+            return make_err(typ, "over can only be used with checksum or checksum_fn");
+        }
+        if (opts.size.is_some()
+            || opts.nul_terminated.is_some()
+            || opts.len_prefix.is_some()
+            || opts.trim.is_some())
+            && !matches!(
+                elem,
+                DataType::Primitive(Primitive {
+                    typ: PrimitiveType::String { .. },
+                    ..
+                })
+            )
+        {
+            return make_err(
+                typ,
+                "size, nul_terminated, len_prefix and trim can only be used on String fields",
+            );
+        }
+
         Ok(ret)
     }
 
+    /// Checks that a field with the `bits` attribute can be read bit by bit.
+    fn check_bits(typ: &syn::Type, data: &DataType, bits: u32) -> syn::Result<()> {
+        let primitive = match data {
+            DataType::Primitive(primitive) => primitive,
+            DataType::Collection(_) => {
+                return make_err(
+                    typ,
+                    "bits can only be used on unsigned integer and bool fields",
+                )
+            }
+        };
+        let width = match primitive.typ {
+            PrimitiveType::U8 => 8,
+            PrimitiveType::U16 => 16,
+            PrimitiveType::U32 => 32,
+            PrimitiveType::U64 | PrimitiveType::Bool { .. } => 64,
+            _ => {
+                return make_err(
+                    typ,
+                    "bits can only be used on unsigned integer and bool fields",
+                )
+            }
+        };
+        if bits > width {
+            return make_err(typ, "bits does not fit in the field's type");
+        }
+        if primitive.decode_with.is_some()
+            || primitive.is_virtual
+            || matches!(primitive.typ, PrimitiveType::Bool { mask, .. } if mask != 0)
+        {
+            return make_err(
+                typ,
+                "bits cannot be combined with decode_with, mask or virtual",
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that a checksum field is an unsigned integer which can hold the checksum, and which
+    /// is added to the tree like a plain integer.
+    fn check_checksum(typ: &syn::Type, data: &DataType, opts: &FieldOptions) -> syn::Result<()> {
+        use PrimitiveType::*;
+
+        if opts.checksum.is_some() && opts.checksum_fn.is_some() {
+            return make_err(typ, "checksum and checksum_fn cannot be combined");
+        }
+        let primitive = match data {
+            DataType::Primitive(primitive) if matches!(primitive.typ, U8 | U16 | U24 | U32) => {
+                primitive
+            }
+            _ => {
+                return make_err(
+                    typ,
+                    "checksum fields must be unsigned integers of at most 32 bits",
+                )
+            }
+        };
+        if primitive.hidden
+            || primitive.decode_with.is_some()
+            || primitive.is_virtual
+            || primitive.bits.is_some()
+        {
+            return make_err(
+                typ,
+                "checksum cannot be combined with hide, decode_with, virtual or bits",
+            );
+        }
+        let width = match opts.checksum.as_deref() {
+            None => return Ok(()),
+            Some("crc16" | "internet" | "fletcher16") => U16,
+            Some(_) => U32,
+        };
+        if primitive.typ != width {
+            let name = opts.checksum.as_deref().unwrap_or_default();
+            let msg = format!(
+                "the {name} checksum must be held in a {} field",
+                width.int_type()
+            );
+            return make_err(typ, &msg);
+        }
+        Ok(())
+    }
+
+    /// The type of the elements of a (possibly nested) list, or the type itself otherwise.
+    fn innermost_elem(&self) -> &DataType {
+        match self {
+            DataType::Collection(Collection {
+                typ: CollectionType::List { elem, .. },
+                ..
+            }) => elem.innermost_elem(),
+            _ => self,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, DataType::Primitive(data) if data.typ.is_integer())
+    }
+
     fn from_array_type(
         array: &syn::TypeArray,
         opts: &FieldOptions,
@@ -212,28 +665,39 @@ impl DataType {
     fn from_vec_type(segment: &syn::PathSegment, opts: &FieldOptions) -> syn::Result<Self> {
         debug_assert!(segment.ident == "Vec");
 
-        let arg = match &segment.arguments {
-            syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-                args,
-                ..
-            }) if args.len() == 1 => args.last().unwrap(),
-            _ => return make_err(&segment.arguments, "expected exactly one generic argument"),
-        };
-        let inner_type = match arg {
-            syn::GenericArgument::Type(inner_type) => inner_type,
-            _ => return make_err(arg, "expected type argument"),
-        };
+        if opts.bytes_len.is_some() && (opts.size_hint.is_some() || opts.len_expr.is_some()) {
+            return make_err(
+                segment,
+                "bytes_len_field cannot be combined with len_field or len",
+            );
+        }
+
+        let open_ended = SizeHint::open_ended(segment, opts)?;
+        if open_ended.is_some()
+            && (opts.size_hint.is_some() || opts.len_expr.is_some() || opts.bytes_len.is_some())
+        {
+            return make_err(
+                segment,
+                "until_end, until and terminator cannot be combined with len_field, len or bytes_len_field",
+            );
+        }
 
+        let inner_type = generic_arg(segment)?;
         let elem_type = Self::from_syn_type(inner_type, opts)?;
         match elem_type {
+            // The number of u8s is also their number of bytes, so both kinds of length fields mean
+            // the same thing here.
             DataType::Primitive(data) if data.typ == PrimitiveType::U8 => {
-                let typ = match (&opts.size_hint, &opts.consume_bytes) {
+                let size_hint = SizeHint::from_opts(opts)?
+                    .or_else(|| opts.bytes_len.clone().map(SizeHint::Field))
+                    .or(open_ended);
+                let typ = match (size_hint, &opts.consume_bytes) {
                     (None, None) => {
                         return make_err(segment, "unable to determine size of these bytes");
                     }
-                    (Some(field), None) => Self::new_primitive(
+                    (Some(size), None) => Self::new_primitive(
                         PrimitiveType::ByteArray {
-                            size: SizeHint::Field(field.clone()),
+                            size,
                             subdissector: None,
                         },
                         opts,
@@ -244,7 +708,7 @@ impl DataType {
                         },
                         opts,
                     ),
-                    (Some(field), Some(consume)) => match consume {
+                    (Some(size), Some(consume)) => match consume {
                         ConsumeBytes::ConsumeWith(_) => {
                             // Of course, if we already have a length indication, then we would not
                             // expect a `consume_with` annotation.
@@ -255,7 +719,7 @@ impl DataType {
                         }
                         ConsumeBytes::Subdissector(subdissector) => Self::new_primitive(
                             PrimitiveType::ByteArray {
-                                size: SizeHint::Field(field.clone()),
+                                size,
                                 subdissector: Some(subdissector.clone()),
                             },
                             opts,
@@ -264,17 +728,24 @@ impl DataType {
                 };
                 Ok(typ)
             }
-            _ if opts.size_hint.is_none() => {
-                make_err(segment, "length of list cannot be determined")
-            }
-            _ => {
+            _ if opts.bytes_len.is_some() => {
                 let elem = Box::new(elem_type);
-                let len = SizeHint::Field(opts.size_hint.clone().unwrap()); // safe to unwrap here, branch above ensures Some
+                let len = SizeHint::Bytes(opts.bytes_len.clone().unwrap()); // safe to unwrap here, guard ensures Some
                 Ok(Self::new_collection(
                     CollectionType::List { elem, len },
                     opts,
                 ))
             }
+            _ => match SizeHint::from_opts(opts)?.or(open_ended) {
+                None => make_err(segment, "length of list cannot be determined"),
+                Some(len) => {
+                    let elem = Box::new(elem_type);
+                    Ok(Self::new_collection(
+                        CollectionType::List { elem, len },
+                        opts,
+                    ))
+                }
+            },
         }
     }
 
@@ -288,6 +759,87 @@ impl DataType {
             })
     }
 
+    /// The field itself, if it is a primitive.
+    pub(crate) fn primitive(&self) -> Option<&Primitive> {
+        match self {
+            DataType::Primitive(data) => Some(data),
+            DataType::Collection(_) => None,
+        }
+    }
+
+    /// For fields of a bits struct only. The number of bits the field takes up.
+    pub(crate) fn bits(&self) -> Option<u32> {
+        match self {
+            DataType::Primitive(data) => data.bits,
+            DataType::Collection(_) => None,
+        }
+    }
+
+    /// If the encoding of this field is picked at dissection time, returns the field it is picked
+    /// from.
+    pub(crate) fn get_enc_field(&self) -> Option<&syn::Ident> {
+        let enc_fn = match self {
+            DataType::Primitive(data) => &data.enc_fn,
+            DataType::Collection(Collection {
+                typ: CollectionType::List { elem, .. },
+                ..
+            }) => return elem.get_enc_field(),
+            DataType::Collection(data) => &data.enc_fn,
+        };
+        enc_fn.as_ref().map(|enc_fn| &enc_fn.field)
+    }
+
+    /// If the size of this field is computed from previous fields, returns that computation.
+    pub(crate) fn get_len_expr(&self) -> Option<&LenExpr> {
+        match self {
+            DataType::Primitive(data) => match &data.typ {
+                PrimitiveType::ByteArray {
+                    size: SizeHint::Expr(len),
+                    ..
+                }
+                | PrimitiveType::String {
+                    size: StringSize::Expr(len),
+                } => Some(len),
+                _ => None,
+            },
+            DataType::Collection(data) => match &data.typ {
+                CollectionType::List {
+                    len: SizeHint::Expr(len),
+                    ..
+                } => Some(len),
+                CollectionType::List { elem, .. } => elem.get_len_expr(),
+                _ => None,
+            },
+        }
+    }
+
+    /// For dissection. Builds the code which computes the size of this field from previous
+    /// fields, if needed. A size which overflows or goes negative makes the packet malformed.
+    pub(crate) fn compute_len(
+        &self,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
+        let len = self.get_len_expr()?.checked();
+        let msg = format!("length of {field_name} is out of range");
+        Some(quote! {
+            let #WSDF_LEN: usize = match #len.and_then(|n| usize::try_from(n).ok()) {
+                Some(n) => n,
+                None => {
+                    wsdf::expert::add_malformed(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        #msg,
+                    );
+                    break #WSDF_DISSECT_BLOCK false;
+                }
+            };
+        })
+    }
+
     /// If this is a repeated field using a previous field for its length, returns that field.
     pub(crate) fn get_len_field(&self) -> Option<&syn::Ident> {
         // The `len_field` annotation can appear in two places. For primitive types, it may
@@ -297,12 +849,15 @@ impl DataType {
                 PrimitiveType::ByteArray {
                     size: SizeHint::Field(ident),
                     ..
+                }
+                | PrimitiveType::String {
+                    size: StringSize::Field(ident),
                 } => Some(ident),
                 _ => None,
             },
             DataType::Collection(data) => match &data.typ {
                 CollectionType::List {
-                    len: SizeHint::Field(ident),
+                    len: SizeHint::Field(ident) | SizeHint::Bytes(ident),
                     ..
                 } => Some(ident),
                 _ => None,
@@ -398,6 +953,122 @@ mod test_datatype_construction {
         assert!(got.is_err());
     }
 
+    #[test]
+    fn bit_fields() -> syn::Result<()> {
+        let opts = FieldOptions {
+            bits: Some(12),
+            ..Default::default()
+        };
+        let got = DataType::from_syn_type(&parse_quote! { u16 }, &opts)?;
+        assert_eq!(got.bits(), Some(12));
+
+        // Too wide for the type.
+        assert!(DataType::from_syn_type(&parse_quote! { u8 }, &opts).is_err());
+        // Signed integers and other types cannot be read bit by bit.
+        assert!(DataType::from_syn_type(&parse_quote! { i16 }, &opts).is_err());
+        assert!(DataType::from_syn_type(&parse_quote! { [u8; 2] }, &opts).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_with_bytes_len() -> syn::Result<()> {
+        let opts = FieldOptions {
+            bytes_len: Some(format_ident!("len")),
+            ..Default::default()
+        };
+
+        let got = DataType::from_syn_type(&parse_quote! { Vec<Foo> }, &opts)?;
+        let want = DataType::new_collection(
+            CollectionType::List {
+                elem: Box::new(DataType::from_syn_type(&parse_quote! { Foo }, &opts)?),
+                len: SizeHint::Bytes(format_ident!("len")),
+            },
+            &opts,
+        );
+        assert_eq!(got, want);
+
+        // A byte length for bytes is just their size.
+        let got = DataType::from_syn_type(&parse_quote! { Vec<u8> }, &opts)?;
+        let want = DataType::new_primitive(
+            PrimitiveType::ByteArray {
+                size: SizeHint::Field(format_ident!("len")),
+                subdissector: None,
+            },
+            &opts,
+        );
+        assert_eq!(got, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_ended_list() -> syn::Result<()> {
+        let opts = FieldOptions {
+            until_end: Some(true),
+            ..Default::default()
+        };
+
+        let got = DataType::from_syn_type(&parse_quote! { Vec<Foo> }, &opts)?;
+        let want = DataType::new_collection(
+            CollectionType::List {
+                elem: Box::new(DataType::from_syn_type(&parse_quote! { Foo }, &opts)?),
+                len: SizeHint::UntilEnd,
+            },
+            &opts,
+        );
+        assert_eq!(got, want);
+
+        // Bytes until the end are a single byte array.
+        let got = DataType::from_syn_type(&parse_quote! { Vec<u8> }, &opts)?;
+        let want = DataType::new_primitive(
+            PrimitiveType::ByteArray {
+                size: SizeHint::UntilEnd,
+                subdissector: None,
+            },
+            &opts,
+        );
+        assert_eq!(got, want);
+
+        let opts = FieldOptions {
+            terminator: Some(0),
+            ..opts
+        };
+        assert!(DataType::from_syn_type(&parse_quote! { Vec<Foo> }, &opts).is_err());
+
+        // Bytes up to a terminator are a single byte array too.
+        let opts = FieldOptions {
+            terminator: Some(0),
+            ..Default::default()
+        };
+        let got = DataType::from_syn_type(&parse_quote! { Vec<u8> }, &opts)?;
+        let want = DataType::new_primitive(
+            PrimitiveType::ByteArray {
+                size: SizeHint::Terminator(0),
+                subdissector: None,
+            },
+            &opts,
+        );
+        assert_eq!(got, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn len_expr_validation() -> syn::Result<()> {
+        let len = LenExpr::new(&parse_quote! { (ihl * 4 - 20) % size })?;
+        assert_eq!(
+            len.fields(),
+            vec![&format_ident!("ihl"), &format_ident!("size")]
+        );
+
+        assert!(LenExpr::new(&parse_quote! { ihl << 2 }).is_err());
+        assert!(LenExpr::new(&parse_quote! { foo.len() }).is_err());
+        assert!(LenExpr::new(&parse_quote! { -1 }).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn struct_type() -> syn::Result<()> {
         let input_type: syn::Type = parse_quote! { path::to::FooStruct };
@@ -650,14 +1321,91 @@ mod test_datatype_construction {
     }
 
     #[test]
-    fn bytes_using_len_and_decode_with_is_ok() -> syn::Result<()> {
-        // Something like
-        //   #[wsdf(len_field = "len", decode_with = "decode_with"")]
-        //   bytes: Vec<u8>
-        let input_type: syn::Type = parse_quote! { Vec<u8> };
+    fn wsdf_types_need_their_path() -> syn::Result<()> {
+        let opts = FieldOptions::default();
+        for input_type in [
+            parse_quote! { wsdf::U24 },
+            parse_quote! { wsdf::types::U24 },
+            parse_quote! { ::wsdf::types::U24 },
+        ] {
+            let got = DataType::from_syn_type(&input_type, &opts)?;
+            assert_eq!(got, DataType::new_primitive(PrimitiveType::U24, &opts));
+        }
 
-        let opts = FieldOptions {
-            size_hint: Some(format_ident!("len")),
+        // Without wsdf in the path, it is a user type which happens to have the same name.
+        let user_types: [syn::TypePath; 3] = [
+            parse_quote! { U24 },
+            parse_quote! { types::U24 },
+            parse_quote! { crate::types::U24 },
+        ];
+        for input_type in user_types {
+            let got = DataType::from_syn_type(&syn::Type::Path(input_type.clone()), &opts)?;
+            let want =
+                DataType::new_collection(CollectionType::Struct { ident: input_type }, &opts);
+            assert_eq!(got, want);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_sizes() -> syn::Result<()> {
+        let input_type: syn::Type = parse_quote! { String };
+        let string_size = |opts: &FieldOptions| -> syn::Result<StringSize> {
+            match DataType::from_syn_type(&input_type, opts)? {
+                DataType::Primitive(Primitive {
+                    typ: PrimitiveType::String { size },
+                    ..
+                }) => Ok(size),
+                got => panic!("expected a string, got {got:?}"),
+            }
+        };
+
+        let opts = FieldOptions {
+            len_prefix: Some(format_ident!("u16")),
+            ..Default::default()
+        };
+        assert_eq!(string_size(&opts)?, StringSize::Prefixed(2));
+
+        // For a list of strings, the len_field is the number of strings.
+        let opts = FieldOptions {
+            size_hint: Some(format_ident!("n")),
+            nul_terminated: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(string_size(&opts)?, StringSize::NulTerminated);
+
+        let opts = FieldOptions {
+            size: Some(8),
+            nul_terminated: Some(true),
+            ..Default::default()
+        };
+        assert!(DataType::from_syn_type(&input_type, &opts).is_err());
+        assert!(DataType::from_syn_type(&input_type, &FieldOptions::default()).is_err());
+
+        // A &str is the same as a String.
+        let opts = FieldOptions {
+            size: Some(8),
+            ..Default::default()
+        };
+        let borrowed: syn::Type = parse_quote! { &'a str };
+        assert_eq!(
+            DataType::from_syn_type(&borrowed, &opts)?,
+            DataType::from_syn_type(&input_type, &opts)?,
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bytes_using_len_and_decode_with_is_ok() -> syn::Result<()> {
+        // Something like
+        //   #[wsdf(len_field = "len", decode_with = "decode_with"")]
+        //   bytes: Vec<u8>
+        let input_type: syn::Type = parse_quote! { Vec<u8> };
+
+        let opts = FieldOptions {
+            size_hint: Some(format_ident!("len")),
             decode_with: Some(parse_quote! { decode_with }),
             ..Default::default()
         };
@@ -706,11 +1454,72 @@ impl DataType {
         }
     }
 
+    /// For dissection. Builds the code which checks that the field lies within the captured
+    /// packet data, and stops dissection otherwise. Only primitive types have a size known up
+    /// front, so other types produce no code here.
+    pub(crate) fn check_bounds(
+        &self,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
+        match self {
+            // A bit field may start in the middle of a byte, and end in the middle of another.
+            DataType::Primitive(Primitive {
+                bits: Some(bits), ..
+            }) => {
+                let bits = *bits as usize;
+                Some(quote! {
+                    if !wsdf::expert::check_bounds(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                        #WSDF_TVB_BUF.len(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        (#WSDF_BIT_OFFSET % 8 + #bits).div_ceil(8),
+                        #field_name,
+                    ) {
+                        break #WSDF_DISSECT_BLOCK false;
+                    }
+                })
+            }
+            DataType::Primitive(data) => {
+                let compute_size = data
+                    .typ
+                    .compute_size(&data.ws_enc(), field_name, root_ident);
+                let size = data.typ.size_expr();
+                let check_varint_fits = data.typ.check_varint_fits(field_name, root_ident);
+                Some(quote! {
+                    #compute_size
+                    if !wsdf::expert::check_bounds(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                        #WSDF_TVB_BUF.len(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        #size,
+                        #field_name,
+                    ) {
+                        break #WSDF_DISSECT_BLOCK false;
+                    }
+                    #check_varint_fits
+                })
+            }
+            DataType::Collection(_) => None,
+        }
+    }
+
     /// For dissection. Builds the code needed to emit the field, which just means extracting
     /// the field's value from the TVB and keeping it in a variable.
-    pub(crate) fn emit_field(&self, field_ident: &syn::Ident) -> Option<proc_macro2::TokenStream> {
+    pub(crate) fn emit_field(
+        &self,
+        field_ident: &syn::Ident,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
         match self {
-            DataType::Primitive(data) => data.emit_field(field_ident),
+            DataType::Primitive(data) => data.emit_field(field_ident, field_name, root_ident),
             DataType::Collection(_) => None,
         }
     }
@@ -811,6 +1620,10 @@ impl DataType {
     ) -> Option<proc_macro2::TokenStream> {
         use CollectionType::*;
 
+        let (strings, bitmask) = match self {
+            DataType::Primitive(primitive) => (primitive.hf_strings(), primitive.typ.bitmask()),
+            DataType::Collection(_) => (None, 0),
+        };
         let create_expr = || {
             Some(Self::create_hf_impl(
                 is_unit_tuple,
@@ -819,6 +1632,8 @@ impl DataType {
                 field_blurb,
                 &self.ws_type(),
                 &self.ws_display(),
+                strings,
+                bitmask,
             ))
         };
 
@@ -843,16 +1658,46 @@ impl DataType {
         }
     }
 
-    fn create_hf_impl(
+    /// Creates the header field, registered under the current WSDF_PREFIX_NEXT. The bitmask
+    /// should be 0 unless the field is one flag out of a bitmask. If a `wsdf::ValueStrings` type
+    /// is given, its table is used to display the field's values.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn create_hf_impl(
         is_unit_tuple: bool,
         root_ident: &syn::Ident,
         field_name: &str,
         field_blurb: &Option<String>,
         ws_type: &syn::Path,
         ws_display: &syn::Expr,
+        strings: Option<HfStrings>,
+        bitmask: u64,
     ) -> proc_macro2::TokenStream {
         let field_name = field_name_expr(field_name, is_unit_tuple);
         let field_blurb = field_blurb_expr(field_blurb, is_unit_tuple);
+        let get_strings: syn::Stmt = match strings {
+            Some(HfStrings::ValueStrings(path)) => parse_quote! {
+                let (#WSDF_STRINGS, #WSDF_STRINGS_DISPLAY) =
+                    <#path as wsdf::ValueStrings>::strings(#ws_type);
+            },
+            Some(HfStrings::Tfs(true_string, false_string)) => {
+                let true_cstr: syn::Expr = cstr!(true_string);
+                let false_cstr: syn::Expr = cstr!(false_string);
+                parse_quote! {
+                    let (#WSDF_STRINGS, #WSDF_STRINGS_DISPLAY) =
+                        (wsdf::make_tfs(#true_cstr, #false_cstr), 0 as std::ffi::c_int);
+                }
+            }
+            Some(HfStrings::ChecksumStatus) => parse_quote! {
+                let (#WSDF_STRINGS, #WSDF_STRINGS_DISPLAY) = (
+                    unsafe { wsdf::epan_sys::proto_checksum_vals.as_ptr() } as *const std::ffi::c_void,
+                    0 as std::ffi::c_int,
+                );
+            },
+            None => parse_quote! {
+                let (#WSDF_STRINGS, #WSDF_STRINGS_DISPLAY) =
+                    (std::ptr::null::<std::ffi::c_void>(), 0 as std::ffi::c_int);
+            },
+        };
 
         // Here, we need a static location for an integer. A pointer to this location is given to
         // Wireshark. We use Box::leak as a convenient way to get a "static" location. This can
@@ -886,9 +1731,9 @@ impl DataType {
                             std::ffi::CString::new(#WSDF_PREFIX_NEXT).unwrap().into_boxed_c_str(), // @todo: handle unwrap here
                         ).as_ptr() as *const std::ffi::c_char,
                     type_: #ws_type,
-                    display: #ws_display,
-                    strings: std::ptr::null(),
-                    bitmask: 0,
+                    display: #ws_display | #WSDF_STRINGS_DISPLAY,
+                    strings: #WSDF_STRINGS,
+                    bitmask: #bitmask,
                     blurb: #field_blurb,
                     id: -1,
                     parent: 0,
@@ -902,6 +1747,7 @@ impl DataType {
         quote! {
             #declare_hf
             #keep_hf
+            #get_strings
             #append_hf
         }
     }
@@ -1013,21 +1859,52 @@ impl Primitive {
             should_save: opts.save.unwrap_or(false),
             decode_with: opts.decode_with.clone(),
             ws_type: opts.ws_type.clone(),
-            ws_enc: opts.ws_enc.clone(),
+            ws_enc: opts
+                .ws_enc
+                .clone()
+                .or_else(|| opts.byte_order.map(|order| order.ws_enc().to_owned())),
             ws_display: opts.ws_display.clone(),
+            value_strings: opts.value_strings.clone(),
+            tfs: opts.tfs.clone(),
+            trim: opts.trim.unwrap_or(false),
+            ascii: opts.ascii.unwrap_or(false),
+            enc_fn: EncFn::new(opts),
+            is_virtual: opts.is_virtual.unwrap_or(false),
+            generated: opts.generated.unwrap_or(false),
+            bits: opts.bits,
+        }
+    }
+
+    /// The table of strings used to display the field's values, if any.
+    fn hf_strings(&self) -> Option<HfStrings<'_>> {
+        match (&self.value_strings, &self.tfs) {
+            (Some(path), _) => Some(HfStrings::ValueStrings(path)),
+            (None, Some((true_string, false_string))) => {
+                Some(HfStrings::Tfs(true_string, false_string))
+            }
+            (None, None) => None,
         }
     }
 
     fn default_ws_type(&self) -> &'static str {
-        self.typ.default_ws_type()
+        match self.ascii {
+            true => "FT_STRING",
+            false => self.typ.default_ws_type(),
+        }
     }
 
-    fn default_ws_enc(&self) -> &'static str {
-        self.typ.default_ws_enc()
+    fn default_ws_enc(&self) -> syn::Expr {
+        match self.ascii {
+            true => format_ws_enc("ENC_ASCII"),
+            false => self.typ.default_ws_enc(),
+        }
     }
 
     fn default_ws_display(&self) -> (&'static str, Option<&'static str>) {
-        self.typ.default_ws_display()
+        match self.ascii {
+            true => ("BASE_NONE", None),
+            false => self.typ.default_ws_display(),
+        }
     }
 
     pub(crate) fn ws_type(&self) -> syn::Path {
@@ -1038,15 +1915,31 @@ impl Primitive {
         format_ws_type(ws_type)
     }
 
-    pub(crate) fn ws_enc(&self) -> syn::Path {
-        let ws_enc = match &self.ws_enc {
-            Some(ws_enc) => ws_enc.as_str(),
-            None => self.default_ws_enc(),
+    pub(crate) fn ws_enc(&self) -> syn::Expr {
+        let ws_enc = match (&self.ws_enc, &self.enc_fn) {
+            (Some(ws_enc), _) => format_ws_enc(ws_enc),
+            (None, Some(enc_fn)) => enc_fn.call(),
+            (None, None) => self.default_ws_enc(),
         };
-        format_ws_enc(ws_enc)
+        // The time encoding follows from the type, so `enc` only needs to give the byte order.
+        match self.typ.time_enc() {
+            Some(time_enc) => {
+                let time_enc = format_ws_enc(time_enc);
+                parse_quote! { #time_enc | #ws_enc }
+            }
+            None => ws_enc,
+        }
     }
 
     pub(crate) fn ws_display(&self) -> syn::Expr {
+        // Wireshark wants the width of the whole integer, in bits, as the display of a boolean
+        // with a bitmask.
+        if let (None, PrimitiveType::Bool { size, mask }) = (&self.ws_display, &self.typ) {
+            if *mask != 0 {
+                let bits = (size * 8) as i32;
+                return parse_quote! { #bits as std::ffi::c_int };
+            }
+        }
         let default_display = FieldDisplayPair::new(self.default_ws_display());
         let ws_display = self.ws_display.as_ref().unwrap_or(&default_display);
         ws_display.to_expr()
@@ -1057,6 +1950,49 @@ impl Primitive {
         field_ident: &syn::Ident,
         root_ident: &syn::Ident,
     ) -> proc_macro2::TokenStream {
+        // A hidden virtual field does not show up, nor take up any bytes.
+        if self.hidden && self.is_virtual {
+            return quote! {};
+        }
+
+        let add_to_tree = self.add_to_tree_and_advance(field_ident, root_ident);
+        // Each way of adding the field binds the item it added, except for bytes handed to a
+        // subdissector, which do not get an item of their own.
+        let adds_item = !matches!(
+            self.typ,
+            PrimitiveType::ByteArray {
+                subdissector: Some(_),
+                ..
+            }
+        );
+        let set_generated = (self.generated && !self.hidden && adds_item).then(|| {
+            quote! { unsafe { wsdf::proto_item_set_generated(#WSDF_ITEM) }; }
+        });
+        if !self.is_virtual {
+            return quote! {
+                #add_to_tree
+                #set_generated
+            };
+        }
+
+        // The field is added like any other, and the offset is moved back afterwards.
+        quote! {
+            let #WSDF_VIRTUAL_OFFSET = #WSDF_OFFSET;
+            #add_to_tree
+            #set_generated
+            #WSDF_OFFSET = #WSDF_VIRTUAL_OFFSET;
+        }
+    }
+
+    fn add_to_tree_and_advance(
+        &self,
+        field_ident: &syn::Ident,
+        root_ident: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        if let Some(bits) = self.bits {
+            return self.add_bits_to_tree(bits);
+        }
+
         // For primitive types, we know its size before starting to decode the field. Thus we can
         // cheat and skip all the work, and just increment the offset.
         if self.hidden {
@@ -1065,11 +2001,56 @@ impl Primitive {
         }
 
         match &self.decode_with {
+            // A trimmed string is different from what is in the packet, so we must give Wireshark
+            // the value ourselves.
+            None if self.trim => {
+                let size = self.typ.size_expr();
+                quote! {
+                    let #WSDF_ITEM = unsafe {
+                        wsdf::epan_sys::proto_tree_add_string(
+                            #WSDF_PARENT_NODE,
+                            #WSDF_HF,
+                            #WSDF_TVB,
+                            #WSDF_START + #WSDF_OFFSET,
+                            #size,
+                            wsdf::to_ui_cstring(#field_ident).as_ptr(),
+                        )
+                    };
+                    #WSDF_OFFSET += #size;
+                }
+            }
             None => self.typ.add_to_tree(&self.ws_enc(), root_ident),
             Some(decode_fn_ident) => self.add_to_tree_decode_with(field_ident, decode_fn_ident),
         }
     }
 
+    /// Adds a field of a bits struct, and moves the bit cursor past it. The byte offset follows
+    /// the byte which the cursor is in.
+    fn add_bits_to_tree(&self, bits: u32) -> proc_macro2::TokenStream {
+        let add_node = (!self.hidden).then(|| {
+            let bit_start = bit_start();
+            let ws_enc = self.ws_enc();
+            quote! {
+                let #WSDF_ITEM = unsafe {
+                    wsdf::epan_sys::proto_tree_add_bits_item(
+                        #WSDF_PARENT_NODE,
+                        #WSDF_HF,
+                        #WSDF_TVB,
+                        #bit_start,
+                        #bits as std::ffi::c_int,
+                        #ws_enc,
+                    )
+                };
+            }
+        });
+        let bits = bits as usize;
+        quote! {
+            #add_node
+            #WSDF_BIT_OFFSET += #bits;
+            #WSDF_OFFSET = (#WSDF_BIT_OFFSET / 8) as std::ffi::c_int;
+        }
+    }
+
     fn add_to_tree_decode_with(
         &self,
         field_ident: &proc_macro2::Ident,
@@ -1085,23 +2066,59 @@ impl Primitive {
         //
         // We'll also need an expression for the value of the field (required by the
         // proto_tree_add... function).
-        let (type_name, value) = match self.typ {
-            U8 | U16 | U32 => ("uint", quote! { #field_ident as u32 }),
-            I8 | I16 | I32 => ("int", quote! { #field_ident as i32 }),
-            U64 => ("uint64", quote! { #field_ident }),
-            I64 => ("int64", quote! { #field_ident }),
+        let typ = match &self.typ {
+            Varint { typ, .. } => typ.as_ref(),
+            typ => typ,
+        };
+        let (type_name, value) = match typ {
+            U8 | U16 | U24 | U32 => ("uint", quote! { #field_ident as u32 }),
+            I8 | I16 | I24 | I32 => ("int", quote! { #field_ident as i32 }),
+            U40 | U48 | U56 | U64 => ("uint64", quote! { #field_ident }),
+            I40 | I48 | I56 | I64 => ("int64", quote! { #field_ident }),
+            U128 | I128 => ("bytes", quote! { #field_ident.to_be_bytes().as_ptr() }),
+            F32 => ("float", quote! { #field_ident }),
+            F64 => ("double", quote! { #field_ident }),
+            // Wireshark applies the bitmask to the value we give, so a true value must have all
+            // the masked bits set.
+            Bool { .. } => (
+                "boolean",
+                quote! { if #field_ident { u32::MAX } else { 0 } },
+            ),
+            String { .. } => (
+                "string",
+                quote! { wsdf::to_ui_cstring(#field_ident).as_ptr() },
+            ),
+            // Addresses are given to Wireshark in the same layout as they appear in the packet.
+            Ipv4 => ("ipv4", quote! { u32::from_ne_bytes(#field_ident.octets()) }),
+            Ipv6 => (
+                "ipv6",
+                quote! { &wsdf::epan_sys::ws_in6_addr { bytes: #field_ident.octets() } },
+            ),
+            Ether => ("ether", quote! { #field_ident.0.as_ptr() }),
+            Eui64 => ("eui64", quote! { u64::from_be_bytes(#field_ident.0) }),
+            Guid => (
+                "guid",
+                quote! { &wsdf::epan_sys::e_guid_t::from(#field_ident) },
+            ),
+            AbsoluteTime(_) | NtpTime => (
+                "time",
+                quote! { &mut wsdf::types::nstime_from_time(#field_ident) },
+            ),
+            RelativeTime(_) => (
+                "time",
+                quote! { &mut wsdf::types::nstime_from_duration(#field_ident) },
+            ),
             ByteArray { .. } => ("bytes", quote! { #field_ident.as_ptr() }),
+            Varint { .. } => unreachable!("varints are decoded into integers"),
         };
         let proto_tree_add_func_ident = format_ident!("proto_tree_add_{}_format_value", type_name);
 
         let call_decode_fn = quote! {
             let #WSDF_UI_STR = wsdf::tap::handle_decode_with(&#WSDF_TAP_CTX, #decode_fn_ident);
-            let #WSDF_UI_STR = std::ffi::CString::new(
-                std::string::ToString::to_string(&#WSDF_UI_STR),
-            ).unwrap(); // @todo: handle unwrap here
+            let #WSDF_UI_STR = wsdf::to_ui_cstring(&#WSDF_UI_STR);
         };
         let add_node = quote! {
-            unsafe {
+            let #WSDF_ITEM = unsafe {
                 wsdf::epan_sys::#proto_tree_add_func_ident(
                     #WSDF_PARENT_NODE,
                     #WSDF_HF,
@@ -1110,8 +2127,8 @@ impl Primitive {
                     #size,
                     #value,
                     #WSDF_UI_STR.as_ptr(),
-                );
-            }
+                )
+            };
         };
         let incr_offset = quote! {
             #WSDF_OFFSET += #size as std::ffi::c_int;
@@ -1129,16 +2146,44 @@ impl Primitive {
             || !self.taps.is_empty()
             || self.decode_with.is_some()
             || self.should_save
+            || self.trim
     }
 
     /// Produces code to get the value of the field and store in in a variable.
-    fn emit_field(&self, field_ident: &syn::Ident) -> Option<proc_macro2::TokenStream> {
+    fn emit_field(
+        &self,
+        field_ident: &syn::Ident,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
         use PrimitiveType::*;
 
         if !self.should_emit() {
             return None;
         }
 
+        if let Some(bits) = self.bits {
+            let bit_start = bit_start();
+            let ws_enc = self.ws_enc();
+            let get_bits = quote! {
+                unsafe {
+                    wsdf::epan_sys::tvb_get_bits64(
+                        #WSDF_TVB,
+                        #bit_start,
+                        #bits as std::ffi::c_int,
+                        #ws_enc,
+                    )
+                }
+            };
+            return Some(match self.typ {
+                Bool { .. } => quote! { let #field_ident = #get_bits != 0; },
+                _ => {
+                    let int_type = self.typ.int_type();
+                    quote! { let #field_ident = #get_bits as #int_type; }
+                }
+            });
+        }
+
         // A closure, for convenience, to grab an integer value from the TVB
         let get_int = |typ: &str| {
             let ws_enc = match typ {
@@ -1160,20 +2205,102 @@ impl Primitive {
         let ret = match &self.typ {
             U8 => get_int("uint8"),
             U16 => get_int("uint16"),
+            U24 => get_int("uint24"),
             U32 => get_int("uint32"),
+            U40 => get_int("uint40"),
+            U48 => get_int("uint48"),
+            U56 => get_int("uint56"),
             U64 => get_int("uint64"),
             I8 => get_int("gint8"),
             I16 => get_int("gint16"),
+            I24 => get_int("gint24"),
             I32 => get_int("gint32"),
+            I40 => get_int("gint40"),
+            I48 => get_int("gint48"),
+            I56 => get_int("gint56"),
             I64 => get_int("gint64"),
+            // Floats are always fetched with an encoding, since they are at least four bytes wide.
+            F32 => get_int("ieee_float"),
+            F64 => get_int("ieee_double"),
+            Bool { size, mask } => {
+                let get_int = get_int(match size {
+                    1 => "uint8",
+                    2 => "uint16",
+                    4 => "uint32",
+                    _ => "uint64",
+                });
+                let mask = if *mask == 0 { u64::MAX } else { *mask };
+                quote! {
+                    #get_int
+                    let #field_ident = #field_ident as u64 & #mask != 0;
+                }
+            }
+            String { size } => {
+                // The length prefix, if any, is not part of the string itself.
+                let (skip, len): (usize, syn::Expr) = match size {
+                    StringSize::Prefixed(n) => (*n, parse_quote! { (#WSDF_FIELD_SIZE - #n) }),
+                    _ => (0, self.typ.size_expr()),
+                };
+                let ws_enc = self.ws_enc();
+                let trim = self.trim.then(|| quote! { .trim() });
+                quote! {
+                    let #field_ident = unsafe {
+                        wsdf::get_string(
+                            #WSDF_PINFO,
+                            #WSDF_TVB,
+                            #WSDF_START + #WSDF_OFFSET + #skip as std::ffi::c_int,
+                            #len as std::ffi::c_int,
+                            #ws_enc,
+                        )
+                    }#trim;
+                }
+            }
+            // The varint has already been decoded, and checked to fit, after finding its size.
+            Varint { .. } => quote! {
+                let #field_ident = #WSDF_VARINT;
+            },
+            // An NTP timestamp in 1968 or 1969 is before the Unix epoch, which not every platform
+            // can represent.
+            NtpTime => {
+                let ws_enc = self.ws_enc();
+                let msg = format!("{field_name} cannot be represented as a time");
+                quote! {
+                    let #field_ident = match unsafe {
+                        wsdf::types::get_ntp_time(#WSDF_TVB, #WSDF_START + #WSDF_OFFSET, #ws_enc)
+                    } {
+                        std::option::Option::Some(time) => time,
+                        std::option::Option::None => {
+                            wsdf::expert::add_malformed(
+                                #WSDF_PINFO,
+                                #WSDF_PARENT_NODE,
+                                #WSDF_TVB,
+                                *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                                #WSDF_START + #WSDF_OFFSET,
+                                #msg,
+                            );
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
+                    };
+                }
+            }
+            U128 | I128 | Ipv4 | Ipv6 | Ether | Eui64 | Guid | AbsoluteTime(_)
+            | RelativeTime(_) => {
+                let ws_enc = self.ws_enc();
+                let func_name = format_ident!("get_{}", self.typ.name());
+                quote! {
+                    let #field_ident = unsafe {
+                        wsdf::types::#func_name(#WSDF_TVB, #WSDF_START + #WSDF_OFFSET, #ws_enc)
+                    };
+                }
+            }
             ByteArray { size, .. } => {
                 let size_expr = size.as_syn_expr();
                 let start_idx: syn::Expr = parse_quote! {
                     (#WSDF_START + #WSDF_OFFSET) as usize
                 };
 
+                // The bounds have already been checked, so slicing is fine here.
                 quote! {
-                    debug_assert!(#start_idx+#size_expr <= #WSDF_TVB_BUF.len());
                     let #field_ident = &#WSDF_TVB_BUF[#start_idx..#start_idx+#size_expr];
                 }
             }
@@ -1191,16 +2318,31 @@ impl Primitive {
             return None;
         }
 
-        let add_fn = match self.typ {
+        let typ = match &self.typ {
+            Varint { typ, .. } => typ.as_ref(),
+            typ => typ,
+        };
+        let add_fn = match typ {
             U8 => quote! { insert_u8 },
             U16 => quote! { insert_u16 },
-            U32 => quote! { insert_u32 },
-            U64 => quote! { insert_u64 },
+            U24 | U32 => quote! { insert_u32 },
+            U40 | U48 | U56 | U64 => quote! { insert_u64 },
             I8 => quote! { insert_i8 },
             I16 => quote! { insert_i16 },
-            I32 => quote! { insert_i32 },
-            I64 => quote! { insert_i64 },
+            I24 | I32 => quote! { insert_i32 },
+            I40 | I48 | I56 | I64 => quote! { insert_i64 },
+            F32 => quote! { insert_f32 },
+            F64 => quote! { insert_f64 },
+            Bool { .. } => quote! { insert_bool },
+            String { .. } => quote! { insert_str },
+            AbsoluteTime(_) | NtpTime => quote! { insert_time },
+            RelativeTime(_) => quote! { insert_duration },
+            U128 | I128 | Ipv4 | Ipv6 | Ether | Eui64 | Guid => {
+                let insert_fn = format_ident!("insert_{}", typ.name());
+                quote! { #insert_fn }
+            }
             ByteArray { .. } => quote! { insert_bytes },
+            Varint { .. } => unreachable!("varints are decoded into integers"),
         };
 
         Some(parse_quote! {
@@ -1215,6 +2357,12 @@ impl Collection {
             typ,
             hidden: opts.hidden.unwrap_or(false),
             taps: opts.taps.clone(),
+            byte_order: match (opts.byte_order, EncFn::new(opts)) {
+                (Some(order), _) => Some(format_ws_enc(order.ws_enc())),
+                (None, Some(enc_fn)) => Some(enc_fn.call()),
+                (None, None) => None,
+            },
+            enc_fn: EncFn::new(opts),
         }
     }
 
@@ -1247,7 +2395,14 @@ impl Collection {
         root_ident: &syn::Ident,
     ) -> proc_macro2::TokenStream {
         // @todo: handle hidden fields
-        self.typ.add_to_tree(field_ident, field_name, root_ident)
+        let add_to_tree = self.typ.add_to_tree(field_ident, field_name, root_ident);
+        match &self.byte_order {
+            Some(byte_order) => quote! {{
+                let #WSDF_BYTE_ORDER: u32 = #byte_order;
+                #add_to_tree
+            }},
+            None => add_to_tree,
+        }
     }
 
     fn register_user_type(
@@ -1297,58 +2452,526 @@ impl Collection {
 }
 
 impl PrimitiveType {
-    fn size_expr(&self) -> syn::Expr {
+    /// Builds a bool type from the `width` and `mask` options of a field.
+    fn new_bool(typ: &syn::Type, opts: &FieldOptions) -> syn::Result<Self> {
+        let width = match &opts.width {
+            None => PrimitiveType::U8,
+            Some(ident) => match Self::from_unsigned_ident(ident) {
+                Some(width) => width,
+                None => return make_err(ident, "expected width to be an unsigned integer type"),
+            },
+        };
+        let mask = opts.mask.unwrap_or(0);
+        if mask & !width.unsigned_max() != 0 {
+            return make_err(typ, "mask does not fit in the field's width");
+        }
+        let size = match width {
+            PrimitiveType::U8 => 1,
+            PrimitiveType::U16 => 2,
+            PrimitiveType::U32 => 4,
+            _ => 8,
+        };
+        Ok(PrimitiveType::Bool { size, mask })
+    }
+
+    /// Builds a string type from the `size`, `len_field`, `nul_terminated` and `len_prefix`
+    /// options of a field. Exactly one of them must be given.
+    fn new_string(typ: &syn::Type, opts: &FieldOptions) -> syn::Result<Self> {
+        let mut sizes = Vec::new();
+        if let Some(n) = opts.size {
+            sizes.push(StringSize::Static(n));
+        }
+        if opts.nul_terminated.unwrap_or(false) {
+            sizes.push(StringSize::NulTerminated);
+        }
+        if let Some(ident) = &opts.len_prefix {
+            let n = match Self::from_unsigned_ident(ident) {
+                Some(PrimitiveType::U8) => 1,
+                Some(PrimitiveType::U16) => 2,
+                Some(PrimitiveType::U32) => 4,
+                _ => return make_err(ident, "expected len_prefix to be u8, u16 or u32"),
+            };
+            sizes.push(StringSize::Prefixed(n));
+        }
+        // A len_field on a list of strings is the number of strings, so it only sizes the
+        // string if nothing else does.
+        match (sizes.is_empty(), SizeHint::from_opts(opts)?) {
+            (true, Some(SizeHint::Field(ident))) => sizes.push(StringSize::Field(ident)),
+            (true, Some(SizeHint::Expr(len))) => sizes.push(StringSize::Expr(len)),
+            _ => (),
+        }
+        match sizes.len() {
+            0 => make_err(
+                typ,
+                "unable to determine size of this string, expected one of size, len_field, len, nul_terminated or len_prefix",
+            ),
+            1 => Ok(PrimitiveType::String {
+                size: sizes.pop().unwrap(),
+            }),
+            _ => make_err(typ, "conflicting indications of size for this string"),
+        }
+    }
+
+    /// A lowercase name for 128-bit integers, address and time types, used to build the names of functions dealing
+    /// with them.
+    fn name(&self) -> std::string::String {
+        use PrimitiveType::*;
+        match self {
+            U128 => "u128".to_string(),
+            I128 => "i128".to_string(),
+            Ipv4 => "ipv4".to_string(),
+            Ipv6 => "ipv6".to_string(),
+            Ether => "ether".to_string(),
+            Eui64 => "eui64".to_string(),
+            Guid => "guid".to_string(),
+            AbsoluteTime(unit) => format!("epoch_{}", unit.name()),
+            NtpTime => "ntp_time".to_string(),
+            RelativeTime(unit) => format!("duration_{}", unit.name()),
+            _ => unreachable!("only 128-bit integers, address and time types have a name"),
+        }
+    }
+
+    /// Whether this is an integer which Wireshark can show as a number.
+    pub(crate) fn is_integer(&self) -> bool {
+        use PrimitiveType::*;
+        self.is_unsigned()
+            || matches!(
+                self,
+                I8 | I16 | I24 | I32 | I40 | I48 | I56 | I64 | Varint { .. }
+            )
+    }
+
+    /// Whether the decoded value implements `Display`. Times are held in a `SystemTime` or
+    /// `Duration`, and bytes in a slice, which do not.
+    pub(crate) fn is_display(&self) -> bool {
+        use PrimitiveType::*;
+        !matches!(
+            self,
+            AbsoluteTime(_) | RelativeTime(_) | NtpTime | ByteArray { .. }
+        )
+    }
+
+    /// Whether this is an unsigned integer of a fixed size.
+    fn is_unsigned(&self) -> bool {
+        use PrimitiveType::*;
+        matches!(self, U8 | U16 | U24 | U32 | U40 | U48 | U56 | U64)
+    }
+
+    /// The Rust type which holds the value of an integer.
+    fn int_type(&self) -> proc_macro2::TokenStream {
+        use PrimitiveType::*;
+        match self {
+            U8 => quote! { u8 },
+            U16 => quote! { u16 },
+            U24 | U32 => quote! { u32 },
+            U40 | U48 | U56 | U64 => quote! { u64 },
+            I8 => quote! { i8 },
+            I16 => quote! { i16 },
+            I24 | I32 => quote! { i32 },
+            I40 | I48 | I56 | I64 => quote! { i64 },
+            _ => unreachable!("only fixed size integers have an integer type"),
+        }
+    }
+
+    /// Builds a varint type out of the integer type it is decoded into, e.g. `Varint<u32>`.
+    fn new_varint(segment: &syn::PathSegment, sdnv: bool) -> syn::Result<Self> {
+        use PrimitiveType::*;
+
+        let inner_type = generic_arg(segment)?;
+        let typ = match DataType::from_syn_type(inner_type, &FieldOptions::default())? {
+            DataType::Primitive(Primitive { typ, .. })
+                if typ.is_integer() && !matches!(typ, Varint { .. }) =>
+            {
+                typ
+            }
+            _ => return make_err(inner_type, "expected a fixed size integer type"),
+        };
+        if sdnv && !typ.is_unsigned() {
+            return make_err(inner_type, "expected an unsigned integer type");
+        }
+        Ok(Varint {
+            typ: Box::new(typ),
+            sdnv,
+        })
+    }
+
+    /// The `ENC_TIME_*` encoding of time types.
+    fn time_enc(&self) -> Option<&'static str> {
+        match self {
+            PrimitiveType::AbsoluteTime(unit) | PrimitiveType::RelativeTime(unit) => {
+                Some(unit.ws_enc())
+            }
+            PrimitiveType::NtpTime => Some("ENC_TIME_NTP"),
+            _ => None,
+        }
+    }
+
+    /// The bitmask to register the field's header field with.
+    pub(crate) fn bitmask(&self) -> u64 {
+        match self {
+            PrimitiveType::Bool { mask, .. } => *mask,
+            _ => 0,
+        }
+    }
+
+    /// Maps the identifier of an unsigned integer type, e.g. `u16`, to its primitive type.
+    pub(crate) fn from_unsigned_ident(ident: &syn::Ident) -> Option<Self> {
+        use PrimitiveType::*;
+        match ident.to_string().as_str() {
+            "u8" => Some(U8),
+            "u16" => Some(U16),
+            "u32" => Some(U32),
+            "u64" => Some(U64),
+            _ => None,
+        }
+    }
+
+    /// The largest value of an unsigned integer type. Any other type is treated as 64 bits wide.
+    pub(crate) fn unsigned_max(&self) -> u64 {
+        use PrimitiveType::*;
+        match self {
+            U8 => u8::MAX as u64,
+            U16 => u16::MAX as u64,
+            U32 => u32::MAX as u64,
+            _ => u64::MAX,
+        }
+    }
+
+    pub(crate) fn size_expr(&self) -> syn::Expr {
         use PrimitiveType::*;
         let n = match self {
             U8 | I8 => 1,
             U16 | I16 => 2,
-            U32 | I32 => 4,
-            U64 | I64 => 8,
+            U24 | I24 => 3,
+            U32 | I32 | F32 | Ipv4 => 4,
+            U40 | I40 => 5,
+            U48 | I48 | Ether => 6,
+            U56 | I56 => 7,
+            U64 | I64 | F64 | Eui64 => 8,
+            U128 | I128 | Ipv6 | Guid => 16,
+            AbsoluteTime(unit) | RelativeTime(unit) => unit.size(),
+            NtpTime => 8,
+            Bool { size, .. } => *size,
+            String { size } => match size {
+                StringSize::Static(n) => *n,
+                StringSize::Field(ident) => return parse_quote! { (#ident as std::ffi::c_int) },
+                StringSize::Expr(_) => return parse_quote! { (#WSDF_LEN as std::ffi::c_int) },
+                StringSize::NulTerminated | StringSize::Prefixed(_) => {
+                    return parse_quote! { (#WSDF_FIELD_SIZE as std::ffi::c_int) }
+                }
+            },
+            Varint { .. } => return parse_quote! { (#WSDF_FIELD_SIZE as std::ffi::c_int) },
             ByteArray { size, .. } => match size {
                 SizeHint::Static(n) => *n,
-                SizeHint::Field(ident) => return parse_quote! { #ident },
+                SizeHint::Field(ident) | SizeHint::Bytes(ident) => return parse_quote! { #ident },
+                SizeHint::Expr(_) => return parse_quote! { #WSDF_LEN },
+                size => {
+                    let size = size.as_syn_expr();
+                    return parse_quote! { (#size as std::ffi::c_int) };
+                }
             },
         };
         parse_quote! { #n as std::ffi::c_int }
     }
 
-    fn default_ws_type(&self) -> &'static str {
+    /// Produces code which finds the size of the field, if it can only be known by looking at the
+    /// packet. The size is kept in WSDF_FIELD_SIZE, and the value of a varint in WSDF_VARINT.
+    fn compute_size(
+        &self,
+        ws_enc: &syn::Expr,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
+        let pos: syn::Expr = parse_quote! { (#WSDF_START + #WSDF_OFFSET) as usize };
+        // A varint which has not ended by its maximum length is malformed, rather than cut off.
+        let overlong = |max_len: proc_macro2::TokenStream| {
+            let msg = format!("{field_name} is longer than a varint may be");
+            quote! {
+                0 if remaining < #max_len => remaining + 1,
+                0 => {
+                    wsdf::expert::add_malformed(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        #msg,
+                    );
+                    break #WSDF_DISSECT_BLOCK false;
+                }
+            }
+        };
+        match self {
+            // The width of the NUL depends on the encoding.
+            PrimitiveType::String {
+                size: StringSize::NulTerminated,
+            } => Some(quote! {
+                let #WSDF_FIELD_SIZE: usize = wsdf::nul_terminated_size(#WSDF_TVB_BUF, #pos, #ws_enc);
+            }),
+            // Similarly, if the prefix itself is cut off, the bounds check fails on the prefix.
+            PrimitiveType::String {
+                size: StringSize::Prefixed(n),
+            } => {
+                let get_prefix = format_ident!(
+                    "{}",
+                    match n {
+                        1 => "tvb_get_uint8",
+                        2 => "tvb_get_uint16",
+                        _ => "tvb_get_uint32",
+                    }
+                );
+                // Like Wireshark, we take the endianness of the prefix from the string's encoding.
+                let ws_enc = (*n > 1).then(|| quote! { , #ws_enc });
+                Some(quote! {
+                    let #WSDF_FIELD_SIZE: usize = if #WSDF_TVB_BUF.len() >= #pos + #n {
+                        let len = unsafe {
+                            wsdf::epan_sys::#get_prefix(
+                                #WSDF_TVB,
+                                #WSDF_START + #WSDF_OFFSET
+                                #ws_enc
+                            )
+                        };
+                        len as usize + #n
+                    } else {
+                        #n
+                    };
+                })
+            }
+            // Wireshark throws an exception if it reads past the end of the packet, so it must
+            // not be allowed to try. A varint which is truncated, or too long, gives a size of zero.
+            PrimitiveType::Varint { sdnv: false, .. } => {
+                let overlong = overlong(quote! { wsdf::epan_sys::FT_VARINT_MAX_LEN as usize });
+                Some(quote! {
+                    let mut #WSDF_VARINT: u64 = 0;
+                    let #WSDF_FIELD_SIZE: usize = {
+                        let remaining = #WSDF_TVB_BUF.len().saturating_sub(#pos);
+                        let maxlen = remaining.min(wsdf::epan_sys::FT_VARINT_MAX_LEN as usize);
+                        match unsafe {
+                            wsdf::epan_sys::tvb_get_varint(
+                                #WSDF_TVB,
+                                #pos as std::ffi::c_uint,
+                                maxlen as std::ffi::c_uint,
+                                &mut #WSDF_VARINT,
+                                #ws_enc,
+                            )
+                        } {
+                            #overlong
+                            n => n as usize,
+                        }
+                    };
+                })
+            }
+            // A missing terminator gives a size one past the end, so that the bounds check fails.
+            PrimitiveType::ByteArray {
+                size: SizeHint::Terminator(terminator),
+                ..
+            } => Some(quote! {
+                let #WSDF_FIELD_SIZE: usize = {
+                    let rest = #WSDF_TVB_BUF.get(#pos..).unwrap_or_default();
+                    match rest.iter().position(|b| *b == #terminator) {
+                        std::option::Option::Some(n) => n + 1,
+                        std::option::Option::None => rest.len() + 1,
+                    }
+                };
+            }),
+            PrimitiveType::ByteArray {
+                size: SizeHint::Until(until),
+                ..
+            } => Some(quote! {
+                let #WSDF_FIELD_SIZE: usize = {
+                    let rest = #WSDF_TVB_BUF.get(#pos..).unwrap_or_default();
+                    (0..rest.len())
+                        .find(|n| #until(&rest[*n..]))
+                        .unwrap_or(rest.len())
+                };
+            }),
+            PrimitiveType::Varint { sdnv: true, .. } => {
+                let overlong = overlong(quote! { wsdf::types::SDNV_MAX_LEN });
+                Some(quote! {
+                    let (#WSDF_VARINT, #WSDF_FIELD_SIZE) = {
+                        let remaining = #WSDF_TVB_BUF.len().saturating_sub(#pos);
+                        let (value, size) =
+                            wsdf::types::get_sdnv(#WSDF_TVB_BUF.get(#pos..).unwrap_or_default());
+                        let size = match size {
+                            #overlong
+                            n => n,
+                        };
+                        (value, size)
+                    };
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Produces code which converts a decoded varint into the integer type it is declared as, and
+    /// stops dissection if the value does not fit. The converted value replaces WSDF_VARINT.
+    fn check_varint_fits(
+        &self,
+        field_name: &str,
+        root_ident: &syn::Ident,
+    ) -> Option<proc_macro2::TokenStream> {
+        use PrimitiveType::*;
+
+        let PrimitiveType::Varint { typ, .. } = self else {
+            return None;
+        };
+        let int_type = typ.int_type();
+        // Wireshark hands back signed values, including zigzag decoded ones, as the bits of an
+        // i64.
+        let value = if typ.is_unsigned() {
+            quote! { #WSDF_VARINT }
+        } else {
+            quote! { #WSDF_VARINT as i64 }
+        };
+        // The odd widths share a Rust type with a wider integer, so their range is checked too.
+        let bits: Option<u32> = match typ.as_ref() {
+            U24 | I24 => Some(24),
+            U40 | I40 => Some(40),
+            U48 | I48 => Some(48),
+            U56 | I56 => Some(56),
+            _ => None,
+        };
+        let in_range = bits.map(|bits| match typ.is_unsigned() {
+            true => quote! { .filter(|v| *v < 1 << #bits) },
+            false => {
+                let half = bits - 1;
+                quote! { .filter(|v| (-(1 << #half)..1 << #half).contains(v)) }
+            }
+        });
+        let type_name = match (bits, typ.is_unsigned()) {
+            (Some(bits), true) => format!("U{bits}"),
+            (Some(bits), false) => format!("I{bits}"),
+            (None, _) => int_type.to_string(),
+        };
+        let msg = format!("{field_name} does not fit in {type_name}");
+        Some(quote! {
+            let #WSDF_VARINT: #int_type = match <#int_type>::try_from(#value).ok()#in_range {
+                std::option::Option::Some(value) => value,
+                std::option::Option::None => {
+                    wsdf::expert::add_malformed(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        #msg,
+                    );
+                    break #WSDF_DISSECT_BLOCK false;
+                }
+            };
+        })
+    }
+
+    pub(crate) fn default_ws_type(&self) -> &'static str {
         use PrimitiveType::*;
         match self {
             U8 => "FT_UINT8",
             U16 => "FT_UINT16",
+            U24 => "FT_UINT24",
             U32 => "FT_UINT32",
+            U40 => "FT_UINT40",
+            U48 => "FT_UINT48",
+            U56 => "FT_UINT56",
             U64 => "FT_UINT64",
             I8 => "FT_INT8",
             I16 => "FT_INT16",
+            I24 => "FT_INT24",
             I32 => "FT_INT32",
+            I40 => "FT_INT40",
+            I48 => "FT_INT48",
+            I56 => "FT_INT56",
             I64 => "FT_INT64",
+            U128 | I128 => "FT_BYTES",
+            F32 => "FT_FLOAT",
+            F64 => "FT_DOUBLE",
+            Bool { .. } => "FT_BOOLEAN",
+            Ipv4 => "FT_IPv4",
+            Ipv6 => "FT_IPv6",
+            Ether => "FT_ETHER",
+            Eui64 => "FT_EUI64",
+            Guid => "FT_GUID",
+            AbsoluteTime(_) | NtpTime => "FT_ABSOLUTE_TIME",
+            RelativeTime(_) => "FT_RELATIVE_TIME",
+            Varint { typ, .. } => typ.default_ws_type(),
+            String { size } => match size {
+                StringSize::Static(_) => "FT_STRINGZPAD",
+                StringSize::Field(_) | StringSize::Expr(_) => "FT_STRING",
+                StringSize::NulTerminated => "FT_STRINGZ",
+                StringSize::Prefixed(_) => "FT_UINT_STRING",
+            },
             ByteArray { .. } => "FT_BYTES",
         }
     }
 
-    fn default_ws_enc(&self) -> &'static str {
+    /// Fields whose byte order matters follow the byte order of their container, which is big
+    /// endian unless the container says otherwise.
+    fn default_ws_enc(&self) -> syn::Expr {
         use PrimitiveType::*;
         match self {
-            U8 | U16 | U32 | U64 | I8 | I16 | I32 | I64 => "ENC_BIG_ENDIAN", // bigendian by default?
-            ByteArray { .. } => "ENC_NA",
+            U8
+            | U16
+            | U24
+            | U32
+            | U40
+            | U48
+            | U56
+            | U64
+            | I8
+            | I16
+            | I24
+            | I32
+            | I40
+            | I48
+            | I56
+            | I64
+            | U128
+            | I128
+            | F32
+            | F64
+            | Bool { .. }
+            | Ipv4
+            | Eui64
+            | Guid
+            | AbsoluteTime(_)
+            | NtpTime
+            | RelativeTime(_) => parse_quote! { #WSDF_BYTE_ORDER },
+            // The byte order is for the length of prefixed strings.
+            String { .. } => parse_quote! { wsdf::epan_sys::ENC_ASCII | #WSDF_BYTE_ORDER },
+            Varint { sdnv: false, .. } => format_ws_enc("ENC_VARINT_PROTOBUF"),
+            Ipv6 | Ether | Varint { sdnv: true, .. } | ByteArray { .. } => format_ws_enc("ENC_NA"),
         }
     }
 
     fn default_ws_display(&self) -> (&'static str, Option<&'static str>) {
         use PrimitiveType::*;
         match self {
-            U8 | U16 | U32 | U64 | I8 | I16 | I32 | I64 => ("BASE_DEC", None),
+            U8 | U16 | U24 | U32 | U40 | U48 | U56 | U64 | I8 | I16 | I24 | I32 | I40 | I48
+            | I56 | I64 => ("BASE_DEC", None),
+            U128
+            | I128
+            | F32
+            | F64
+            | Bool { .. }
+            | String { .. }
+            | Ipv4
+            | Ipv6
+            | Ether
+            | Eui64
+            | Guid
+            | RelativeTime(_) => ("BASE_NONE", None),
+            AbsoluteTime(_) | NtpTime => ("ABSOLUTE_TIME_LOCAL", None),
+            Varint { typ, .. } => typ.default_ws_display(),
             ByteArray { .. } => ("SEP_COLON", Some("BASE_SHOW_ASCII_PRINTABLE")),
         }
     }
 
-    fn add_to_tree(&self, ws_enc: &syn::Path, root_ident: &syn::Ident) -> proc_macro2::TokenStream {
+    fn add_to_tree(&self, ws_enc: &syn::Expr, root_ident: &syn::Ident) -> proc_macro2::TokenStream {
         use PrimitiveType::*;
 
         let add_item = |size: &syn::Expr| -> syn::Stmt {
             parse_quote! {
-                unsafe {
+                let #WSDF_ITEM = unsafe {
                     wsdf::epan_sys::proto_tree_add_item(
                         #WSDF_PARENT_NODE,
                         #WSDF_HF,
@@ -1356,16 +2979,81 @@ impl PrimitiveType {
                         #WSDF_START + #WSDF_OFFSET,
                         #size as std::ffi::c_int,
                         #ws_enc,
-                    );
-                }
+                    )
+                };
             }
         };
 
         let size = match self {
             U8 | I8 => parse_quote! { 1 },
             U16 | I16 => parse_quote! { 2 },
-            U32 | I32 => parse_quote! { 4 },
-            U64 | I64 => parse_quote! { 8 },
+            U24 | I24 => parse_quote! { 3 },
+            U32 | I32 | F32 | Ipv4 => parse_quote! { 4 },
+            U40 | I40 => parse_quote! { 5 },
+            U48 | I48 | Ether => parse_quote! { 6 },
+            U56 | I56 => parse_quote! { 7 },
+            U64 | I64 | F64 | Eui64 => parse_quote! { 8 },
+            U128 | I128 | Ipv6 | Guid => parse_quote! { 16 },
+            AbsoluteTime(unit) | RelativeTime(unit) => {
+                let size = unit.size();
+                parse_quote! { #size }
+            }
+            NtpTime => parse_quote! { 8 },
+            Bool { size, .. } => parse_quote! { #size },
+            // For a string with a length prefix, Wireshark wants the size of the prefix.
+            String {
+                size: StringSize::Prefixed(n),
+            } => {
+                let add_item = add_item(&parse_quote! { #n });
+                let size = self.size_expr();
+                return quote! {
+                    #add_item
+                    #WSDF_OFFSET += #size;
+                };
+            }
+            String { .. } => self.size_expr(),
+            Varint { sdnv: false, .. } => {
+                let size = self.size_expr();
+                return quote! {
+                    let #WSDF_ITEM = unsafe {
+                        wsdf::epan_sys::proto_tree_add_item_ret_varint(
+                            #WSDF_PARENT_NODE,
+                            #WSDF_HF,
+                            #WSDF_TVB,
+                            #WSDF_START + #WSDF_OFFSET,
+                            #size,
+                            #ws_enc,
+                            std::ptr::null_mut(),
+                            std::ptr::null_mut(),
+                        )
+                    };
+                    #WSDF_OFFSET += #size;
+                };
+            }
+            // Wireshark cannot decode SDNVs, so we give it the value ourselves.
+            Varint { typ, sdnv: true } => {
+                let size = self.size_expr();
+                let (add_fn, value) = match typ.as_ref() {
+                    U8 | U16 | U24 | U32 => (
+                        quote! { proto_tree_add_uint },
+                        quote! { #WSDF_VARINT as u32 },
+                    ),
+                    _ => (quote! { proto_tree_add_uint64 }, quote! { #WSDF_VARINT }),
+                };
+                return quote! {
+                    let #WSDF_ITEM = unsafe {
+                        wsdf::epan_sys::#add_fn(
+                            #WSDF_PARENT_NODE,
+                            #WSDF_HF,
+                            #WSDF_TVB,
+                            #WSDF_START + #WSDF_OFFSET,
+                            #size,
+                            #value,
+                        )
+                    };
+                    #WSDF_OFFSET += #size;
+                };
+            }
             ByteArray { size, subdissector } => match subdissector {
                 None => size.as_syn_expr(),
                 Some(subdissector) => {
@@ -1413,10 +3101,186 @@ impl CollectionType {
         use CollectionType::*;
 
         match self {
+            List {
+                elem,
+                len: len @ SizeHint::Bytes(_),
+            } => {
+                let check_bounds = elem.check_bounds(field_name, root_ident);
+                let elem_instructions = elem.add_to_tree(field_ident, field_name, root_ident);
+                let len = len.as_syn_expr();
+                let proto_id: syn::Expr =
+                    parse_quote! { <#root_ident as wsdf::ProtocolField>::proto_id() };
+                quote! {{
+                    let #WSDF_LIST_LEN = #len;
+                    if !wsdf::expert::check_bounds(
+                        #WSDF_PINFO,
+                        #WSDF_PARENT_NODE,
+                        #WSDF_TVB,
+                        *#proto_id,
+                        #WSDF_TVB_BUF.len(),
+                        #WSDF_START + #WSDF_OFFSET,
+                        #WSDF_LIST_LEN,
+                        #field_name,
+                    ) {
+                        break #WSDF_DISSECT_BLOCK false;
+                    }
+
+                    // The elements are dissected within a subset of the packet, so that they
+                    // cannot read past the end of the list. The offset keeps counting from the
+                    // start of the parent, so the start is moved back to line up with the subset.
+                    let #WSDF_LIST_START = (#WSDF_START + #WSDF_OFFSET) as usize;
+                    let #WSDF_TVB = unsafe {
+                        wsdf::epan_sys::tvb_new_subset_length(
+                            #WSDF_TVB,
+                            #WSDF_LIST_START as std::ffi::c_int,
+                            #WSDF_LIST_LEN as std::ffi::c_int,
+                        )
+                    };
+                    let #WSDF_TVB_BUF =
+                        &#WSDF_TVB_BUF[#WSDF_LIST_START..#WSDF_LIST_START + #WSDF_LIST_LEN];
+                    let #WSDF_START = -#WSDF_OFFSET;
+                    let #WSDF_LIST_END = #WSDF_OFFSET + #WSDF_LIST_LEN as std::ffi::c_int;
+
+                    while #WSDF_OFFSET < #WSDF_LIST_END {
+                        let #WSDF_ELEM_START = #WSDF_OFFSET;
+                        #check_bounds
+                        #elem_instructions
+                        // An element which consumes nothing would have us loop forever.
+                        if #WSDF_OFFSET == #WSDF_ELEM_START {
+                            wsdf::expert::add_malformed(
+                                #WSDF_PINFO,
+                                #WSDF_PARENT_NODE,
+                                #WSDF_TVB,
+                                *#proto_id,
+                                #WSDF_START + #WSDF_OFFSET,
+                                "empty element in list",
+                            );
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
+                    }
+                }}
+            }
+            List {
+                elem,
+                len: len @ (SizeHint::UntilEnd | SizeHint::Until(_) | SizeHint::Terminator(_)),
+            } => {
+                let check_bounds = elem.check_bounds(field_name, root_ident);
+                let proto_id: syn::Expr =
+                    parse_quote! { <#root_ident as wsdf::ProtocolField>::proto_id() };
+                let pos: syn::Expr = parse_quote! { (#WSDF_START + #WSDF_OFFSET) as usize };
+                let stop = match len {
+                    SizeHint::Until(until) => quote! {
+                        if #pos >= #WSDF_TVB_BUF.len() || #until(&#WSDF_TVB_BUF[#pos..]) {
+                            break;
+                        }
+                    },
+                    SizeHint::Terminator(terminator) => quote! {
+                        // The list must end with its terminator.
+                        if !wsdf::expert::check_bounds(
+                            #WSDF_PINFO,
+                            #WSDF_PARENT_NODE,
+                            #WSDF_TVB,
+                            *#proto_id,
+                            #WSDF_TVB_BUF.len(),
+                            #WSDF_START + #WSDF_OFFSET,
+                            1,
+                            #field_name,
+                        ) {
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
+                        if #WSDF_TVB_BUF[#pos] == #terminator {
+                            #WSDF_OFFSET += 1;
+                            break;
+                        }
+                    },
+                    _ => quote! {
+                        if #pos >= #WSDF_TVB_BUF.len() {
+                            break;
+                        }
+                    },
+                };
+
+                // Elements are labelled with their index, since there is no telling how many of them
+                // there are. Structs take the label as their subtree's, while anything else has it
+                // appended to the item it added.
+                let elem_instructions = match elem.as_ref() {
+                    DataType::Collection(Collection {
+                        typ: Struct { ident },
+                        ..
+                    }) => {
+                        let label_fmt = format!(
+                            "{} [{{}}]",
+                            field_name.replace('{', "{{").replace('}', "}}")
+                        );
+                        let call_dissect = propagate_dissect(quote! {
+                            <#ident as wsdf::ProtocolField>::dissect(
+                                #WSDF_START + #WSDF_OFFSET,
+                                #WSDF_TVB,
+                                #WSDF_PARENT_NODE,
+                                &#WSDF_PREFIX_NEXT,
+                                wsdf::VariantDispatch::None,
+                                wsdf::SubtreeLabel::new(#WSDF_ELEM_LABEL.as_ptr()),
+                                #WSDF_TVB_BUF,
+                                #WSDF_PINFO,
+                                #WSDF_PROTO_TREE_ROOT,
+                                #WSDF_FIELDS_STORE,
+                                #WSDF_BYTE_ORDER,
+                            )
+                        });
+                        quote! {
+                            let #WSDF_ELEM_LABEL = wsdf::to_ui_cstring(
+                                format!(#label_fmt, #WSDF_LIST_INDEX),
+                            );
+                            #call_dissect
+                        }
+                    }
+                    // A nested list adds many items, none of which stands for the element.
+                    DataType::Collection(Collection {
+                        typ: List { .. }, ..
+                    }) => elem.add_to_tree(field_ident, field_name, root_ident),
+                    _ => {
+                        let add_elem = elem.add_to_tree(field_ident, field_name, root_ident);
+                        quote! {
+                            let #WSDF_PREV_ITEM = unsafe { wsdf::last_child(#WSDF_PARENT_NODE) };
+                            #add_elem
+                            unsafe {
+                                let #WSDF_ITEM = wsdf::last_child(#WSDF_PARENT_NODE);
+                                if #WSDF_ITEM != #WSDF_PREV_ITEM {
+                                    wsdf::append_list_index(#WSDF_ITEM, #WSDF_LIST_INDEX);
+                                }
+                            }
+                        }
+                    }
+                };
+
+                quote! {{
+                    let mut #WSDF_LIST_INDEX = 0usize;
+                    loop {
+                        #stop
+                        let #WSDF_ELEM_START = #WSDF_OFFSET;
+                        #check_bounds
+                        #elem_instructions
+                        // An element which consumes nothing would have us loop forever.
+                        if #WSDF_OFFSET == #WSDF_ELEM_START {
+                            wsdf::expert::add_malformed(
+                                #WSDF_PINFO,
+                                #WSDF_PARENT_NODE,
+                                #WSDF_TVB,
+                                *#proto_id,
+                                #WSDF_START + #WSDF_OFFSET,
+                                "empty element in list",
+                            );
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
+                        #WSDF_LIST_INDEX += 1;
+                    }
+                }}
+            }
             List { elem, len } => {
+                let check_bounds = elem.check_bounds(field_name, root_ident);
                 let elem_instructions = elem.add_to_tree(field_ident, field_name, root_ident);
                 let len = len.as_syn_expr();
-                quote! { for i in 0..#len { #elem_instructions } }
+                quote! { for i in 0..#len { #check_bounds #elem_instructions } }
             }
             // Structs must call the corresponding ProtocolField::dissect routines. It must not
             // directly add nodes to the virtual tree in this scope, or we'll be double
@@ -1425,8 +3289,8 @@ impl CollectionType {
             // Similar for enums below.
             Struct { ident } => {
                 let subtree_label: syn::Expr = cstr!(field_name);
-                quote! {
-                    #WSDF_OFFSET += <#ident as wsdf::ProtocolField>::dissect(
+                propagate_dissect(quote! {
+                    <#ident as wsdf::ProtocolField>::dissect(
                         #WSDF_START + #WSDF_OFFSET,
                         #WSDF_TVB,
                         #WSDF_PARENT_NODE,
@@ -1437,13 +3301,14 @@ impl CollectionType {
                         #WSDF_PINFO,
                         #WSDF_PROTO_TREE_ROOT,
                         #WSDF_FIELDS_STORE,
-                    );
-                }
+                        #WSDF_BYTE_ORDER,
+                    )
+                })
             }
             Enum { ident, dispatch } => {
                 let dispatch_fn_ident = format_ident!("dispatch_{}", dispatch);
-                quote! {
-                    #WSDF_OFFSET += <#ident as wsdf::ProtocolField>::dissect(
+                propagate_dissect(quote! {
+                    <#ident as wsdf::ProtocolField>::dissect(
                         #WSDF_START + #WSDF_OFFSET,
                         #WSDF_TVB,
                         #WSDF_PARENT_NODE,
@@ -1454,26 +3319,36 @@ impl CollectionType {
                         #WSDF_PINFO,
                         #WSDF_PROTO_TREE_ROOT,
                         #WSDF_FIELDS_STORE,
-                    );
-                }
+                        #WSDF_BYTE_ORDER,
+                    )
+                })
             }
             Bytes { consume } => match consume {
                 ConsumeBytes::ConsumeWith(consume_fn) => {
                     let call_consume_fn = quote! {
-                        debug_assert!(#WSDF_TAP_CTX.offset <= #WSDF_TVB_BUF.len());
-
                         let (#WSDF_NR_BYTES_CONSUMED, #WSDF_UI_STR) =
                             wsdf::tap::handle_consume_with(&#WSDF_TAP_CTX, #consume_fn);
-                        let #WSDF_UI_STR =
-                            std::ffi::CString::new(std::string::ToString::to_string(&#WSDF_UI_STR))
-                            .unwrap(); // @todo: handle the unwrap
+                        let #WSDF_UI_STR = wsdf::to_ui_cstring(&#WSDF_UI_STR);
+
+                        // The user's function may claim more bytes than we actually have.
+                        if !wsdf::expert::check_bounds(
+                            #WSDF_PINFO,
+                            #WSDF_PARENT_NODE,
+                            #WSDF_TVB,
+                            *<#root_ident as wsdf::ProtocolField>::proto_id(),
+                            #WSDF_TVB_BUF.len(),
+                            #WSDF_START + #WSDF_OFFSET,
+                            #WSDF_NR_BYTES_CONSUMED,
+                            #field_name,
+                        ) {
+                            break #WSDF_DISSECT_BLOCK false;
+                        }
 
                         // We need a pointer into some offset of the TVB. This is used as an
                         // argument to the proto_tree_add_XXX function.
-                        let #WSDF_VALUE_P = if #WSDF_TAP_CTX.offset == #WSDF_TVB_BUF.len() {
-                            std::ptr::null()
-                        } else {
-                            &#WSDF_TVB_BUF[#WSDF_TAP_CTX.offset as usize] as *const u8
+                        let #WSDF_VALUE_P = match #WSDF_TVB_BUF.get(#WSDF_TAP_CTX.offset) {
+                            std::option::Option::Some(b) => b as *const u8,
+                            std::option::Option::None => std::ptr::null(),
                         };
                     };
                     let add_node = quote! {
@@ -1506,18 +3381,178 @@ impl CollectionType {
     }
 }
 
+/// Wraps a call to some `ProtocolField::dissect` function, such that the offset is incremented by
+/// the bytes consumed, and dissection stops if the callee found the packet to be malformed.
+fn propagate_dissect(call_dissect: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote! {
+        match #call_dissect {
+            std::result::Result::Ok(n) => #WSDF_OFFSET += n,
+            std::result::Result::Err(n) => {
+                #WSDF_OFFSET += n;
+                break #WSDF_DISSECT_BLOCK false;
+            }
+        }
+    }
+}
+
+/// The position of the bit cursor within the TVB, counted in bits as Wireshark expects it.
+fn bit_start() -> syn::Expr {
+    parse_quote! { (#WSDF_START as u32 * 8 + #WSDF_BIT_OFFSET as u32) }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum SizeHint {
     Static(usize),
     Field(syn::Ident),
+    /// For lists only. A previous field which gives the size of the list in bytes, rather than
+    /// its number of elements.
+    Bytes(syn::Ident),
+    /// Computed from previous fields.
+    Expr(LenExpr),
+    /// Runs until the end of the packet, or of the enclosing list.
+    UntilEnd,
+    /// Runs until a function, given the remaining bytes, returns true.
+    Until(syn::Path),
+    /// Runs until a byte with this value, which is consumed. Bytes include it, while lists do not
+    /// show it.
+    Terminator(u8),
 }
 
 impl SizeHint {
+    /// The size given by `until_end`, `until` or `terminator`, if any.
+    fn open_ended(segment: &syn::PathSegment, opts: &FieldOptions) -> syn::Result<Option<Self>> {
+        let mut hints = Vec::new();
+        if opts.until_end == Some(true) {
+            hints.push(SizeHint::UntilEnd);
+        }
+        if let Some(until) = &opts.until {
+            hints.push(SizeHint::Until(until.clone()));
+        }
+        if let Some(terminator) = opts.terminator {
+            hints.push(SizeHint::Terminator(terminator));
+        }
+        match hints.len() {
+            0 | 1 => Ok(hints.pop()),
+            _ => make_err(
+                segment,
+                "expected only one of until_end, until and terminator",
+            ),
+        }
+    }
+
+    /// The size given by `len_field` or `len`, if any.
+    fn from_opts(opts: &FieldOptions) -> syn::Result<Option<Self>> {
+        match (&opts.size_hint, &opts.len_expr) {
+            (Some(field), Some(_)) => make_err(field, "len_field and len cannot be combined"),
+            (Some(field), None) => Ok(Some(SizeHint::Field(field.clone()))),
+            (None, Some(expr)) => Ok(Some(SizeHint::Expr(LenExpr::new(expr)?))),
+            (None, None) => Ok(None),
+        }
+    }
+
     fn as_syn_expr(&self) -> syn::Expr {
         match self {
             SizeHint::Static(n) => parse_quote! { #n },
-            SizeHint::Field(field) => parse_quote! { #field as usize },
+            SizeHint::Field(field) | SizeHint::Bytes(field) => parse_quote! { #field as usize },
+            SizeHint::Expr(_) => parse_quote! { #WSDF_LEN },
+            SizeHint::UntilEnd => parse_quote! {
+                #WSDF_TVB_BUF.len().saturating_sub((#WSDF_START + #WSDF_OFFSET) as usize)
+            },
+            // Only bytes get here, since open ended lists are dissected element by element. Their
+            // size has already been found by scanning the packet.
+            SizeHint::Until(_) | SizeHint::Terminator(_) => parse_quote! { #WSDF_FIELD_SIZE },
+        }
+    }
+}
+
+/// A size computed from prior integer fields, e.g. `ihl * 4 - 20`. Only `+`, `-`, `*`, `/` and `%`
+/// over fields and integer literals are allowed, so that every step can be checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LenExpr(Box<syn::Expr>);
+
+impl LenExpr {
+    pub(crate) fn new(expr: &syn::Expr) -> syn::Result<Self> {
+        Self::validate(expr)?;
+        Ok(Self(Box::new(expr.clone())))
+    }
+
+    fn validate(expr: &syn::Expr) -> syn::Result<()> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(lit),
+                ..
+            }) => lit.base10_parse::<u64>().map(|_| ()),
+            syn::Expr::Path(path) if path.path.get_ident().is_some() => Ok(()),
+            syn::Expr::Paren(paren) => Self::validate(&paren.expr),
+            syn::Expr::Binary(binary) if Self::checked_method(&binary.op).is_some() => {
+                Self::validate(&binary.left)?;
+                Self::validate(&binary.right)
+            }
+            _ => make_err(
+                expr,
+                "expected integer fields and literals combined with +, -, *, / or %",
+            ),
+        }
+    }
+
+    fn checked_method(op: &syn::BinOp) -> Option<syn::Ident> {
+        let method = match op {
+            syn::BinOp::Add(_) => "checked_add",
+            syn::BinOp::Sub(_) => "checked_sub",
+            syn::BinOp::Mul(_) => "checked_mul",
+            syn::BinOp::Div(_) => "checked_div",
+            syn::BinOp::Rem(_) => "checked_rem",
+            _ => return None,
+        };
+        Some(format_ident!("{}", method))
+    }
+
+    /// The fields referenced by the expression.
+    pub(crate) fn fields(&self) -> Vec<&syn::Ident> {
+        fn collect<'a>(expr: &'a syn::Expr, fields: &mut Vec<&'a syn::Ident>) {
+            match expr {
+                syn::Expr::Path(path) => fields.extend(path.path.get_ident()),
+                syn::Expr::Paren(paren) => collect(&paren.expr, fields),
+                syn::Expr::Binary(binary) => {
+                    collect(&binary.left, fields);
+                    collect(&binary.right, fields);
+                }
+                _ => (),
+            }
+        }
+        let mut fields = Vec::new();
+        collect(&self.0, &mut fields);
+        fields
+    }
+
+    /// An expression evaluating to `Option<u64>`, which is None if any step overflows, goes
+    /// negative or divides by zero.
+    pub(crate) fn checked(&self) -> syn::Expr {
+        fn checked(expr: &syn::Expr) -> syn::Expr {
+            match expr {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(lit),
+                    ..
+                }) => {
+                    let n = lit.base10_parse::<u64>().unwrap_or_default(); // already validated
+                    parse_quote! { Some(#n) }
+                }
+                syn::Expr::Paren(paren) => checked(&paren.expr),
+                syn::Expr::Binary(binary) => {
+                    let left = checked(&binary.left);
+                    let right = checked(&binary.right);
+                    let method = LenExpr::checked_method(&binary.op);
+                    parse_quote! { #left.zip(#right).and_then(|(l, r)| l.#method(r)) }
+                }
+                syn::Expr::Path(path) => {
+                    let var = format_ident!("{}", path.to_token_stream().to_wsdf_snake_case());
+                    parse_quote! { u64::try_from(#var).ok() }
+                }
+                // Anything else has been rejected by `LenExpr::validate`.
+                _ => parse_quote! { None },
+            }
         }
+        checked(&self.0)
     }
 }
 
@@ -1525,3 +3560,17 @@ const WSDF_HF: IdentHelper = IdentHelper("__wsdf_hf");
 const WSDF_UI_STR: IdentHelper = IdentHelper("__wsdf_ui_str");
 const WSDF_NR_BYTES_CONSUMED: IdentHelper = IdentHelper("__wsdf_nr_consumed");
 const WSDF_VALUE_P: IdentHelper = IdentHelper("__wsdf_value_p");
+const WSDF_STRINGS: IdentHelper = IdentHelper("__wsdf_strings");
+const WSDF_FIELD_SIZE: IdentHelper = IdentHelper("__wsdf_field_size");
+const WSDF_VARINT: IdentHelper = IdentHelper("__wsdf_varint");
+const WSDF_STRINGS_DISPLAY: IdentHelper = IdentHelper("__wsdf_strings_display");
+const WSDF_LIST_LEN: IdentHelper = IdentHelper("__wsdf_list_len");
+const WSDF_LEN: IdentHelper = IdentHelper("__wsdf_len");
+const WSDF_VIRTUAL_OFFSET: IdentHelper = IdentHelper("__wsdf_virtual_offset");
+const WSDF_LIST_INDEX: IdentHelper = IdentHelper("__wsdf_list_index");
+const WSDF_ELEM_LABEL: IdentHelper = IdentHelper("__wsdf_elem_label");
+const WSDF_LIST_START: IdentHelper = IdentHelper("__wsdf_list_start");
+const WSDF_LIST_END: IdentHelper = IdentHelper("__wsdf_list_end");
+const WSDF_ELEM_START: IdentHelper = IdentHelper("__wsdf_elem_start");
+const WSDF_ITEM: IdentHelper = IdentHelper("__wsdf_item");
+const WSDF_PREV_ITEM: IdentHelper = IdentHelper("__wsdf_prev_item");
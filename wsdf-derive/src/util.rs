@@ -28,6 +28,23 @@ macro_rules! get_lit {
     };
 }
 
+/// Gives a `'_` for each lifetime parameter of a type, e.g. `<'_>` for `Foo<'a>`, so the type can be
+/// named in an impl without clashing with the lifetimes used inside of it. Other kinds of generic
+/// parameters are not supported.
+pub(crate) fn elided_lifetimes(generics: &syn::Generics) -> syn::Result<proc_macro2::TokenStream> {
+    if generics.params.is_empty() {
+        return Ok(quote! {});
+    }
+    let mut elided = Vec::with_capacity(generics.params.len());
+    for param in &generics.params {
+        match param {
+            syn::GenericParam::Lifetime(_) => elided.push(quote! { '_ }),
+            _ => return make_err(param, "only lifetime parameters are supported"),
+        }
+    }
+    Ok(quote! { <#(#elided),*> })
+}
+
 pub(crate) fn get_lit_str(expr: &syn::Expr) -> syn::Result<&syn::LitStr> {
     get_lit!(expr, Str, "expected a string literal")
 }
@@ -74,6 +91,7 @@ impl quote::ToTokens for IdentHelper<'_> {
 
 pub(crate) const WSDF_PARENT_NODE: IdentHelper = IdentHelper("__wsdf_parent");
 pub(crate) const WSDF_OFFSET: IdentHelper = IdentHelper("__wsdf_offset");
+pub(crate) const WSDF_BIT_OFFSET: IdentHelper = IdentHelper("__wsdf_bit_offset");
 pub(crate) const WSDF_DISSECTOR_TABLE: IdentHelper = IdentHelper("__wsdf_dissector_table");
 pub(crate) const WSDF_PROTO_ID: IdentHelper = IdentHelper("__wsdf_proto_id");
 pub(crate) const WSDF_TVB: IdentHelper = IdentHelper("__wsdf_tvb");
@@ -89,8 +107,23 @@ pub(crate) const WSDF_FIELD_IDENT: IdentHelper = IdentHelper("__wsdf_field_ident
 pub(crate) const WSDF_FIELD_BLURB: IdentHelper = IdentHelper("__wsdf_field_blurb");
 pub(crate) const WSDF_PROTO_TREE_ROOT: IdentHelper = IdentHelper("__wsdf_proto_tree_root");
 pub(crate) const WSDF_FIELDS_STORE: IdentHelper = IdentHelper("__wsdf_fields_store");
+pub(crate) const WSDF_BYTE_ORDER: IdentHelper = IdentHelper("__wsdf_byte_order");
 pub(crate) const WSDF_TAP_CTX: IdentHelper = IdentHelper("__wsdf_tap_ctx");
 
+/// Like `IdentHelper`, but for labels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LabelHelper<'a>(pub(crate) &'a str);
+
+impl quote::ToTokens for LabelHelper<'_> {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        syn::Lifetime::new(self.0, proc_macro2::Span::call_site()).to_tokens(tokens);
+    }
+}
+
+/// Labels the block holding the dissection code of a type's fields. Breaking out of it with
+/// `false` stops dissection, e.g. when the packet is malformed.
+pub(crate) const WSDF_DISSECT_BLOCK: LabelHelper = LabelHelper("'__wsdf_dissect");
+
 /// Unpacks an array or tuple expression into its individual elements. Otherwise, return the
 /// original expression.
 pub(crate) fn unpack_expr(expr: &syn::Expr) -> Vec<&syn::Expr> {
@@ -128,6 +161,70 @@ mod test_unpack_expr {
     }
 }
 
+/// Extracts the names of the arguments referenced by a format string, e.g. `["seq", "n"]` for
+/// `"Seq={seq} N={n:04}"`. Each name appears once. Positional arguments are not allowed, since
+/// there is nothing to refer to.
+pub(crate) fn format_arg_names(fmt: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let mut arg = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated `{` in format string".to_string()),
+                    }
+                }
+                let name = match arg.split_once(':') {
+                    Some((name, _)) => name.trim().to_string(),
+                    None => arg.trim().to_string(),
+                };
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err("expected each `{...}` to name a field".to_string());
+                }
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '}' => return Err("unmatched `}` in format string".to_string()),
+            _ => (),
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod test_format_arg_names {
+    use super::*;
+
+    #[test]
+    fn extracts_named_args() {
+        assert_eq!(
+            format_arg_names("Seq={seq} Msgs={n:04} {{raw}} again={seq}"),
+            Ok(vec!["seq".to_string(), "n".to_string()]),
+        );
+    }
+
+    #[test]
+    fn rejects_positional_or_malformed_args() {
+        assert!(format_arg_names("{}").is_err());
+        assert!(format_arg_names("{0}").is_err());
+        assert!(format_arg_names("{seq").is_err());
+        assert!(format_arg_names("seq}").is_err());
+    }
+}
+
 /// Produces a token stream for a null-terminated string expression. The resultant expression can
 /// be used anywhere in a quote macro.
 macro_rules! cstr {
@@ -185,9 +282,26 @@ pub(crate) fn format_ws_type(typ: &str) -> syn::Path {
     parse_quote! { wsdf::epan_sys::#unqualified }
 }
 
-pub(crate) fn format_ws_enc(enc: &str) -> syn::Path {
-    let unqualified = format_ident!("{}", enc);
-    parse_quote! { wsdf::epan_sys::#unqualified }
+/// Formats a Wireshark encoding, which may be several encodings OR-ed together, e.g.
+/// "ENC_UTF_16 | ENC_LITTLE_ENDIAN".
+pub(crate) fn format_ws_enc(enc: &str) -> syn::Expr {
+    let unqualified = enc.split('|').map(|enc| format_ident!("{}", enc.trim()));
+    parse_quote! { #(wsdf::epan_sys::#unqualified)|* }
+}
+
+#[cfg(test)]
+mod test_format_ws_enc {
+    use super::*;
+
+    #[test]
+    fn single_and_combined_encodings() {
+        let want: syn::Expr = parse_quote! { wsdf::epan_sys::ENC_LITTLE_ENDIAN };
+        assert_eq!(format_ws_enc("ENC_LITTLE_ENDIAN"), want);
+
+        let want: syn::Expr =
+            parse_quote! { wsdf::epan_sys::ENC_UTF_16 | wsdf::epan_sys::ENC_LITTLE_ENDIAN };
+        assert_eq!(format_ws_enc("ENC_UTF_16 | ENC_LITTLE_ENDIAN"), want);
+    }
 }
 
 pub(crate) trait CaseConvert {
@@ -317,6 +431,7 @@ impl From<_DissectionParams> for Punctuated<syn::FnArg, syn::Token![,]> {
             #WSDF_PINFO: *mut wsdf::epan_sys::_packet_info,
             #WSDF_PROTO_TREE_ROOT: *mut wsdf::epan_sys::_proto_node,
             #WSDF_FIELDS_STORE: &mut wsdf::FieldsStore<'a>,
+            #WSDF_BYTE_ORDER: u32,
         }
     }
 }
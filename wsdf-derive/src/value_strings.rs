@@ -0,0 +1,225 @@
+use quote::quote;
+
+use crate::attributes::*;
+use crate::util::*;
+
+/// An enum which derives ValueStrings. Each variant names a value, or a range of values, of some
+/// integer field.
+///
+/// ```ignore
+/// #[derive(ValueStrings)]
+/// #[repr(u16)]
+/// enum QType {
+///     A = 1,
+///     #[wsdf(rename = "CNAME")]
+///     Cname = 5,
+///     #[wsdf(range = (65280, 65534))]
+///     Private,
+/// }
+/// ```
+#[derive(Debug)]
+pub(crate) struct ValueStringsEnum<'a> {
+    ident: &'a syn::Ident,
+    variants: Vec<ValueString<'a>>,
+}
+
+#[derive(Debug)]
+struct ValueString<'a> {
+    ident: &'a syn::Ident,
+    opts: ValueStringOptions,
+}
+
+impl<'a> ValueStringsEnum<'a> {
+    pub(crate) fn from_input(input: &'a syn::DeriveInput) -> syn::Result<Self> {
+        let variants = match &input.data {
+            syn::Data::Enum(data) => &data.variants,
+            _ => return make_err(&input.ident, "only enums can derive ValueStrings"),
+        };
+        if variants.is_empty() {
+            return make_err(&input.ident, "expected at least one variant");
+        }
+
+        let mut ret = Vec::with_capacity(variants.len());
+        for variant in variants {
+            if !matches!(variant.fields, syn::Fields::Unit) {
+                return make_err(&variant.ident, "expected a unit variant");
+            }
+            ret.push(ValueString {
+                ident: &variant.ident,
+                opts: init_options::<ValueStringOptions>(&variant.attrs)?,
+            });
+        }
+
+        Ok(Self {
+            ident: &input.ident,
+            variants: ret,
+        })
+    }
+
+    /// Builds the `(min, max, name)` entries of the table. Values are taken from the enum's
+    /// discriminants, unless the variant specifies a range.
+    fn entries(&self) -> Vec<proc_macro2::TokenStream> {
+        let self_ident = self.ident;
+        self.variants
+            .iter()
+            .map(|variant| {
+                let variant_ident = variant.ident;
+                let name = match &variant.opts.rename {
+                    Some(rename) => rename.clone(),
+                    None => variant_ident.to_string(),
+                };
+                let name_cstr: syn::Expr = cstr!(name);
+                match variant.opts.range {
+                    Some((min, max)) => quote! { (#min, #max, #name_cstr) },
+                    None => quote! {{
+                        let value = #self_ident::#variant_ident as u64;
+                        (value, value, #name_cstr)
+                    }},
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn impl_value_strings(&self) -> proc_macro2::TokenStream {
+        let self_ident = self.ident;
+        let entries = self.entries();
+        let pref_enum = self.impl_pref_enum();
+        quote! {
+            impl wsdf::ValueStrings for #self_ident {
+                fn strings(
+                    ws_type: wsdf::epan_sys::ftenum,
+                ) -> (*const std::ffi::c_void, std::ffi::c_int) {
+                    wsdf::make_value_strings(&[#(#entries),*], ws_type)
+                }
+            }
+
+            #pref_enum
+        }
+    }
+
+    /// Lets the enum be used as a preference, as long as each variant names a single value.
+    fn impl_pref_enum(&self) -> Option<proc_macro2::TokenStream> {
+        if self
+            .variants
+            .iter()
+            .any(|variant| variant.opts.range.is_some())
+        {
+            return None;
+        }
+
+        let self_ident = self.ident;
+        let variant_idents = self
+            .variants
+            .iter()
+            .map(|variant| variant.ident)
+            .collect::<Vec<_>>();
+        let enum_vals = self.variants.iter().map(|variant| {
+            let variant_ident = variant.ident;
+            let name = variant_ident.to_wsdf_snake_case();
+            let description = match &variant.opts.rename {
+                Some(rename) => rename.clone(),
+                None => variant_ident.to_string(),
+            };
+            let name_cstr: syn::Expr = cstr!(name);
+            let description_cstr: syn::Expr = cstr!(description);
+            quote! {
+                (#name_cstr, #description_cstr, #self_ident::#variant_ident as std::ffi::c_int)
+            }
+        });
+
+        Some(quote! {
+            impl wsdf::PrefEnum for #self_ident {
+                fn enum_vals() -> *const wsdf::epan_sys::enum_val_t {
+                    wsdf::make_enum_vals(&[#(#enum_vals),*])
+                }
+
+                fn to_pref(&self) -> std::ffi::c_int {
+                    match self {
+                        #(#self_ident::#variant_idents => #self_ident::#variant_idents as std::ffi::c_int,)*
+                    }
+                }
+
+                fn from_pref(value: std::ffi::c_int) -> std::option::Option<Self> {
+                    #(
+                        if value == #self_ident::#variant_idents as std::ffi::c_int {
+                            return std::option::Option::Some(#self_ident::#variant_idents);
+                        }
+                    )*
+                    std::option::Option::None
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_value_strings_enum {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn uses_discriminants_unless_given_a_range() -> syn::Result<()> {
+        let input: syn::DeriveInput = parse_quote! {
+            enum QType {
+                A = 1,
+                #[wsdf(rename = "CNAME")]
+                Cname = 5,
+                #[wsdf(range = (65280, 65534))]
+                Private,
+            }
+        };
+        let value_strings = ValueStringsEnum::from_input(&input)?;
+        let entries = value_strings.entries();
+        assert_eq!(
+            entries[1].to_string(),
+            quote! {{
+                let value = QType::Cname as u64;
+                (value, value, concat!("CNAME", '\0').as_ptr() as *const std::ffi::c_char)
+            }}
+            .to_string(),
+        );
+        assert_eq!(
+            entries[2].to_string(),
+            quote! {
+                (65280u64, 65534u64, concat!("Private", '\0').as_ptr() as *const std::ffi::c_char)
+            }
+            .to_string(),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_single_values_can_be_preferences() -> syn::Result<()> {
+        let input: syn::DeriveInput = parse_quote! {
+            enum Mode {
+                Fast,
+                Slow,
+            }
+        };
+        assert!(ValueStringsEnum::from_input(&input)?
+            .impl_pref_enum()
+            .is_some());
+
+        let input: syn::DeriveInput = parse_quote! {
+            enum Port {
+                Http = 80,
+                #[wsdf(range = (49152, 65535))]
+                Dynamic,
+            }
+        };
+        assert!(ValueStringsEnum::from_input(&input)?
+            .impl_pref_enum()
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn variants_with_fields_are_rejected() {
+        let input: syn::DeriveInput = parse_quote! {
+            enum Foo {
+                A(u8),
+            }
+        };
+        assert!(ValueStringsEnum::from_input(&input).is_err());
+    }
+}
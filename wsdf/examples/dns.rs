@@ -1,9 +1,12 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use wsdf::tap::{Field, Offset, Packet};
-use wsdf::{version, Dispatch, Protocol, ProtocolField};
+use wsdf::{plugin, version, Dispatch, Flags, Protocol, ProtocolField, ValueStrings};
 
 version!("0.0.1", 4, 4);
+plugin!(BabyDNS);
 
 #[derive(Protocol)]
 #[wsdf(
@@ -14,7 +17,7 @@ version!("0.0.1", 4, 4);
 )]
 struct BabyDNS {
     identification: u16,
-    flags: u16,
+    flags: DnsFlags,
     #[wsdf(rename = "Number of Questions")]
     number_of_questions: u16,
     #[wsdf(rename = "Number of Answers")]
@@ -33,21 +36,66 @@ struct BabyDNS {
     additional_information: Vec<ResourceRecord>,
 }
 
+#[derive(Flags)]
+struct DnsFlags {
+    #[wsdf(mask = 0x8000, rename = "Response")]
+    qr: u16,
+    #[wsdf(mask = 0x7800, value_strings = "Opcode")]
+    opcode: u16,
+    #[wsdf(mask = 0x0400, rename = "Authoritative")]
+    aa: u16,
+    #[wsdf(mask = 0x0200, rename = "Truncated")]
+    tc: u16,
+    #[wsdf(mask = 0x0100, rename = "Recursion desired")]
+    rd: u16,
+    #[wsdf(mask = 0x0080, rename = "Recursion available")]
+    ra: u16,
+    #[wsdf(mask = 0x0070, rename = "Reserved")]
+    z: u16,
+    #[wsdf(mask = 0x000f, rename = "Reply code", value_strings = "Rcode")]
+    rcode: u16,
+}
+
+#[derive(ValueStrings)]
+enum Opcode {
+    #[wsdf(rename = "Standard query")]
+    Query = 0,
+    #[wsdf(rename = "Inverse query")]
+    IQuery = 1,
+    #[wsdf(rename = "Server status request")]
+    Status = 2,
+}
+
+#[derive(ValueStrings)]
+enum Rcode {
+    #[wsdf(rename = "No error")]
+    NoError = 0,
+    #[wsdf(rename = "Format error")]
+    FormErr = 1,
+    #[wsdf(rename = "Server failure")]
+    ServFail = 2,
+    #[wsdf(rename = "No such name")]
+    NxDomain = 3,
+    #[wsdf(rename = "Not implemented")]
+    NotImp = 4,
+    Refused = 5,
+}
+
 #[derive(ProtocolField)]
 struct Question {
     name: CharStr,
-    #[wsdf(decode_with = "decode_qtype", rename = "Type")]
+    #[wsdf(value_strings = "QType", rename = "Type")]
     type_: u16,
-    #[wsdf(decode_with = "decode_class")]
+    #[wsdf(value_strings = "Class")]
     class: u16,
 }
 
 #[derive(ProtocolField)]
 struct ResourceRecord {
     name: CharStr,
-    #[wsdf(decode_with = "decode_qtype", rename = "Type")]
+    #[wsdf(value_strings = "QType", rename = "Type")]
     type_: u16,
-    #[wsdf(decode_with = "decode_class")]
+    #[wsdf(value_strings = "Class")]
     class: u16,
     #[wsdf(rename = "TTL")]
     ttl: Seconds,
@@ -60,7 +108,7 @@ struct ResourceRecord {
 #[derive(ProtocolField, Dispatch)]
 enum Rdata {
     #[wsdf(rename = "A (Host address)")]
-    A(#[wsdf(typ = "FT_IPv4", display = "BASE_NETMASK")] u32),
+    A(wsdf::types::Ipv4),
     #[wsdf(rename = "NS (Authoritative name server)")]
     NS(
         /// Specifies a host which should be authoritative for the specified class and domain.
@@ -104,7 +152,7 @@ enum Rdata {
         mail_exchanger: CharStr,
     },
     #[wsdf(rename = "AAAA (IPv6 address)")]
-    Aaaa(#[wsdf(typ = "FT_IPv6", display = "BASE_NONE")] [u8; 16]),
+    Aaaa(wsdf::types::Ipv6),
     // To keep this example simple, we ignore the other resource records.
     Unknown(#[wsdf(consume_with = "drain_rdata")] Vec<u8>),
 }
@@ -177,37 +225,38 @@ impl CharStr {
     }
 }
 
-fn decode_class(Field(x): Field<u16>) -> String {
-    // See https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-2
-    let s = match x {
-        1 => "Internet (1)",
-        3 => "Chaos (3)",
-        4 => "Hesoid (4)",
-        _ => return format!("Unknown ({})", x),
-    };
-    s.to_string()
+/// See https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-2
+#[derive(ValueStrings)]
+enum Class {
+    Internet = 1,
+    Chaos = 3,
+    Hesoid = 4,
+    #[wsdf(range = (65280, 65534), rename = "Private use")]
+    Private,
 }
 
-fn decode_qtype(Field(x): Field<u16>) -> String {
-    // See https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-4
-    let s = match x {
-        1 => "A [Host address] (1)",
-        2 => "NS [Authoritative name server] (2)",
-        5 => "CNAME [Canonical name for an alias] (5)",
-        6 => "SOA [Start of a zone of authority] (6)",
-        12 => "PTR [Domain name pointer] (12)",
-        15 => "MX [Mail exchange] (15)",
-        28 => "AAAA [IP6 address] (28)",
-        _ => return format!("Unknown ({})", x),
-    };
-    s.to_string()
+/// See https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-4
+#[derive(ValueStrings)]
+enum QType {
+    A = 1,
+    NS = 2,
+    #[wsdf(rename = "CNAME")]
+    Cname = 5,
+    #[wsdf(rename = "SOA")]
+    Soa = 6,
+    #[wsdf(rename = "PTR")]
+    Ptr = 12,
+    MX = 15,
+    #[wsdf(rename = "AAAA")]
+    Aaaa = 28,
 }
 
 #[derive(ProtocolField)]
-struct Seconds(#[wsdf(decode_with = "Seconds::decode")] u32);
+struct Seconds(#[wsdf(decode_with = "Seconds::decode")] wsdf::types::DurationSecs);
 
 impl Seconds {
-    fn decode(Field(s): Field<u32>) -> String {
+    fn decode(Field(duration): Field<Duration>) -> String {
+        let s = duration.as_secs();
         let seconds = s % 60;
         let minutes = (s / 60) % 60;
         let hours = (s / 60) / 60;
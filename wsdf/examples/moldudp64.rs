@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
-use wsdf::{version, Protocol, ProtocolField};
+use wsdf::{plugin, version, PrefRange, Preferences, Protocol, ProtocolField};
 
 version!("0.0.1", 4, 4);
+plugin!(BabyMoldUDP64);
 
 #[derive(Protocol)]
 #[wsdf(
@@ -10,6 +11,8 @@ version!("0.0.1", 4, 4);
     proto_name = "Baby MoldUDP64",
     proto_filter = "baby_moldudp64",
     decode_from = ["udp.port"],
+    info = "Seq={sequence_number} Msgs={message_count}",
+    prefs = "BabyMoldUDP64Prefs",
 )]
 struct BabyMoldUDP64 {
     session: [u8; 10],
@@ -19,6 +22,13 @@ struct BabyMoldUDP64 {
     messages: Vec<MessageBlock>,
 }
 
+#[derive(Preferences)]
+struct BabyMoldUDP64Prefs {
+    /// UDP ports which are always decoded as MoldUDP64, without needing "Decode As".
+    #[wsdf(decode_from = "udp.port", rename = "UDP ports")]
+    ports: PrefRange,
+}
+
 #[derive(ProtocolField)]
 struct MessageBlock {
     message_length: u16,
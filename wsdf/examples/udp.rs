@@ -1,8 +1,12 @@
 #![allow(dead_code)]
 
-use wsdf::{version, Protocol};
+use std::net::IpAddr;
+
+use wsdf::tap::{Addresses, Field};
+use wsdf::{plugin, version, Protocol};
 
 version!("0.0.1", 4, 4);
+plugin!(BabyUDP);
 
 // The ip.proto field obtained from http://www.iana.org/assignments/protocol-numbers/protocol-numbers.xml
 
@@ -17,7 +21,33 @@ struct BabyUDP {
     source_port: u16,
     dest_port: u16,
     length: u16,
+    #[wsdf(checksum_fn = "udp_checksum", over = "source_port..payload")]
     checksum: u16,
     #[wsdf(subdissector = ("baby_udp.port", "dest_port", "source_port"))]
     payload: Vec<u8>,
 }
+
+/// Computes the UDP checksum over the IPv4 pseudo-header and the datagram, with the checksum
+/// field itself taken as zero.
+fn udp_checksum(Field(datagram): Field<&[u8]>, addrs: Addresses) -> Option<u32> {
+    // A zero checksum means the sender did not compute one.
+    if datagram.get(6..8)? == [0, 0] {
+        return None;
+    }
+    let (Some(IpAddr::V4(src)), Some(IpAddr::V4(dst))) = (addrs.src, addrs.dst) else {
+        return None;
+    };
+    let mut data = Vec::with_capacity(12 + datagram.len());
+    data.extend_from_slice(&src.octets());
+    data.extend_from_slice(&dst.octets());
+    data.extend_from_slice(&[0, 17]);
+    data.extend_from_slice(&(datagram.len() as u16).to_be_bytes());
+    data.extend_from_slice(&datagram[..6]);
+    data.extend_from_slice(&[0, 0]);
+    data.extend_from_slice(&datagram[8..]);
+    match wsdf::checksum::internet(&data) {
+        // A computed checksum of zero is transmitted as all ones.
+        0 => Some(0xffff),
+        sum => Some(sum),
+    }
+}
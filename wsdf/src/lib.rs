@@ -14,20 +14,36 @@
 //! [GitHub repo](https://github.com/ghpr-asia/wsdf/tree/main/wsdf/examples/).
 //!
 //! * [Getting started](#getting-started)
+//!     * [Multiple protocols](#multiple-protocols)
 //! * [Types](#types)
 //!     * [Mapping](#mapping)
+//!     * [Byte order](#byte-order)
+//!     * [Booleans](#booleans)
+//!     * [Strings](#strings)
 //!     * [User-defined types](#user-defined-types)
 //!     * [Decoding enums](#decoding-enums)
+//!     * [Tagged enums](#tagged-enums)
 //!     * [Lists](#lists)
+//!     * [Optional fields](#optional-fields)
+//!     * [Virtual fields](#virtual-fields)
+//!     * [Padding and alignment](#padding-and-alignment)
+//!     * [Bit fields](#bit-fields)
+//!     * [Checksums](#checksums)
+//!     * [Flags](#flags)
+//!     * [Value strings](#value-strings)
 //! * [Taps and custom displays](#taps-and-custom-displays)
 //!     * [Using `Fields`](#using-fields)
 //!     * [Custom displays](#custom-displays)
 //!         * [`decode_with`](#decode_with)
 //!         * [`consume_with`](#consume_with)
+//! * [Packet list columns](#packet-list-columns)
+//! * [Preferences](#preferences)
 //! * [Calling subdissectors](#calling-subdissectors)
+//! * [Malformed packets](#malformed-packets)
 //! * [Attributes](#attributes)
 //!     * [Protocol attributes](#protocol-attributes)
 //!     * [Variant attributes](#variant-attributes)
+//!     * [Preferences attributes](#preferences-attributes)
 //!     * [Field attributes](#field-attributes)
 //!
 //! # Getting started
@@ -40,6 +56,7 @@
 //! ```rust
 //! // lib.rs
 //! wsdf::version!("0.0.1", 4, 4);
+//! wsdf::plugin!(UDP);
 //!
 //! #[derive(wsdf::Protocol)]
 //! #[wsdf(decode_from = [("ip.proto", 17)])]
@@ -54,11 +71,13 @@
 //! ```
 //!
 //! * The **`wsdf::version!` macro** specifies the plugin version as 0.0.1, built for Wireshark
-//! version 4.4.X. This information is required by Wireshark when loading the plugin.
+//!   version 4.4.X. This information is required by Wireshark when loading the plugin.
+//! * The **`wsdf::plugin!` macro** lists the protocols provided by the plugin. It generates the
+//!   entry point Wireshark calls to register them.
 //! * The protocol itself should **derive `wsdf::Protocol`**. Since this is UDP, the dissector is
-//! registered to the `"ip.proto"` dissector table, and also sets up the `"udp.port"` dissector
-//! table for subdissectors to use. More details about these annotations can be found in the
-//! sections below.
+//!   registered to the `"ip.proto"` dissector table, and also sets up the `"udp.port"` dissector
+//!   table for subdissectors to use. More details about these annotations can be found in the
+//!   sections below.
 //!
 //! The crate type must be specified in `Cargo.toml`.
 //!
@@ -78,17 +97,271 @@
 //! You can use **Help › About Wireshark › Folders**
 //! or `tshark -G folders` to find the default locations for plugins on your system.
 //!
+//! ## Multiple protocols
+//!
+//! A single plugin may provide any number of protocols. Each of them derives `Protocol` as usual,
+//! and all of them are passed to `wsdf::plugin!`.
+//!
+//! ```rust
+//! wsdf::plugin!(MoldUDP64, Itch);
+//!
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct MoldUDP64 {
+//!     session: [u8; 10],
+//!     sequence_number: u64,
+//!     message_count: u16,
+//!     #[wsdf(len_field = "message_count")]
+//!     messages: Vec<MessageBlock>,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct MessageBlock {
+//!     message_length: u16,
+//!     #[wsdf(len_field = "message_length", subdissector = "mold_udp64.payload")]
+//!     message_data: Vec<u8>,
+//! }
+//!
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "mold_udp64.payload")]
+//! struct Itch {
+//!     message_type: u8,
+//!     timestamp: u64,
+//! }
+//! ```
+//!
+//! Each protocol is registered and handed off independently, so they may be registered to
+//! different dissector tables, or even call each other as subdissectors.
+//!
 //! # Types
 //!
 //! ## Mapping
 //!
 //! wsdf automatically maps some Rust types to Wireshark types.
 //!
-//! Rust type              | WS type    | WS encoding      | WS display
-//! -----------------------|------------|------------------|-----------------------------------------
-//! `u8` to `u64`          | `FT_UINT*` | `ENC_BIG_ENDIAN` | `BASE_DEC`
-//! `i8` to `i64`          | `FT_INT*`  | `ENC_BIG_ENDIAN` | `BASE_DEC`
-//! `Vec<u8>` or `[u8; _]` | `FT_BYTES` | `ENC_NA`         | `SEP_COLON \| BASE_SHOW_ASCII_PRINTABLE`
+//! Rust type                     | WS type            | WS encoding      | WS display
+//! ------------------------------|--------------------|------------------|-----------------------------------------
+//! `u8` to `u64`, `U24` to `U56` | `FT_UINT*`         | `ENC_BIG_ENDIAN` | `BASE_DEC`
+//! `i8` to `i64`, `I24` to `I56` | `FT_INT*`          | `ENC_BIG_ENDIAN` | `BASE_DEC`
+//! `u128` or `i128`              | `FT_BYTES`         | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `Varint<T>` or `Sdnv<T>`      | same as `T`        | `ENC_VARINT_*`   | `BASE_DEC`
+//! `f32`                         | `FT_FLOAT`         | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `f64`                         | `FT_DOUBLE`        | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `bool`                        | `FT_BOOLEAN`       | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `String` or `&str`            | `FT_STRING*`       | `ENC_ASCII`      | `BASE_NONE`
+//! `Ipv4` or `Ipv4Addr`          | `FT_IPv4`          | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `Ipv6` or `Ipv6Addr`          | `FT_IPv6`          | `ENC_NA`         | `BASE_NONE`
+//! `Ether`                       | `FT_ETHER`         | `ENC_NA`         | `BASE_NONE`
+//! `Eui64`                       | `FT_EUI64`         | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `Guid`                        | `FT_GUID`          | `ENC_BIG_ENDIAN` | `BASE_NONE`
+//! `EpochSecs` etc.              | `FT_ABSOLUTE_TIME` | `ENC_TIME_*`     | `ABSOLUTE_TIME_LOCAL`
+//! `NtpTime`                     | `FT_ABSOLUTE_TIME` | `ENC_TIME_NTP`   | `ABSOLUTE_TIME_LOCAL`
+//! `DurationSecs` etc.           | `FT_RELATIVE_TIME` | `ENC_TIME_*`     | `BASE_NONE`
+//! `Vec<u8>` or `[u8; _]`        | `FT_BYTES`         | `ENC_NA`         | `SEP_COLON \| BASE_SHOW_ASCII_PRINTABLE`
+//!
+//! Integers of 24, 40, 48 and 56 bits are written as [`U24`], [`I48`] and so on. These are
+//! aliases of the next larger Rust integer, which is what taps and `decode_with` functions receive,
+//! and they can be used as length or dispatch fields like any other integer. wsdf recognizes them
+//! by their path, so they must be written as e.g. `wsdf::U24` or `wsdf::types::U24`. A bare
+//! `U24`, or a `types::U24` from some other module, is taken to be a type of your own, which
+//! happens to have the same name. Wireshark has no
+//! 128-bit integers, so `u128` and `i128` fields are shown as bytes, but taps still receive the
+//! number.
+//!
+//! Floating point fields are read as IEEE 754 values. Like integers, they can be made little
+//! endian with `#[wsdf(le)]`.
+//!
+//! ## Byte order
+//!
+//! Fields are big endian by default. A type marked `#[wsdf(le)]` makes its fields little endian,
+//! and the types nested in it inherit this, unless they are marked `#[wsdf(be)]` themselves. A
+//! single field can also be marked `le` or `be`. On a field of a nested type, this changes the
+//! default byte order of that type's fields.
+//!
+//! ```
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port", le)]
+//! struct Frame {
+//!     len: u32, // little endian
+//!     #[wsdf(be)]
+//!     port: u16,
+//!     header: Header,
+//!     #[wsdf(be)]
+//!     trailer: Header,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct Header {
+//!     id: u32, // little endian in `header`, big endian in `trailer`
+//! }
+//! ```
+//!
+//! The byte order also applies to tags, flags, and the length prefixes of strings. Use `enc` for
+//! any other encoding.
+//!
+//! When the byte order is only known from the packet itself, e.g. a byte-order mark, the encoding
+//! can be picked at dissection time instead. `enc_fn` is called with a previous field, given by
+//! `enc_field`, and returns the Wireshark encoding to use. On a field of a nested type, this is the
+//! default byte order of that type's fields.
+//!
+//! ```
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Section {
+//!     bom: u32,
+//!     #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+//!     version: u16,
+//!     #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+//!     body: Body,
+//! }
+//! # #[derive(wsdf::ProtocolField)]
+//! # struct Body {
+//! #     len: u32,
+//! # }
+//!
+//! fn pick_enc(bom: &u32) -> u32 {
+//!     match bom {
+//!         0x4d3c2b1a => wsdf::epan_sys::ENC_LITTLE_ENDIAN,
+//!         _ => wsdf::epan_sys::ENC_BIG_ENDIAN,
+//!     }
+//! }
+//! ```
+//!
+//! ## Varints
+//!
+//! Integers which take up a variable number of bytes are written as [`Varint<T>`](Varint), where
+//! `T` is the integer type they are decoded into. The encoding is picked with `enc`.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Frame {
+//!     length: wsdf::Varint<u64>, // LEB128, as used by protobuf
+//!     #[wsdf(len_field = "length")]
+//!     payload: Vec<u8>,
+//!     #[wsdf(enc = "ENC_VARINT_QUIC")]
+//!     stream_id: wsdf::Varint<u64>,
+//!     #[wsdf(enc = "ENC_VARINT_ZIGZAG")]
+//!     delta: wsdf::Varint<i32>,
+//!     bundle_age: wsdf::Sdnv<u64>,
+//! }
+//! ```
+//!
+//! Wireshark does not decode SDNVs (RFC 6256) itself, so they have their own type,
+//! [`Sdnv<T>`](Sdnv). Varints are registered as regular integer fields, and taps receive a `T`.
+//! They can be saved, and used as length or dispatch fields. Like the integers above, they must be
+//! written with their path. A varint whose value does not fit in `T`, or which runs on for longer
+//! than its encoding allows, marks the packet as malformed.
+//!
+//! ## Booleans
+//!
+//! A `bool` field is read from a single byte by default, and is true iff the byte is not zero. A
+//! wider integer may be given with `width`, and a `mask` restricts the bits which are checked.
+//! The labels shown for true and false can be changed with `tfs`.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Options {
+//!     enabled: bool,
+//!     #[wsdf(width = u16, mask = 0x0100, tfs = ("Compressed", "Not compressed"))]
+//!     compressed: bool,
+//! }
+//! ```
+//!
+//! A `bool` with `decode_with` cannot have a mask above the lowest 32 bits, since Wireshark only
+//! takes 32 bits for a boolean with a custom format. `bool` may also be used for the flags of a
+//! type deriving [`Flags`].
+//!
+//! ## Strings
+//!
+//! A `String` or `&str` field is decoded as text. Its size must be given in one of five ways.
+//!
+//! Attribute                  | Size                                       | WS type
+//! ---------------------------|--------------------------------------------|------------------
+//! `size = 8`                 | A fixed number of bytes, padded with NULs  | `FT_STRINGZPAD`
+//! `len_field = "..."`        | The value of a previous field, in bytes    | `FT_STRING`
+//! `len = "..."`              | Computed from previous fields, in bytes    | `FT_STRING`
+//! `nul_terminated`           | Up to and including a NUL character        | `FT_STRINGZ`
+//! `len_prefix = u8`          | A `u8`, `u16` or `u32` length, then text   | `FT_UINT_STRING`
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Login {
+//!     #[wsdf(size = 8, trim)]
+//!     user: String,
+//!     #[wsdf(nul_terminated, enc = "ENC_UTF_8")]
+//!     greeting: String,
+//!     #[wsdf(len_prefix = u16, enc = "ENC_UTF_16 | ENC_LITTLE_ENDIAN")]
+//!     display_name: String,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct Note<'a> {
+//!     #[wsdf(nul_terminated, enc = "ENC_UCS_2 | ENC_BIG_ENDIAN")]
+//!     title: &'a str,
+//! }
+//! ```
+//!
+//! Strings are ASCII by default, and any of Wireshark's string encodings may be given with `enc`,
+//! e.g. `ENC_UTF_8` or `ENC_EBCDIC`. Encodings may be combined with `|`, which is how the
+//! endianness of UTF-16 strings and length prefixes is chosen. The `trim` attribute removes white
+//! space from both ends, which is useful for space padded fields. A NUL-terminated string in
+//! UTF-16, UCS-2 or UCS-4 ends at a NUL character of the same width, i.e. two or four zero bytes.
+//!
+//! Taps and `decode_with` functions receive strings as `&str`, and saved strings can be read with
+//! [`get_str`](FieldsStore::get_str).
+//!
+//! ## Addresses
+//!
+//! Network addresses and identifiers have their own types in [`types`], which must be written with
+//! their path, e.g. `wsdf::types::Ether`, like the integers of odd widths. The standard library's
+//! [`Ipv4Addr`](std::net::Ipv4Addr) and [`Ipv6Addr`](std::net::Ipv6Addr) may be used as well.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Lease {
+//!     client_mac: wsdf::types::Ether,
+//!     client_ip: wsdf::types::Ipv4,
+//!     server_ip: std::net::Ipv4Addr,
+//!     #[wsdf(enc = "ENC_LITTLE_ENDIAN")]
+//!     session: wsdf::types::Guid,
+//! }
+//! ```
+//!
+//! Wireshark resolves IPv4, IPv6 and MAC addresses to names if name resolution is turned on. Taps
+//! and `decode_with` functions receive the typed values, and saved addresses can be read with
+//! e.g. [`get_ipv4`](FieldsStore::get_ipv4).
+//!
+//! ## Times
+//!
+//! Timestamps and intervals have their own types in [`types`], which say how the time is encoded.
+//!
+//! Type                                                | Encoding
+//! ----------------------------------------------------|------------------------------------
+//! `EpochSecs`                                         | 32-bit seconds since the Unix epoch
+//! `EpochMillis`, `EpochMicros`, `EpochNanos`          | 64-bit count since the Unix epoch
+//! `NtpTime`                                           | 64-bit NTP timestamp
+//! `DurationSecs`                                      | 32-bit count of seconds
+//! `DurationMillis`, `DurationMicros`, `DurationNanos` | 64-bit count
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Order {
+//!     #[wsdf(enc = "ENC_LITTLE_ENDIAN")]
+//!     sent: wsdf::types::EpochNanos,
+//!     #[wsdf(display = "ABSOLUTE_TIME_UTC")]
+//!     received: wsdf::types::EpochNanos,
+//!     time_in_force: wsdf::types::DurationSecs,
+//! }
+//! ```
+//!
+//! Wireshark shows timestamps as dates, in local time by default, and they can be filtered like
+//! `frame.time`. The `enc` attribute only sets the byte order, as the `ENC_TIME_*` encoding
+//! follows from the type. The types are aliases of [`SystemTime`](std::time::SystemTime) and
+//! [`Duration`](std::time::Duration), which is what taps and `decode_with` functions receive. As
+//! wsdf tells them apart by name, they must be written with their path, e.g.
+//! `wsdf::types::EpochNanos`.
+//! Saved times can be read with [`get_time`](FieldsStore::get_time) and
+//! [`get_duration`](FieldsStore::get_duration).
 //!
 //! ## User-defined types
 //!
@@ -183,6 +456,34 @@
 //! This generates a new enum named `DataDispatch` which implements `Into<usize>`, which can be
 //! directly returned from the `dispatch_typ` function.
 //!
+//! ## Tagged enums
+//!
+//! Often, the variant is given by a tag right at the start of the enum's data. Such enums can
+//! read the tag themselves, without a prior field or a `dispatch_*` method.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Order {
+//!     side: Side,
+//!     price: i32,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! #[wsdf(tag = u8)]
+//! enum Side {
+//!     #[wsdf(tag_value = 0x42)]
+//!     Bid,
+//!     #[wsdf(tag_value = 0x41)]
+//!     Ask,
+//!     #[wsdf(fallback)]
+//!     Unknown,
+//! }
+//! ```
+//!
+//! The tag is added to the tree as a field, named after the variants, and registered under the
+//! enum field's own filter, e.g. `<prefix>.side == 0x42`. If the tag matches no variant and there
+//! is no `fallback` variant, the packet is reported as malformed.
+//!
 //! ## Lists
 //!
 //! wsdf understands arrays and `Vec`s. You would use a `Vec` if the number of elements is unknown
@@ -206,6 +507,307 @@
 //! # }
 //! ```
 //!
+//! When the length has to be worked out from other fields, `len` takes an arithmetic expression
+//! instead. It may use integer literals, prior integer fields, parentheses, and `+`, `-`, `*`, `/`
+//! and `%`. The expression is checked at compile time and evaluated at dissection time. If any step
+//! overflows, goes below zero or divides by zero, the packet is marked as malformed. `len` works
+//! for `Vec`s and strings alike.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Header {
+//!     ihl: u8,
+//!     #[wsdf(len = "ihl * 4 - 20")]
+//!     options: Vec<u8>,
+//! }
+//! ```
+//!
+//! If the other field gives the size of the list in bytes instead, use `bytes_len_field`. The
+//! elements are dissected one after another until exactly that many bytes are consumed. They only
+//! see those bytes, so an element which runs past the end of the list is reported as an expert
+//! item, just like a field running past the end of the packet.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Options {
+//!     len: u16,
+//!     #[wsdf(bytes_len_field = "len")]
+//!     options: Vec<Tlv>,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct Tlv {
+//!     typ: u8,
+//!     len: u8,
+//!     #[wsdf(len_field = "len")]
+//!     value: Vec<u8>,
+//! }
+//! ```
+//!
+//! Some lists have no length at all. With `until_end`, elements are dissected until the end of
+//! the packet, or of the enclosing list if it was sized by `bytes_len_field`. With
+//! `terminator = 0x00`, the list ends at a byte with that value, which is consumed but not shown.
+//! A missing terminator is reported like a field running past the end of the packet. For anything
+//! else, `until` names a function which is given the remaining bytes before each element, and
+//! returns `true` to end the list. The list also ends if the packet runs out.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Message {
+//!     #[wsdf(terminator = 0x00)]
+//!     options: Vec<Opt>,
+//!     #[wsdf(until = "at_trailer")]
+//!     words: Vec<u16>,
+//!     #[wsdf(until_end)]
+//!     records: Vec<Opt>,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct Opt {
+//!     typ: u8,
+//!     len: u8,
+//!     #[wsdf(len_field = "len")]
+//!     value: Vec<u8>,
+//! }
+//!
+//! fn at_trailer(rest: &[u8]) -> bool {
+//!     rest.starts_with(&[0xff, 0xff])
+//! }
+//! ```
+//!
+//! Since there is no telling how many elements such a list holds, each element is labelled with
+//! its index in the tree, e.g. "Options [0]" for a struct, or "Words: 7 [0]" for anything else.
+//! Elements which are themselves lists are not labelled.
+//!
+//! The same attributes work on a `Vec<u8>`, which is then shown as a single run of bytes.
+//! `until_end` takes the rest of the packet, `until` stops where the function, given the remaining
+//! bytes, first returns `true`, and `terminator` stops after the terminating byte, which is part of
+//! the bytes.
+//! For text ended by a NUL, use a `String` with `nul_terminated` instead.
+//!
+//! ## Optional fields
+//!
+//! A field which only exists in some packets, e.g. when a flag bit is set, is written as an
+//! `Option<T>` with one of two attributes. `if` takes an expression over previous fields, which may
+//! also use constants in scope. `present_if` names a function which takes the same parameters as a
+//! [tap](#taps-and-custom-displays) and returns a `bool`.
+//!
+//! ```rust
+//! use wsdf::tap::Fields;
+//!
+//! const HAS_CHECKSUM: u8 = 0x80;
+//!
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Message {
+//!     #[wsdf(save)]
+//!     flags: u8,
+//!     #[wsdf(if = "flags & HAS_CHECKSUM != 0")]
+//!     checksum: Option<u16>,
+//!     #[wsdf(present_if = "has_trailer")]
+//!     trailer: Option<u32>,
+//! }
+//!
+//! fn has_trailer(Fields(fields): Fields) -> bool {
+//!     fields.get_u8("message.flags").is_some_and(|flags| flags & 0x01 != 0)
+//! }
+//! ```
+//!
+//! An absent field takes up no bytes and does not appear in the tree. Since its value may not
+//! exist, other fields cannot use it, e.g. as a `len_field`.
+//!
+//! ## Virtual fields
+//!
+//! A field marked `virtual` is read at the current offset without moving past it, so the next field
+//! starts at the same byte. This is useful to peek at a value, e.g. a version nibble, which decides
+//! how the rest of the header is laid out. A virtual field can be used like any other, e.g. as a
+//! `dispatch_field` or `len_field`. Adding `generated` shows it in square brackets, as Wireshark
+//! does for values which are not really in the packet.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Packet {
+//!     #[wsdf(virtual, generated)]
+//!     version: u8,
+//!     #[wsdf(dispatch_field = "version")]
+//!     header: Header,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! enum Header {
+//!     V1(u32),
+//!     V2(u64),
+//! }
+//!
+//! impl Header {
+//!     fn dispatch_version(version: &u8) -> usize {
+//!         (*version >> 4) as usize
+//!     }
+//! }
+//! ```
+//!
+//! Only fields of primitive types, e.g. integers, strings and byte arrays, can be virtual.
+//!
+//! ## Padding and alignment
+//!
+//! Bytes which carry no data can be skipped before a field. `skip` skips a fixed number of bytes,
+//! and `skip_expr` computes the number from previous integer fields, like `len`. `align` then skips
+//! ahead to the next multiple of some number of bytes. On a type, `align` pads the end of the type
+//! instead, so whatever follows it starts on a boundary.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port")]
+//! struct Packet {
+//!     kind: u8,
+//!     #[wsdf(align = 4)]
+//!     len: u16,
+//!     #[wsdf(skip = 2)]
+//!     flags: u8,
+//!     #[wsdf(skip_expr = "len % 4")]
+//!     record: Record,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! #[wsdf(align = 8, show_padding)]
+//! struct Record {
+//!     id: u16,
+//!     value: u8,
+//! }
+//! ```
+//!
+//! Alignment is counted from the start of the type which holds the field, e.g. from the start of
+//! each record in a list of them. The padding at the end of a type is counted from the start of
+//! the type itself. Skipped bytes are hidden unless `show_padding` is given, in which case they are
+//! added to the tree as `<protocol>.padding_bytes`, with a warning if any of them are not zero.
+//!
+//! ## Bit fields
+//!
+//! Some protocols pack their fields bit by bit, so a field may start or end in the middle of a
+//! byte. A struct marked `#[wsdf(bits)]` reads each of its fields as the number of bits given by
+//! its `bits` attribute, one after the other. Once the struct's fields are done, dissection carries
+//! on from the next whole byte.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! #[wsdf(bits)]
+//! struct Header {
+//!     #[wsdf(bits = 3)]
+//!     version: u8,
+//!     #[wsdf(bits = 1)]
+//!     urgent: bool,
+//!     #[wsdf(bits = 12)]
+//!     sequence: u16,
+//! }
+//! ```
+//!
+//! Every field of a bits struct must be an unsigned integer or a `bool` with a `bits` attribute.
+//! The bits are read from the most significant bit first, or from the least significant bit first
+//! if the struct is little endian. Bit fields can be saved, passed to taps and used in `if`
+//! expressions like other integer fields, but they cannot use `decode_with` or `virtual`.
+//!
+//! ## Checksums
+//!
+//! A field with a `checksum` attribute is checked against a checksum computed over a range of the
+//! type's fields. The range is given by `over`, either as a single field or as `"first..last"`,
+//! both ends included. Without `over`, the checksum covers every field before it.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct Frame {
+//!     len: u8,
+//!     #[wsdf(len_field = "len")]
+//!     payload: Vec<u8>,
+//!     #[wsdf(checksum = "crc32")]
+//!     crc: u32,
+//! }
+//! ```
+//!
+//! The built-in checksums are the functions in the [`checksum`] module: `crc16`, `crc32`,
+//! `crc32c`, `internet`, `adler32`, `fletcher16` and `fletcher32`. The `crc16`, `internet` and
+//! `fletcher16` checksums must be held in a `u16`, the rest in a `u32`. A range may include the
+//! checksum field itself, which is how the internet checksum is usually computed.
+//!
+//! For anything else, `checksum_fn` names a function which is called like a
+//! [tap](#taps-and-custom-displays) with the covered bytes as its [`Field`](tap::Field). It returns
+//! the expected value of the checksum, or `None` if the checksum cannot be verified, e.g. because
+//! it was left out.
+//!
+//! ```rust
+//! # use wsdf::tap::Field;
+//! #[derive(wsdf::ProtocolField)]
+//! struct Message {
+//!     body: [u8; 8],
+//!     #[wsdf(checksum_fn = "xor_bytes")]
+//!     check: u8,
+//! }
+//!
+//! fn xor_bytes(Field(bytes): Field<&[u8]>) -> Option<u32> {
+//!     Some(bytes.iter().fold(0, |acc, b| acc ^ b) as u32)
+//! }
+//! ```
+//!
+//! The checksum is added to the tree once both the field and its range have been dissected, along
+//! with a `<field filter>.status` field which tells whether it is good, bad or unverified. A bad
+//! checksum also raises the `<protocol>.expert.bad_checksum` expert info. Checksum fields and the
+//! ends of their ranges cannot be `Option` fields.
+//!
+//! ## Flags
+//!
+//! A struct deriving [`Flags`] represents a single integer field which is displayed as a set of
+//! bitmasked sub-items. Each field of the struct must be of the same unsigned integer type, or a
+//! `bool`, and carries a `mask` attribute. If every flag is a `bool`, the integer type of the whole
+//! value must be given with `#[wsdf(width = ...)]` on the struct.
+//!
+//! ```rust
+//! #[derive(wsdf::Flags)]
+//! struct DnsFlags {
+//!     #[wsdf(mask = 0x8000, rename = "Response")]
+//!     qr: u16,
+//!     #[wsdf(mask = 0x7800)]
+//!     opcode: u16,
+//!     #[wsdf(mask = 0x000f, rename = "Reply code")]
+//!     rcode: u16,
+//! }
+//! ```
+//!
+//! When used as a field named `flags`, the whole value is registered under `<prefix>.flags` and
+//! each sub-item under `<prefix>.flags.<name>`, so filters like `dns.flags.qr == 1` work as
+//! expected. The `save` attribute may be used on a flag to store its (shifted) value in
+//! [`Fields`](tap::Fields).
+//!
+//! ## Value strings
+//!
+//! Integer fields can be displayed with names for their values, e.g. "A (1)" instead of "1", by
+//! deriving [`ValueStrings`](macro@ValueStrings) on a fieldless enum and pointing the field at it.
+//! The names are handed to Wireshark, so they also work in display filters, e.g.
+//! `dns.type == "A"`.
+//!
+//! ```rust
+//! #[derive(wsdf::ValueStrings)]
+//! enum QType {
+//!     A = 1,
+//!     #[wsdf(rename = "CNAME")]
+//!     Cname = 5,
+//!     #[wsdf(range = (65280, 65534), rename = "Private use")]
+//!     Private,
+//! }
+//!
+//! #[derive(wsdf::ProtocolField)]
+//! struct Question {
+//!     #[wsdf(value_strings = "QType")]
+//!     typ: u16,
+//! }
+//! ```
+//!
+//! Each variant names the value of its discriminant, unless it covers a range of values with
+//! `#[wsdf(range = (min, max))]`. Discriminants may be negative, for signed fields, though ranges
+//! may not.
+//!
 //! # Taps and custom displays
 //!
 //! wsdf features a `tap` attribute which allows you to register some function(s) to be called
@@ -220,6 +822,9 @@
 //! * [`Offset`](tap::Offset), the current byte offset into the packet
 //! * [`Packet`](tap::Packet), the raw bytes of the packet
 //! * [`PacketNanos`](tap::PacketNanos), the nanosecond timestamp at which the packet was recorded
+//! * [`Columns`](tap::Columns), to write to the packet list's columns, e.g. the Info column
+//! * [`Addresses`](tap::Addresses), the source and destination IP addresses of the packet
+//! * [`Prefs`](tap::Prefs), the current values of the protocol's [preferences](#preferences)
 //!
 //! Any permutation of the parameters is supported.
 //!
@@ -360,38 +965,108 @@
 //! }
 //! ```
 //!
-//! # Calling subdissectors
-//!
-//! For lower level protocols, you would want to hand the packet's payload to a subdissector. There
-//! are two ways to achieve this - using a "Decode As" subdissector or using a regular dissector
-//! table.
+//! # Packet list columns
 //!
-//! The first variant can be seen in the MoldUDP64 dissector.
+//! A protocol can summarize each packet in the Info column of the packet list with the `info`
+//! attribute. It is a format string, which may reference fields of the type by name. These can be
+//! numbers, bools, strings or addresses, i.e. anything whose value is formatted with `Display`.
+//! Times, bytes and nested types cannot be referenced.
 //!
 //! ```rust
-//! #[derive(wsdf::ProtocolField)]
-//! struct MessageBlock {
-//!     message_length: u16,
-//!     #[wsdf(len_field = "message_length", subdissector = "moldudp64.payload")]
-//!     message_data: Vec<u8>,
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = "udp.port", info = "{session} Seq={sequence} Msgs={message_count}")]
+//! struct MoldUDP64 {
+//!     #[wsdf(size = 10, trim)]
+//!     session: String,
+//!     sequence: u64,
+//!     message_count: u16,
 //! }
 //! ```
 //!
-//! Whichever dissector has been registered to the pattern `"moldudp64.payload"` will be invoked
-//! with the payload bytes.
+//! The text is appended to the Info column as soon as the fields it references have been
+//! dissected. The `info` attribute may also be given on enum variants. For anything more
+//! involved, taps can write to the columns through the [`Columns`](tap::Columns) parameter.
 //!
-//! The second variant can be seen in the UDP dissector.
+//! # Preferences
+//!
+//! A protocol can offer options in its section of Wireshark's preferences dialog. These are
+//! declared as the fields of a struct deriving [`Preferences`](macro@Preferences), which is
+//! attached to the protocol with the `prefs` attribute.
 //!
 //! ```rust
+//! # use wsdf::tap::{Field, Prefs};
 //! #[derive(wsdf::Protocol)]
-//! #[wsdf(decode_from = [("ip.proto", 17)])]
-//! struct UDP {
-//!     src_port: u16,
-//!     dst_port: u16,
-//!     length: u16,
-//!     checksum: u16,
-//!     #[wsdf(subdissector = ("udp.port", "src_port", "dst_port"))]
-//!     payload: Vec<u8>,
+//! #[wsdf(decode_from = "udp.port", prefs = "MyPrefs")]
+//! struct MyProto {
+//!     #[wsdf(tap = "check_price")]
+//!     price: u64,
+//! }
+//!
+//! #[derive(wsdf::Preferences)]
+//! struct MyPrefs {
+//!     /// Show prices as decimals rather than fixed point integers.
+//!     decimal_prices: bool,
+//!     #[wsdf(default = "9000-9010", decode_from = "udp.port")]
+//!     ports: wsdf::PrefRange,
+//! }
+//!
+//! fn check_price(Field(price): Field<u64>, Prefs(prefs): Prefs<MyPrefs>) {
+//!     if prefs.decimal_prices {
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! The kind of each preference is picked from the field's type.
+//!
+//! * `bool` is a checkbox.
+//! * `u32` is a number.
+//! * `String` is a text box.
+//! * [`PrefRange`] is a set of ranges, e.g. `"80,8000-8080"`.
+//! * An enum deriving [`ValueStrings`](macro@ValueStrings) is a drop-down list. Its variants must
+//!   each name a single value, and the preference must have a default, e.g.
+//!   `#[wsdf(default = "Mode::Fast")]`.
+//!
+//! A range preference with a `decode_from` attribute binds the protocol to its values in that
+//! dissector table. Whenever the preference changes, the protocol is unbound from the old values
+//! and bound to the new ones. With such a preference, the protocol's own `decode_from` may be
+//! left out.
+//!
+//! The current values are read through the [`Prefs`](tap::Prefs) parameter, which works anywhere
+//! a tap does.
+//!
+//! # Calling subdissectors
+//!
+//! For lower level protocols, you would want to hand the packet's payload to a subdissector. There
+//! are two ways to achieve this - using a "Decode As" subdissector or using a regular dissector
+//! table.
+//!
+//! The first variant can be seen in the MoldUDP64 dissector.
+//!
+//! ```rust
+//! #[derive(wsdf::ProtocolField)]
+//! struct MessageBlock {
+//!     message_length: u16,
+//!     #[wsdf(len_field = "message_length", subdissector = "moldudp64.payload")]
+//!     message_data: Vec<u8>,
+//! }
+//! ```
+//!
+//! Whichever dissector has been registered to the pattern `"moldudp64.payload"` will be invoked
+//! with the payload bytes.
+//!
+//! The second variant can be seen in the UDP dissector.
+//!
+//! ```rust
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(decode_from = [("ip.proto", 17)])]
+//! struct UDP {
+//!     src_port: u16,
+//!     dst_port: u16,
+//!     length: u16,
+//!     checksum: u16,
+//!     #[wsdf(subdissector = ("udp.port", "src_port", "dst_port"))]
+//!     payload: Vec<u8>,
 //! }
 //! ```
 //!
@@ -400,6 +1075,17 @@
 //! port. If no subdissector is found, wsdf tries again with the destination port. And if that
 //! fails, Wireshark's default data dissector is invoked.
 //!
+//! # Malformed packets
+//!
+//! Packets may be truncated or simply not follow the protocol. wsdf never reads past the end of
+//! the captured data. Instead, the generated dissector adds an expert item to the offending node,
+//! stops dissecting and returns the number of bytes dissected so far, like any native dissector
+//! would. Each protocol registers two expert fields, which can be used in display filters.
+//!
+//! * `<protocol>.expert.past_end` - a field extends past the end of the packet.
+//! * `<protocol>.expert.malformed` - the packet is malformed in some other way, e.g. no enum
+//!   variant could be picked.
+//!
 //! # Attributes
 //!
 //! Attributes are used to customize fields or provide additional information. They can appear on
@@ -416,6 +1102,33 @@
 //! A tuple like `("udp.port", 30000, 30001)` registers the dissector to be used for UDP port
 //! values 30000 and 30001.
 //!
+//! * `#[wsdf(heuristic = ("...", "..."))]`
+//! * `#[wsdf(heuristic = [("...", "..."), ...])]`
+//!
+//! Registers the dissector with heuristic dissector table(s), e.g. `"udp"`, for protocols which
+//! must be recognized by their content rather than by port. Each table is paired with the path to
+//! a function which checks whether a packet belongs to the protocol. It follows the same rules as
+//! taps, e.g. taking [`Packet`](tap::Packet) and [`Offset`](tap::Offset), and returns a `bool`.
+//! The packet is only dissected if the check passes. Otherwise, Wireshark goes on to try other
+//! heuristic dissectors.
+//!
+//! ```rust
+//! # use wsdf::tap::Packet;
+//! #[derive(wsdf::Protocol)]
+//! #[wsdf(heuristic = ("udp", "is_feed"))]
+//! struct Feed {
+//!     magic: [u8; 4],
+//!     sequence: u64,
+//! }
+//!
+//! fn is_feed(Packet(packet): Packet) -> bool {
+//!     packet.starts_with(b"FEED")
+//! }
+//! ```
+//!
+//! Each heuristic dissector can be turned on and off in Wireshark's Enabled Protocols dialog,
+//! where it is listed as e.g. "Feed over UDP".
+//!
 //! * `#[wsdf(proto_desc = "...")]`
 //!
 //! Full protocol description. This is used in the packet list pane.
@@ -428,6 +1141,11 @@
 //!
 //! Protocol name used in the display filter.
 //!
+//! * `#[wsdf(prefs = "...")]`
+//!
+//! Path to a type deriving [`Preferences`](macro@Preferences), which is registered as the
+//! protocol's preferences. See [Preferences](#preferences).
+//!
 //! ## Type-level attributes
 //!
 //! These attributes can appear on any type which derives `Protocol` or `ProtocolField`.
@@ -444,6 +1162,54 @@
 //! Provide path(s) to function(s) to call *after* the last field of the type is dissected. The
 //! functions' parameters follow the same rules as taps.
 //!
+//! * `#[wsdf(info = "...")]`
+//!
+//! For structs only. A format string to append to the Info column, e.g. `"Seq={seq}"`. See
+//! [Packet list columns](#packet-list-columns).
+//!
+//! * `#[wsdf(tag = u8)]`
+//!
+//! For enums only. The unsigned integer type of a tag which is read from the packet to pick the
+//! variant. See [Tagged enums](#tagged-enums).
+//!
+//! * `#[wsdf(le)]`
+//! * `#[wsdf(be)]`
+//!
+//! Sets the default byte order of the type's fields, including those of nested types. See [Byte
+//! order](#byte-order).
+//!
+//! * `#[wsdf(align = ...)]` and `#[wsdf(show_padding)]`
+//!
+//! Skips ahead to a multiple of the given number of bytes after the last field of the type, and
+//! optionally shows the skipped bytes. See [Padding and alignment](#padding-and-alignment).
+//!
+//! * `#[wsdf(bits)]`
+//!
+//! For structs only. Packs the fields bit by bit instead of byte by byte. See [Bit
+//! fields](#bit-fields).
+//!
+//! ## Flags attributes
+//!
+//! These attributes are specific to types which derive [`Flags`].
+//!
+//! * `#[wsdf(enc = "...")]`
+//! * `#[wsdf(display = "...")]`
+//!
+//! On the struct itself, these set the encoding and display of the whole flags value. The display
+//! defaults to `BASE_HEX`, and the byte order follows the containing type unless the struct is
+//! marked `#[wsdf(le)]` or `#[wsdf(be)]`.
+//!
+//! * `#[wsdf(width = ...)]`
+//!
+//! On the struct itself. The unsigned integer type of the whole flags value, e.g. `u16`. Only
+//! needed if every flag is a `bool`.
+//!
+//! * `#[wsdf(mask = ...)]`
+//!
+//! Required on every flag. The integer bitmask selecting the flag's bits. It must be non-zero and
+//! fit within the flags' integer type. Flags may also use `rename`, `display`, `save` and
+//! `value_strings`, and `bool` flags may use `tfs`.
+//!
 //! ## Variant attributes
 //!
 //! * `#[wsdf(rename = "...")]`
@@ -451,6 +1217,42 @@
 //! Custom name for the variant when displayed in Wireshark. See the sample DNS dissector for
 //! examples.
 //!
+//! * `#[wsdf(tag_value = ...)]`
+//!
+//! For tagged enums only. The tag value which picks this variant.
+//!
+//! * `#[wsdf(fallback)]`
+//!
+//! For tagged enums only. Picks this variant when the tag matches no other variant.
+//!
+//! * `#[wsdf(info = "...")]`
+//!
+//! A format string to append to the Info column when this variant is dissected. It may reference
+//! the variant's own fields, like the `info` attribute of structs.
+//!
+//! ## Preferences attributes
+//!
+//! These attributes are specific to the fields of types which derive
+//! [`Preferences`](macro@Preferences). The preference's description is taken from the field's doc
+//! comment.
+//!
+//! * `#[wsdf(rename = "...")]`
+//!
+//! Custom title for the preference. Defaults to the field's name in title case.
+//!
+//! * `#[wsdf(default = ...)]`
+//!
+//! The default value, as a literal of the field's type. For enums, a string holding the variant,
+//! e.g. `"Mode::Fast"`. Range preferences take a string like `"9000-9010"`.
+//!
+//! * `#[wsdf(decode_from = "...")]`
+//!
+//! For range preferences only. The dissector table whose values the protocol is bound to.
+//!
+//! * `#[wsdf(max = ...)]`
+//!
+//! For range preferences only. The largest value which may be entered. Defaults to 65535.
+//!
 //! ## Field attributes
 //!
 //! * `#[wsdf(rename = "...")]`
@@ -471,6 +1273,57 @@
 //! Intended for fields of type `Vec<_>`. Must point to a prior integer field which specifies the
 //! number of elements for the field.
 //!
+//! * `#[wsdf(len = "...")]`
+//!
+//! Like `len_field`, but computes the length from an arithmetic expression over prior integer
+//! fields, e.g. `"ihl * 4 - 20"`. See [Lists](#lists).
+//!
+//! * `#[wsdf(bytes_len_field = "...")]`
+//!
+//! Like `len_field`, but the prior field specifies the size of the `Vec` in bytes. See
+//! [Lists](#lists).
+//!
+//! * `#[wsdf(until_end)]`, `#[wsdf(until = "...")]` and `#[wsdf(terminator = ...)]`
+//!
+//! For a `Vec` without a length. Dissects elements until the end of the packet, until a function
+//! given the remaining bytes returns `true`, or until a terminating byte. See [Lists](#lists).
+//!
+//! * `#[wsdf(if = "...")]` and `#[wsdf(present_if = "...")]`
+//!
+//! For `Option` fields only. Decides whether the field is present, either from an expression over
+//! previous fields or by calling a function. See [Optional fields](#optional-fields).
+//!
+//! * `#[wsdf(virtual)]`
+//!
+//! Reads the field without moving past it. See [Virtual fields](#virtual-fields).
+//!
+//! * `#[wsdf(generated)]`
+//!
+//! Marks the field as generated, which Wireshark shows in square brackets.
+//!
+//! * `#[wsdf(skip = ...)]`, `#[wsdf(skip_expr = "...")]` and `#[wsdf(align = ...)]`
+//!
+//! Skips some bytes before the field, either a fixed number, a number computed from previous
+//! fields, or up to a multiple of the given number. See [Padding and
+//! alignment](#padding-and-alignment).
+//!
+//! * `#[wsdf(show_padding)]`
+//!
+//! Shows the bytes skipped before the field in the tree.
+//!
+//! * `#[wsdf(bits = ...)]`
+//!
+//! For fields of a `#[wsdf(bits)]` struct only. The number of bits the field takes up.
+//!
+//! * `#[wsdf(checksum = "...")]` and `#[wsdf(checksum_fn = "...")]`
+//!
+//! Verifies the field as a checksum, either with one of the built-in checksums or with the given
+//! function. See [Checksums](#checksums).
+//!
+//! * `#[wsdf(over = "...")]`
+//!
+//! The field, or inclusive range of fields, covered by a checksum.
+//!
 //! * `#[wsdf(typ = "...")]`
 //!
 //! Specifies a Wireshark type to map the field to. Sensible mappings are chosen for most types,
@@ -480,8 +1333,20 @@
 //! * `#[wsdf(enc = "...")]`
 //!
 //! Specifies an encoding for the field, e.g. `ENC_LITTLE_ENDIAN`. *By default, all integer fields
-//! are encoded as big endian.* The full list of encodings and where they are applicable can be
-//! found in Wireshark's `README.dissector` file.
+//! are encoded as big endian,* unless the type says otherwise. The full list of encodings and
+//! where they are applicable can be found in Wireshark's `README.dissector` file.
+//!
+//! * `#[wsdf(le)]`
+//! * `#[wsdf(be)]`
+//!
+//! Shorthands for `#[wsdf(enc = "ENC_LITTLE_ENDIAN")]` and `#[wsdf(enc = "ENC_BIG_ENDIAN")]`. On
+//! a field of a nested type, sets the default byte order of its fields. See [Byte
+//! order](#byte-order).
+//!
+//! * `#[wsdf(enc_field = "...", enc_fn = "...")]`
+//!
+//! Picks the encoding at dissection time, by passing a reference to a previous field to a function
+//! which returns the encoding as a `u32`. See [Byte order](#byte-order).
 //!
 //! * `#[wsdf(display = "...")]`
 //! * `#[wsdf(display = "..." | "...")]`
@@ -535,13 +1400,39 @@
 //! the example above). Each field listed afterwards is used to try and find a subdissector
 //! registered to the table and field's value, one by one, until the first success. This is used in
 //! the UDP example.
+//!
+//! * `#[wsdf(value_strings = "...")]`
+//!
+//! Path to a type implementing [`ValueStrings`], usually an enum deriving it. The field's values
+//! are displayed with their names from the table. Only valid on integer fields.
+//!
+//! * `#[wsdf(width = ...)]`
+//! * `#[wsdf(mask = ...)]`
+//! * `#[wsdf(tfs = ("...", "..."))]`
+//!
+//! For `bool` fields only. See [Booleans](#booleans).
+//!
+//! * `#[wsdf(size = ...)]`
+//! * `#[wsdf(nul_terminated)]`
+//! * `#[wsdf(len_prefix = ...)]`
+//! * `#[wsdf(trim)]`
+//!
+//! For `String` and `&str` fields only. See [Strings](#strings).
+//!
+//! * `#[wsdf(ascii)]`
+//!
+//! For byte arrays, i.e. `[u8; _]` and `Vec<u8>` with a `len_field`. Shows the bytes as an ASCII
+//! string instead of octets, while taps still receive the bytes.
 
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ffi::{c_char, c_int, c_void};
+use std::ffi::{c_char, c_int, c_void, CString};
 
 pub use epan_sys;
-pub use wsdf_derive::{version, Dispatch, Protocol, ProtocolField};
+pub use types::{Sdnv, Varint, I24, I40, I48, I56, U24, U40, U48, U56};
+pub use wsdf_derive::{
+    plugin, version, Dispatch, Flags, Preferences, Protocol, ProtocolField, ValueStrings,
+};
 
 /// Relevant to enum types only. Represents how the variant should be picked.
 #[doc(hidden)]
@@ -669,117 +1560,1622 @@ wrap_pointer!(SubtreeLabel, c_char); // label for text nodes
 wrap_pointer!(FieldIdent, c_char); // field name
 wrap_pointer!(FieldBlurb, c_char); // field description
 
-/// A data type whose fields can be registered in Wireshark and dissected. *Not intended for public
-/// use*.
-pub trait ProtocolField {
-    #[allow(clippy::too_many_arguments)]
-    fn dissect<'a>(
-        __wsdf_start: c_int,
-        __wsdf_tvb: *mut epan_sys::tvbuff,
-        __wsdf_parent: *mut epan_sys::_proto_node,
-        __wsdf_prefix: &str,
-        __wsdf_dispatch: VariantDispatch,
-        __wsdf_subtree_label: SubtreeLabel,
-        __wsdf_tvb_buf: &'a [u8],
-        __wsdf_pinfo: *mut epan_sys::_packet_info,
-        __wsdf_proto_tree_root: *mut epan_sys::_proto_node,
-        __wsdf_fields_store: &mut FieldsStore<'a>,
-    ) -> c_int;
-
-    fn register(
-        __wsdf_prefix: &str,
-        __wsdf_proto_id: c_int,
-        __wsdf_field_ident: FieldIdent,
-        __wsdf_field_blurb: FieldBlurb,
-    );
-
-    fn ett() -> c_int;
+/// Marks `item` as generated, which Wireshark shows in square brackets. This mirrors
+/// `proto_item_set_generated`, which is inline in C and so has no binding. *Not intended for
+/// public use*.
+///
+/// # Safety
+///
+/// `item` must be null or point to a valid item, as returned by the `proto_tree_add_*` functions.
+#[doc(hidden)]
+pub unsafe fn proto_item_set_generated(item: *mut epan_sys::proto_item) {
+    if item.is_null() || (*item).finfo.is_null() {
+        return;
+    }
+    (*(*item).finfo).flags |= epan_sys::FI_GENERATED;
+}
 
-    fn proto_id() -> &'static mut c_int;
+/// Returns the last item added under `tree`, or null if there is none. *Not intended for public
+/// use*.
+///
+/// # Safety
+///
+/// `tree` must be null or point to a valid tree.
+#[doc(hidden)]
+pub unsafe fn last_child(tree: *mut epan_sys::proto_tree) -> *mut epan_sys::proto_item {
+    if tree.is_null() {
+        return std::ptr::null_mut();
+    }
+    (*tree).last_child
+}
 
-    fn hf_map(op: HfMapOp) -> Option<c_int>;
+/// Appends ` [index]` to the text of an item in a list. *Not intended for public use*.
+///
+/// # Safety
+///
+/// `item` must be null or point to a valid item.
+#[doc(hidden)]
+pub unsafe fn append_list_index(item: *mut epan_sys::proto_item, index: usize) {
+    if item.is_null() {
+        return;
+    }
+    let text = to_ui_cstring(format!(" [{index}]"));
+    epan_sys::proto_item_append_text(item, c"%s".as_ptr(), text.as_ptr());
+}
 
-    fn subdissector_map(op: SubdissectorMapOp) -> Option<epan_sys::dissector_table_t>;
+/// Borrows the captured bytes of `tvb` without copying them. *Not intended for public use*.
+///
+/// # Safety
+///
+/// `tvb` must be valid, and the slice must not outlive it.
+#[doc(hidden)]
+pub unsafe fn tvb_captured_bytes<'a>(tvb: *mut epan_sys::tvbuff) -> &'a [u8] {
+    let len = epan_sys::tvb_captured_length(tvb) as usize;
+    if len == 0 {
+        return &[];
+    }
+    let ptr = epan_sys::tvb_get_ptr(tvb, 0, len as c_int);
+    if ptr.is_null() {
+        return &[];
+    }
+    std::slice::from_raw_parts(ptr, len)
 }
 
-/// A data type which represents the root of the protocol. *Not intended for public use.*
-pub trait Protocol: ProtocolField {
-    #[allow(clippy::missing_safety_doc)]
-    unsafe extern "C" fn dissect_main(
-        __wsdf_tvb: *mut epan_sys::tvbuff,
-        __wsdf_pinfo: *mut epan_sys::_packet_info,
-        __wsdf_tree: *mut epan_sys::_proto_node,
-        __wsdf_data: *mut c_void,
-    ) -> c_int;
+/// Converts a UI string into a C string. Anything after an interior nul byte is dropped, instead
+/// of failing the conversion. *Not intended for public use*.
+#[doc(hidden)]
+pub fn to_ui_cstring(s: impl std::fmt::Display) -> CString {
+    let mut bytes = s.to_string().into_bytes();
+    if let Some(nul) = bytes.iter().position(|b| *b == 0) {
+        bytes.truncate(nul);
+    }
+    CString::new(bytes).unwrap_or_default()
+}
 
-    extern "C" fn proto_register();
+#[cfg(test)]
+mod test_to_ui_cstring {
+    use super::*;
 
-    extern "C" fn proto_reg_handoff();
+    #[test]
+    fn truncates_at_interior_nul() {
+        assert_eq!(to_ui_cstring("foo\0bar").as_bytes(), b"foo");
+        assert_eq!(to_ui_cstring(42).as_bytes(), b"42");
+    }
 }
 
-/// Helper types to work with taps, inspired by Axum's magic functions.
-///
-/// Although the module is called "tap", it is used in multiple places in wsdf, beyond the `tap`
-/// attribute.
+/// Expert info support for the generated code. *Not intended for public use*.
 ///
-/// See <https://github.com/alexpusch/rust-magic-function-params> for how the magic parameter
-/// passing stuff works.
-pub mod tap {
-    use crate::FieldsStore;
+/// Each protocol registers a couple of expert fields, which are used to flag malformed packets in
+/// Wireshark's UI. The generated code stops dissecting once one of these is added, instead of
+/// panicking and bringing down Wireshark with it.
+#[doc(hidden)]
+pub mod expert {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::ffi::{c_char, c_int, CString};
 
-    /// A context holding packet information we might care about. *Meant for internal use*.
-    #[derive(Clone)]
-    #[doc(hidden)]
-    pub struct Context<'a, T: Clone> {
-        pub field: T,
-        pub fields: &'a FieldsStore<'a>,
-        pub pinfo: *mut epan_sys::_packet_info,
-        pub packet: &'a [u8],
-        pub offset: usize,
+    use crate::to_ui_cstring;
+
+    /// Expert fields for a single protocol. Wireshark keeps pointers to these, so they are leaked
+    /// once registered.
+    #[derive(Clone, Copy)]
+    struct ExpertFields {
+        malformed: *mut epan_sys::expert_field,
+        past_end: *mut epan_sys::expert_field,
+        nonzero_padding: *mut epan_sys::expert_field,
+        bad_checksum: *mut epan_sys::expert_field,
+        /// The hf of padding items, which may come with a `nonzero_padding` warning.
+        padding: *mut c_int,
     }
 
-    trait FromContext<'a, T: Clone> {
-        fn from_ctx(ctx: &Context<'a, T>) -> Self;
+    thread_local! {
+        static EXPERT_FIELDS: RefCell<HashMap<c_int, ExpertFields>> = RefCell::default();
     }
 
-    /// The current field, if any. The absence of a value is represented by `Field(())`.
-    ///
-    /// ```rust
-    /// # use wsdf::tap::Field;
-    /// # use wsdf::ProtocolField;
-    /// #[derive(ProtocolField)]
-    /// struct MyProto {
-    ///     #[wsdf(tap = "log_port")]
-    ///     src_port: u16,
-    /// }
-    /// fn log_port(Field(port): Field<u16>) {
-    ///     println!("Received packet from port {port}");
-    /// }
-    /// ```
-    pub struct Field<T: Clone>(pub T);
-    /// A key value store of previous fields encountered and saved. Each key is the Wireshark
-    /// filter for that field.
-    pub struct Fields<'a>(pub &'a FieldsStore<'a>);
-    /// The nanosecond timestamp recorded in the packet capture data.
-    pub struct PacketNanos(pub i64);
-    /// Raw bytes of the packet.
-    pub struct Packet<'a>(pub &'a [u8]);
-    /// Current offset into the packet.
-    ///
-    /// You probably want to use this in combination with [`Packet`] to index and slice the packet
-    /// data.
-    pub struct Offset(pub usize);
+    fn leak_cstr(s: String) -> *const c_char {
+        Box::leak(to_ui_cstring(s).into_boxed_c_str()).as_ptr()
+    }
 
-    impl<T: Clone> FromContext<'_, T> for Field<T> {
-        fn from_ctx(ctx: &Context<T>) -> Self {
-            Self(ctx.field.clone())
+    fn header_field_info(
+        name: *const c_char,
+        abbrev: *const c_char,
+        type_: epan_sys::ftenum,
+    ) -> epan_sys::header_field_info {
+        epan_sys::header_field_info {
+            name,
+            abbrev,
+            type_,
+            display: epan_sys::field_display_e_BASE_NONE as c_int,
+            strings: std::ptr::null(),
+            bitmask: 0,
+            blurb: std::ptr::null(),
+            // Everything below is filled in by Wireshark (this is the HFILL macro).
+            id: -1,
+            parent: 0,
+            ref_type: epan_sys::hf_ref_type_HF_REF_TYPE_NONE,
+            same_name_prev_id: -1,
+            same_name_next: std::ptr::null_mut(),
         }
     }
 
-    impl<T: Clone> FromContext<'_, T> for PacketNanos {
-        fn from_ctx(ctx: &Context<T>) -> Self {
-            let abs_ts = unsafe { (*ctx.pinfo).abs_ts };
-            Self(abs_ts.secs * 1e9 as i64 + abs_ts.nsecs as i64)
+    fn ei_register_info(
+        ids: *mut epan_sys::expert_field,
+        abbrev: String,
+        group: u32,
+        severity: u32,
+        summary: &str,
+    ) -> epan_sys::ei_register_info {
+        epan_sys::ei_register_info {
+            ids,
+            eiinfo: epan_sys::expert_field_info {
+                name: leak_cstr(abbrev),
+                group: group as c_int,
+                severity: severity as c_int,
+                summary: leak_cstr(summary.to_string()),
+                // Everything below is filled in by Wireshark (this is the EXPFILL macro).
+                id: 0,
+                protocol: std::ptr::null(),
+                orig_severity: 0,
+                hf_info: epan_sys::hf_register_info {
+                    p_id: std::ptr::null_mut(),
+                    hfinfo: header_field_info(
+                        std::ptr::null(),
+                        std::ptr::null(),
+                        epan_sys::ftenum_FT_NONE,
+                    ),
+                },
+            },
+        }
+    }
+
+    /// Registers the expert fields for a protocol. Must be called while Wireshark is registering
+    /// protocols.
+    pub fn register(proto_id: c_int, proto_filter: &str) {
+        // An expert_field is `{ int ei; int hf; }` in C, but opaque in the bindings. Both members
+        // start out as -1, which is what EI_INIT expands to.
+        let new_field =
+            || Box::leak(Box::new([-1 as c_int; 2])) as *mut _ as *mut epan_sys::expert_field;
+        let fields = ExpertFields {
+            malformed: new_field(),
+            past_end: new_field(),
+            nonzero_padding: new_field(),
+            bad_checksum: new_field(),
+            padding: Box::leak(Box::new(-1)),
+        };
+
+        let eis = Box::leak(Box::new([
+            ei_register_info(
+                fields.malformed,
+                format!("{proto_filter}.expert.malformed"),
+                epan_sys::PI_MALFORMED,
+                epan_sys::PI_ERROR,
+                "Malformed packet",
+            ),
+            ei_register_info(
+                fields.past_end,
+                format!("{proto_filter}.expert.past_end"),
+                epan_sys::PI_MALFORMED,
+                epan_sys::PI_ERROR,
+                "Field extends past end of packet",
+            ),
+            ei_register_info(
+                fields.nonzero_padding,
+                format!("{proto_filter}.expert.nonzero_padding"),
+                epan_sys::PI_PROTOCOL,
+                epan_sys::PI_WARN,
+                "Padding is not zero",
+            ),
+            ei_register_info(
+                fields.bad_checksum,
+                format!("{proto_filter}.expert.bad_checksum"),
+                epan_sys::PI_CHECKSUM,
+                epan_sys::PI_ERROR,
+                "Bad checksum",
+            ),
+        ]));
+        let hfs = Box::leak(Box::new([epan_sys::hf_register_info {
+            p_id: fields.padding,
+            hfinfo: header_field_info(
+                leak_cstr("Padding".to_string()),
+                leak_cstr(format!("{proto_filter}.padding_bytes")),
+                epan_sys::ftenum_FT_BYTES,
+            ),
+        }]));
+
+        unsafe {
+            let module = epan_sys::expert_register_protocol(proto_id);
+            epan_sys::expert_register_field_array(module, eis.as_mut_ptr(), eis.len() as c_int);
+            epan_sys::proto_register_field_array(proto_id, hfs.as_mut_ptr(), hfs.len() as c_int);
+        }
+
+        EXPERT_FIELDS.with(|experts| experts.borrow_mut().insert(proto_id, fields));
+    }
+
+    fn add(
+        pinfo: *mut epan_sys::_packet_info,
+        tree: *mut epan_sys::_proto_node,
+        tvb: *mut epan_sys::tvbuff,
+        ei: *mut epan_sys::expert_field,
+        start: c_int,
+        length: c_int,
+        msg: CString,
+    ) {
+        unsafe {
+            epan_sys::proto_tree_add_expert_format(
+                tree,
+                pinfo,
+                ei,
+                tvb,
+                start,
+                length,
+                c"%s".as_ptr(),
+                msg.as_ptr(),
+            );
+        }
+    }
+
+    /// The expert field for bad checksums, which is given to `proto_tree_add_checksum`.
+    pub fn bad_checksum(proto_id: c_int) -> *mut epan_sys::expert_field {
+        get(proto_id).map_or(std::ptr::null_mut(), |fields| fields.bad_checksum)
+    }
+
+    fn add_item(
+        tree: *mut epan_sys::_proto_node,
+        tvb: *mut epan_sys::tvbuff,
+        hf: *mut c_int,
+        start: c_int,
+        length: c_int,
+    ) {
+        unsafe {
+            epan_sys::proto_tree_add_item(tree, *hf, tvb, start, length, epan_sys::ENC_NA);
+        }
+    }
+
+    fn get(proto_id: c_int) -> Option<ExpertFields> {
+        EXPERT_FIELDS.with(|experts| experts.borrow().get(&proto_id).copied())
+    }
+
+    fn fits<S: TryInto<usize>>(captured_len: usize, start: c_int, size: S) -> bool {
+        usize::try_from(start)
+            .ok()
+            .zip(size.try_into().ok())
+            .and_then(|(start, size)| start.checked_add(size))
+            .is_some_and(|end| end <= captured_len)
+    }
+
+    /// Checks that `size` bytes starting at `start` are available in the captured packet data. If
+    /// not, an expert item is added to `tree` and `false` is returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_bounds<S: TryInto<usize>>(
+        pinfo: *mut epan_sys::_packet_info,
+        tree: *mut epan_sys::_proto_node,
+        tvb: *mut epan_sys::tvbuff,
+        proto_id: c_int,
+        captured_len: usize,
+        start: c_int,
+        size: S,
+        field_name: &str,
+    ) -> bool {
+        if fits(captured_len, start, size) {
+            return true;
+        }
+
+        // The expert item itself must stay within the captured data, or Wireshark will throw.
+        let start = start.clamp(0, captured_len as c_int);
+        let remaining = captured_len as c_int - start;
+        if let Some(fields) = get(proto_id) {
+            let msg = to_ui_cstring(format!("{field_name} extends past end of packet"));
+            add(pinfo, tree, tvb, fields.past_end, start, remaining, msg);
+        }
+        false
+    }
+
+    /// Flags the packet as malformed, with a short description of what went wrong.
+    pub fn add_malformed(
+        pinfo: *mut epan_sys::_packet_info,
+        tree: *mut epan_sys::_proto_node,
+        tvb: *mut epan_sys::tvbuff,
+        proto_id: c_int,
+        start: c_int,
+        msg: &str,
+    ) {
+        if let Some(fields) = get(proto_id) {
+            let msg = to_ui_cstring(format!("Malformed packet: {msg}"));
+            add(pinfo, tree, tvb, fields.malformed, start, 0, msg);
+        }
+    }
+
+    /// Shows some padding in the tree, with a warning if any of its bytes are not zero.
+    pub fn add_padding(
+        pinfo: *mut epan_sys::_packet_info,
+        tree: *mut epan_sys::_proto_node,
+        tvb: *mut epan_sys::tvbuff,
+        proto_id: c_int,
+        start: c_int,
+        padding: &[u8],
+    ) {
+        if padding.is_empty() {
+            return;
+        }
+        if let Some(fields) = get(proto_id) {
+            let len = padding.len() as c_int;
+            add_item(tree, tvb, fields.padding, start, len);
+            if padding.iter().any(|b| *b != 0) {
+                let msg = to_ui_cstring("Padding is not zero");
+                add(pinfo, tree, tvb, fields.nonzero_padding, start, len, msg);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test_bounds {
+        use super::fits;
+        use std::ffi::c_int;
+
+        #[test]
+        fn fits_within_captured_data() {
+            assert!(fits(8, 0, 8u16));
+            assert!(fits(8, 4, 4 as c_int));
+            assert!(fits(8, 8, 0u8));
+        }
+
+        #[test]
+        fn does_not_fit_past_end_or_with_bogus_sizes() {
+            assert!(!fits(8, 4, 5u16));
+            assert!(!fits(8, 9, 0u8));
+            assert!(!fits(8, -1, 1u8));
+            assert!(!fits(8, 0, -1i8));
+            assert!(!fits(8, 1, u64::MAX));
+        }
+    }
+}
+
+/// Checksums which can be verified with the `checksum` field attribute.
+///
+/// Each function takes the bytes covered by the checksum, and returns the value which the checksum
+/// field should hold.
+pub mod checksum {
+    /// CRC-16/X-25, the CCITT CRC used by HDLC and PPP.
+    pub fn crc16(data: &[u8]) -> u32 {
+        let mut crc = u16::MAX;
+        for byte in data {
+            crc ^= *byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0x8408
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc as u32
+    }
+
+    /// CRC-32, as used by Ethernet and zlib.
+    pub fn crc32(data: &[u8]) -> u32 {
+        reflected_crc32(0xEDB88320, data)
+    }
+
+    /// CRC-32C (Castagnoli), as used by iSCSI and SCTP.
+    pub fn crc32c(data: &[u8]) -> u32 {
+        reflected_crc32(0x82F63B78, data)
+    }
+
+    fn reflected_crc32(poly: u32, data: &[u8]) -> u32 {
+        let mut crc = u32::MAX;
+        for byte in data {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ poly
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    /// The Internet checksum from RFC 1071, i.e. the ones' complement of the ones' complement sum
+    /// of big endian 16-bit words. An odd trailing byte is padded with a zero.
+    pub fn internet(data: &[u8]) -> u32 {
+        let mut sum: u32 = 0;
+        for word in data.chunks(2) {
+            let word = match word {
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+                [hi] => u16::from_be_bytes([*hi, 0]),
+                _ => unreachable!(),
+            };
+            sum += word as u32;
+            sum = (sum & 0xFFFF) + (sum >> 16);
+        }
+        !sum & 0xFFFF
+    }
+
+    /// Adler-32, as used by zlib.
+    pub fn adler32(data: &[u8]) -> u32 {
+        let (mut a, mut b) = (1u32, 0u32);
+        for byte in data {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        (b << 16) | a
+    }
+
+    /// Fletcher-16, over bytes.
+    pub fn fletcher16(data: &[u8]) -> u32 {
+        let (mut sum1, mut sum2) = (0u32, 0u32);
+        for byte in data {
+            sum1 = (sum1 + *byte as u32) % 255;
+            sum2 = (sum2 + sum1) % 255;
+        }
+        (sum2 << 8) | sum1
+    }
+
+    /// Fletcher-32, over little endian 16-bit words. An odd trailing byte is padded with a zero.
+    pub fn fletcher32(data: &[u8]) -> u32 {
+        let (mut sum1, mut sum2) = (0u32, 0u32);
+        for word in data.chunks(2) {
+            let word = match word {
+                [lo, hi] => u16::from_le_bytes([*lo, *hi]),
+                [lo] => u16::from_le_bytes([*lo, 0]),
+                _ => unreachable!(),
+            };
+            sum1 = (sum1 + word as u32) % 65535;
+            sum2 = (sum2 + sum1) % 65535;
+        }
+        (sum2 << 16) | sum1
+    }
+
+    #[cfg(test)]
+    mod test_checksum {
+        use super::*;
+
+        #[test]
+        fn crcs_match_check_values() {
+            assert_eq!(crc16(b"123456789"), 0x906E);
+            assert_eq!(crc32(b"123456789"), 0xCBF43926);
+            assert_eq!(crc32c(b"123456789"), 0xE3069283);
+        }
+
+        #[test]
+        fn sums_match_known_values() {
+            // The example from RFC 1071.
+            assert_eq!(
+                internet(&[0x00, 0x01, 0xF2, 0x03, 0xF4, 0xF5, 0xF6, 0xF7]),
+                0x220D
+            );
+            // Summing the checksum along with the data gives zero.
+            let with_checksum = [0x00, 0x01, 0xF2, 0x03, 0xF4, 0xF5, 0xF6, 0xF7, 0x22, 0x0D];
+            assert_eq!(internet(&with_checksum), 0);
+            assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+            assert_eq!(fletcher16(b"abcde"), 0xC8F0);
+            assert_eq!(fletcher32(b"abcde"), 0xF04FC729);
+            assert_eq!(fletcher32(b"abcdef"), 0x56502D2A);
+        }
+    }
+}
+
+/// A data type whose fields can be registered in Wireshark and dissected. *Not intended for public
+/// use*.
+///
+/// Dissection returns the number of bytes consumed. If the packet turns out to be malformed, an
+/// expert item is added and the bytes consumed up to that point are returned as an error.
+#[diagnostic::on_unimplemented(
+    note = "wsdf's own types, such as `U24` or `Ether`, are only recognized when written with their path, e.g. `wsdf::types::Ether`"
+)]
+pub trait ProtocolField {
+    #[allow(clippy::too_many_arguments)]
+    fn dissect<'a>(
+        __wsdf_start: c_int,
+        __wsdf_tvb: *mut epan_sys::tvbuff,
+        __wsdf_parent: *mut epan_sys::_proto_node,
+        __wsdf_prefix: &str,
+        __wsdf_dispatch: VariantDispatch,
+        __wsdf_subtree_label: SubtreeLabel,
+        __wsdf_tvb_buf: &'a [u8],
+        __wsdf_pinfo: *mut epan_sys::_packet_info,
+        __wsdf_proto_tree_root: *mut epan_sys::_proto_node,
+        __wsdf_fields_store: &mut FieldsStore<'a>,
+        __wsdf_byte_order: u32,
+    ) -> Result<c_int, c_int>;
+
+    fn register(
+        __wsdf_prefix: &str,
+        __wsdf_proto_id: c_int,
+        __wsdf_field_ident: FieldIdent,
+        __wsdf_field_blurb: FieldBlurb,
+    );
+
+    fn ett() -> c_int;
+
+    fn proto_id() -> &'static mut c_int;
+
+    fn hf_map(op: HfMapOp) -> Option<c_int>;
+
+    fn subdissector_map(op: SubdissectorMapOp) -> Option<epan_sys::dissector_table_t>;
+}
+
+/// A data type which represents the root of the protocol. *Not intended for public use.*
+pub trait Protocol: ProtocolField {
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn dissect_main(
+        __wsdf_tvb: *mut epan_sys::tvbuff,
+        __wsdf_pinfo: *mut epan_sys::_packet_info,
+        __wsdf_tree: *mut epan_sys::_proto_node,
+        __wsdf_data: *mut c_void,
+    ) -> c_int;
+
+    extern "C" fn proto_register();
+
+    extern "C" fn proto_reg_handoff();
+}
+
+/// A table of names for the values of an integer field. Derive it with
+/// [`ValueStrings`](macro@ValueStrings), and use it through the `value_strings` field attribute.
+pub trait ValueStrings {
+    /// Returns a pointer to the table, suitable for a header field's `strings`, and the display
+    /// flags which must be set alongside it. The table's layout depends on the field's Wireshark
+    /// type, since 64-bit fields need a `val64_string` table.
+    fn strings(ws_type: epan_sys::ftenum) -> (*const c_void, c_int);
+}
+
+/// Builds a null-terminated value string table out of `(min, max, name)` entries. *Not intended
+/// for public use*.
+///
+/// A `range_string` table is built if any entry spans more than one value. Otherwise we build a
+/// `val64_string` table for 64-bit fields, and a plain `value_string` table for the rest. The
+/// table is leaked, since Wireshark keeps a pointer to it for as long as the field exists.
+///
+/// Negative values are given in two's complement, i.e. -1 is `u64::MAX`. Wireshark looks up the
+/// values of fields up to 32 bits wide as a `u32`, so their entries are cut down to 32 bits.
+#[doc(hidden)]
+pub fn make_value_strings(
+    entries: &[(u64, u64, *const c_char)],
+    ws_type: epan_sys::ftenum,
+) -> (*const c_void, c_int) {
+    fn leak<T>(table: Vec<T>) -> *const c_void {
+        Box::leak(table.into_boxed_slice()).as_ptr() as *const c_void
+    }
+
+    let is_64_bit = matches!(
+        ws_type,
+        epan_sys::ftenum_FT_UINT40
+            | epan_sys::ftenum_FT_UINT48
+            | epan_sys::ftenum_FT_UINT56
+            | epan_sys::ftenum_FT_UINT64
+            | epan_sys::ftenum_FT_INT40
+            | epan_sys::ftenum_FT_INT48
+            | epan_sys::ftenum_FT_INT56
+            | epan_sys::ftenum_FT_INT64
+    );
+    let lookup_value = |value: u64| {
+        if is_64_bit {
+            value
+        } else {
+            value as u32 as u64
+        }
+    };
+
+    if entries.iter().any(|(min, max, _)| min != max) {
+        let table = entries
+            .iter()
+            .map(|&(value_min, value_max, strptr)| epan_sys::range_string {
+                value_min: lookup_value(value_min),
+                value_max: lookup_value(value_max),
+                strptr,
+            })
+            .chain(std::iter::once(epan_sys::range_string {
+                value_min: 0,
+                value_max: 0,
+                strptr: std::ptr::null(),
+            }))
+            .collect();
+        (leak(table), epan_sys::BASE_RANGE_STRING as c_int)
+    } else if is_64_bit {
+        let table = entries
+            .iter()
+            .map(|&(value, _, strptr)| epan_sys::val64_string { value, strptr })
+            .chain(std::iter::once(epan_sys::val64_string {
+                value: 0,
+                strptr: std::ptr::null(),
+            }))
+            .collect();
+        (leak(table), epan_sys::BASE_VAL64_STRING as c_int)
+    } else {
+        let table = entries
+            .iter()
+            .map(|&(value, _, strptr)| epan_sys::value_string {
+                value: lookup_value(value) as u32,
+                strptr,
+            })
+            .chain(std::iter::once(epan_sys::value_string {
+                value: 0,
+                strptr: std::ptr::null(),
+            }))
+            .collect();
+        (leak(table), 0)
+    }
+}
+
+/// Builds a `true_false_string` out of two labels. *Not intended for public use*.
+///
+/// Like value string tables, it is leaked, since Wireshark keeps a pointer to it.
+#[doc(hidden)]
+pub fn make_tfs(true_string: *const c_char, false_string: *const c_char) -> *const c_void {
+    let tfs = epan_sys::true_false_string {
+        true_string,
+        false_string,
+    };
+    Box::leak(Box::new(tfs)) as *const epan_sys::true_false_string as *const c_void
+}
+
+/// Reads a string out of the packet, which Wireshark converts from the given encoding into UTF-8.
+/// *Not intended for public use*.
+///
+/// # Safety
+///
+/// `pinfo` and `tvb` must be valid, and the string must lie within the captured data. The string
+/// is allocated in the packet's memory pool, so it must not outlive the dissection of the packet.
+#[doc(hidden)]
+pub unsafe fn get_string<'a>(
+    pinfo: *mut epan_sys::_packet_info,
+    tvb: *mut epan_sys::tvbuff,
+    start: c_int,
+    length: c_int,
+    encoding: u32,
+) -> &'a str {
+    let s = epan_sys::tvb_get_string_enc((*pinfo).pool, tvb, start, length, encoding);
+    // Wireshark replaces anything it cannot decode, so this should always be valid UTF-8.
+    std::ffi::CStr::from_ptr(s as *const c_char)
+        .to_str()
+        .unwrap_or_default()
+}
+
+/// Finds the size of a NUL-terminated string starting at `pos`, including the terminator. With
+/// UTF-16, UCS-2 and UCS-4, the terminator is a NUL character as wide as the others, which must
+/// line up with the start of the string. *Not intended for public use*.
+///
+/// If there is no terminator, the size runs one byte past the end of the packet, so that the
+/// bounds check on the string fails.
+#[doc(hidden)]
+pub fn nul_terminated_size(buf: &[u8], pos: usize, encoding: u32) -> usize {
+    let width = match encoding & epan_sys::ENC_CHARENCODING_MASK {
+        epan_sys::ENC_UTF_16 | epan_sys::ENC_UCS_2 => 2,
+        epan_sys::ENC_UCS_4 => 4,
+        _ => 1,
+    };
+    let rest = buf.get(pos..).unwrap_or_default();
+    rest.chunks_exact(width)
+        .position(|c| c.iter().all(|b| *b == 0))
+        .map(|i| (i + 1) * width)
+        .unwrap_or(rest.len() + 1)
+}
+
+#[cfg(test)]
+mod test_strings {
+    use super::*;
+
+    #[test]
+    fn nul_terminated_utf16_string_before_another_field() {
+        // "A\u{100}" in UTF-16LE, its terminator, then a u16 field. The 0x00 0x00 spanning the
+        // two characters is not aligned, so it does not end the string.
+        let packet = [0x41, 0x00, 0x00, 0x01, 0x00, 0x00, 0x34, 0x12];
+        let enc = epan_sys::ENC_UTF_16 | epan_sys::ENC_LITTLE_ENDIAN;
+        let size = nul_terminated_size(&packet, 0, enc);
+        assert_eq!(size, 6);
+        assert_eq!(u16::from_le_bytes([packet[size], packet[size + 1]]), 0x1234);
+    }
+
+    #[test]
+    fn nul_terminated_size_by_encoding() {
+        let packet = [0x01, 0x41, 0x00, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(nul_terminated_size(&packet, 1, epan_sys::ENC_ASCII), 2);
+        assert_eq!(nul_terminated_size(&packet, 1, epan_sys::ENC_UCS_2), 6);
+        assert_eq!(nul_terminated_size(&packet, 1, epan_sys::ENC_UCS_4), 8);
+        // Without a terminator, the string runs past the end.
+        assert_eq!(nul_terminated_size(&packet, 8, epan_sys::ENC_UTF_16), 2);
+        assert_eq!(nul_terminated_size(&packet, 0, epan_sys::ENC_UTF_8), 3);
+        assert_eq!(nul_terminated_size(&packet, 9, epan_sys::ENC_UTF_8), 1);
+    }
+}
+
+#[cfg(test)]
+mod test_value_strings {
+    use super::*;
+
+    const A: *const c_char = c"A".as_ptr();
+    const B: *const c_char = c"B".as_ptr();
+
+    #[test]
+    fn picks_table_layout() {
+        let (ptr, flags) = make_value_strings(&[(1, 1, A), (2, 2, B)], epan_sys::ftenum_FT_UINT16);
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::value_string, 3) };
+        assert_eq!(flags, 0);
+        assert_eq!((table[1].value, table[1].strptr), (2, B));
+        assert!(table[2].strptr.is_null());
+
+        let (ptr, flags) =
+            make_value_strings(&[(u64::MAX, u64::MAX, A)], epan_sys::ftenum_FT_INT64);
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::val64_string, 2) };
+        assert_eq!(flags, epan_sys::BASE_VAL64_STRING as c_int);
+
+        let (_, flags) = make_value_strings(&[(1, 1, A)], epan_sys::ftenum_FT_UINT48);
+        assert_eq!(flags, epan_sys::BASE_VAL64_STRING as c_int);
+        assert_eq!(table[0].value, u64::MAX);
+        assert!(table[1].strptr.is_null());
+
+        let (ptr, flags) = make_value_strings(&[(1, 1, A), (10, 20, B)], epan_sys::ftenum_FT_UINT8);
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::range_string, 3) };
+        assert_eq!(flags, epan_sys::BASE_RANGE_STRING as c_int);
+        assert_eq!((table[1].value_min, table[1].value_max), (10, 20));
+        assert!(table[2].strptr.is_null());
+    }
+
+    #[test]
+    fn negative_values_match_the_field_width() {
+        let minus_one = -1i64 as u64;
+
+        let (ptr, _) = make_value_strings(&[(minus_one, minus_one, A)], epan_sys::ftenum_FT_INT8);
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::value_string, 2) };
+        assert_eq!(table[0].value, u32::MAX);
+
+        let (ptr, _) = make_value_strings(
+            &[(minus_one, minus_one, A), (10, 20, B)],
+            epan_sys::ftenum_FT_INT32,
+        );
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::range_string, 3) };
+        assert_eq!(
+            (table[0].value_min, table[0].value_max),
+            (0xffff_ffff, 0xffff_ffff)
+        );
+        assert_eq!((table[1].value_min, table[1].value_max), (10, 20));
+
+        let (ptr, _) = make_value_strings(
+            &[(minus_one, minus_one, A), (10, 20, B)],
+            epan_sys::ftenum_FT_INT64,
+        );
+        let table = unsafe { std::slice::from_raw_parts(ptr as *const epan_sys::range_string, 3) };
+        assert_eq!(table[0].value_min, u64::MAX);
+    }
+}
+
+/// A set of options shown in the protocol's section of Wireshark's preferences dialog. Derive it
+/// with [`Preferences`](macro@Preferences), and attach it to the protocol with
+/// `#[wsdf(prefs = "...")]`. The current values can be read from taps through
+/// [`Prefs`](tap::Prefs).
+pub trait Preferences: Sized {
+    /// Whether any range preference names a dissector table to bind the protocol to. *Not
+    /// intended for public use*.
+    #[doc(hidden)]
+    const BINDS_TABLE: bool;
+
+    /// Registers each preference with the protocol's preferences module. *Not intended for public
+    /// use*.
+    #[doc(hidden)]
+    fn register(module: *mut epan_sys::module_t);
+
+    /// Binds the protocol's dissector handle to the values of any range preferences which name a
+    /// dissector table, replacing the values it was bound to before. This is called at handoff,
+    /// and again whenever the preferences change. *Not intended for public use*.
+    #[doc(hidden)]
+    fn bind(handle: epan_sys::dissector_handle_t);
+
+    /// Returns the current value of each preference.
+    fn current() -> Self;
+}
+
+/// An enum which can be used as a preference, shown as a drop-down list. It is implemented by
+/// [`ValueStrings`](macro@ValueStrings) enums whose variants each name a single value.
+pub trait PrefEnum: Sized {
+    /// Returns a null-terminated table of the variants. *Not intended for public use*.
+    #[doc(hidden)]
+    fn enum_vals() -> *const epan_sys::enum_val_t;
+
+    /// The value which Wireshark stores for the variant.
+    fn to_pref(&self) -> c_int;
+
+    /// The variant for a value stored by Wireshark, if there is one.
+    fn from_pref(value: c_int) -> Option<Self>;
+}
+
+/// Builds a null-terminated `enum_val_t` table out of `(name, description, value)` entries. *Not
+/// intended for public use*.
+///
+/// The table is leaked, since Wireshark keeps a pointer to it for as long as the preference
+/// exists.
+#[doc(hidden)]
+pub fn make_enum_vals(
+    entries: &[(*const c_char, *const c_char, c_int)],
+) -> *const epan_sys::enum_val_t {
+    let table = entries
+        .iter()
+        .map(|&(name, description, value)| epan_sys::enum_val_t {
+            name,
+            description,
+            value,
+        })
+        .chain(std::iter::once(epan_sys::enum_val_t {
+            name: std::ptr::null(),
+            description: std::ptr::null(),
+            value: 0,
+        }))
+        .collect::<Vec<_>>();
+    Box::leak(table.into_boxed_slice()).as_ptr()
+}
+
+/// The value of a range preference, such as a set of ports, as a list of inclusive ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefRange(pub Vec<std::ops::RangeInclusive<u32>>);
+
+impl PrefRange {
+    /// Returns true if the value lies within any of the ranges.
+    pub fn contains(&self, value: u32) -> bool {
+        self.0.iter().any(|range| range.contains(&value))
+    }
+
+    /// Copies the ranges out of a Wireshark `range_t`. *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `range` must either be null or point to a valid `range_t`.
+    #[doc(hidden)]
+    pub unsafe fn from_raw(range: *const epan_sys::range_t) -> Self {
+        if range.is_null() {
+            return Self::default();
+        }
+        // The ranges are a flexible array member, so there may be more than the one declared.
+        let nranges = (*range).nranges as usize;
+        let ranges = std::ptr::addr_of!((*range).ranges) as *const epan_sys::range_admin_t;
+        let ranges = std::slice::from_raw_parts(ranges, nranges);
+        Self(ranges.iter().map(|range| range.low..=range.high).collect())
+    }
+}
+
+/// Copies the value of a string preference. *Not intended for public use*.
+///
+/// # Safety
+///
+/// `s` must either be null or point to a NUL-terminated string.
+#[doc(hidden)]
+pub unsafe fn pref_string(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test_prefs {
+    use super::*;
+
+    #[test]
+    fn range_from_raw() {
+        let range = epan_sys::range_t {
+            nranges: 1,
+            ranges: [epan_sys::range_admin_t {
+                low: 9000,
+                high: 9010,
+            }],
+        };
+        let range = unsafe { PrefRange::from_raw(&range) };
+        assert_eq!(range, PrefRange(vec![9000..=9010]));
+        assert!(range.contains(9005));
+        assert!(!range.contains(9011));
+
+        assert_eq!(
+            unsafe { PrefRange::from_raw(std::ptr::null()) },
+            PrefRange::default()
+        );
+    }
+
+    #[test]
+    fn enum_vals_are_null_terminated() {
+        let vals = make_enum_vals(&[(c"fast".as_ptr(), c"Fast".as_ptr(), 1)]);
+        let vals = unsafe { std::slice::from_raw_parts(vals, 2) };
+        assert_eq!(vals[0].value, 1);
+        assert!(vals[1].name.is_null());
+    }
+}
+
+/// Types for fields which Wireshark understands natively, such as network addresses and times.
+///
+/// Fields of these types are decoded with Wireshark's own getters, so they display the same way
+/// as in built-in dissectors, including name resolution if it is enabled. wsdf recognizes these
+/// types by their path, so they are written as e.g. `wsdf::types::Ether`.
+///
+/// ```rust
+/// #[derive(wsdf::ProtocolField)]
+/// struct Neighbour {
+///     mac: wsdf::types::Ether,
+///     ip: wsdf::types::Ipv4,
+///     last_seen: wsdf::types::EpochNanos,
+///     ttl: wsdf::types::DurationSecs,
+/// }
+/// ```
+pub mod types {
+    use std::ffi::c_int;
+    use std::fmt;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// An IPv4 address, mapped to `FT_IPv4`. This is the same type as [`std::net::Ipv4Addr`],
+    /// which can be used directly too.
+    pub use std::net::Ipv4Addr as Ipv4;
+    /// An IPv6 address, mapped to `FT_IPv6`. This is the same type as [`std::net::Ipv6Addr`],
+    /// which can be used directly too.
+    pub use std::net::Ipv6Addr as Ipv6;
+
+    /// A 48-bit MAC address, mapped to `FT_ETHER`.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Ether(pub [u8; 6]);
+
+    /// A 64-bit extended unique identifier, mapped to `FT_EUI64`.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Eui64(pub [u8; 8]);
+
+    /// A GUID, mapped to `FT_GUID`. The layout follows Wireshark's `e_guid_t`.
+    ///
+    /// The first three groups are read with the field's encoding, which is big endian by default.
+    /// Use `#[wsdf(enc = "ENC_LITTLE_ENDIAN")]` for Microsoft style GUIDs.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Guid {
+        pub data1: u32,
+        pub data2: u16,
+        pub data3: u16,
+        pub data4: [u8; 8],
+    }
+
+    fn write_colon_separated(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+        for (i, b) in bytes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+
+    impl fmt::Display for Ether {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_colon_separated(f, &self.0)
+        }
+    }
+
+    impl fmt::Display for Eui64 {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_colon_separated(f, &self.0)
+        }
+    }
+
+    impl fmt::Display for Guid {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let d = &self.data4;
+            write!(
+                f,
+                "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+                self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7],
+            )
+        }
+    }
+
+    impl From<epan_sys::e_guid_t> for Guid {
+        fn from(guid: epan_sys::e_guid_t) -> Self {
+            Self {
+                data1: guid.data1,
+                data2: guid.data2,
+                data3: guid.data3,
+                data4: guid.data4,
+            }
+        }
+    }
+
+    impl From<Guid> for epan_sys::e_guid_t {
+        fn from(guid: Guid) -> Self {
+            Self {
+                data1: guid.data1,
+                data2: guid.data2,
+                data3: guid.data3,
+                data4: guid.data4,
+            }
+        }
+    }
+
+    // Like the time types below, integers of odd widths are aliases. wsdf picks the width from the
+    // name of the type, and the value is held in the next larger Rust integer.
+
+    /// A 24-bit unsigned integer, mapped to `FT_UINT24`.
+    pub type U24 = u32;
+    /// A 40-bit unsigned integer, mapped to `FT_UINT40`.
+    pub type U40 = u64;
+    /// A 48-bit unsigned integer, mapped to `FT_UINT48`.
+    pub type U48 = u64;
+    /// A 56-bit unsigned integer, mapped to `FT_UINT56`.
+    pub type U56 = u64;
+    /// A 24-bit signed integer, mapped to `FT_INT24`.
+    pub type I24 = i32;
+    /// A 40-bit signed integer, mapped to `FT_INT40`.
+    pub type I40 = i64;
+    /// A 48-bit signed integer, mapped to `FT_INT48`.
+    pub type I48 = i64;
+    /// A 56-bit signed integer, mapped to `FT_INT56`.
+    pub type I56 = i64;
+
+    /// An integer encoded in a variable number of bytes, which is decoded into a `T`.
+    ///
+    /// The encoding is given with `enc`, and is `ENC_VARINT_PROTOBUF` (LEB128) by default. QUIC
+    /// varints use `ENC_VARINT_QUIC`, and zigzag encoded signed integers use `ENC_VARINT_ZIGZAG`.
+    pub type Varint<T> = T;
+    /// A self-delimiting numeric value (RFC 6256), decoded into an unsigned `T`.
+    pub type Sdnv<T> = T;
+
+    /// The most bytes an SDNV may take up, which is enough for any `u64`. *Not intended for
+    /// public use*.
+    #[doc(hidden)]
+    pub const SDNV_MAX_LEN: usize = 10;
+
+    /// Decodes an SDNV from the start of `bytes`, giving its value and size. *Not intended for
+    /// public use*.
+    ///
+    /// Like `tvb_get_varint`, the size is zero if the SDNV does not end within `bytes`, or within
+    /// [`SDNV_MAX_LEN`] bytes.
+    #[doc(hidden)]
+    pub fn get_sdnv(bytes: &[u8]) -> (u64, usize) {
+        let mut value = 0;
+        for (i, b) in bytes.iter().take(SDNV_MAX_LEN).enumerate() {
+            value = value << 7 | (b & 0x7f) as u64;
+            if b & 0x80 == 0 {
+                return (value, i + 1);
+            }
+        }
+        (0, 0)
+    }
+
+    // The time types are aliases, since the name of the type is all that wsdf needs to know how a
+    // time is encoded. Taps receive a `SystemTime` or `Duration` either way.
+
+    /// Seconds since the Unix epoch, as a 32-bit integer. Mapped to `FT_ABSOLUTE_TIME`.
+    pub type EpochSecs = SystemTime;
+    /// Milliseconds since the Unix epoch, as a 64-bit integer. Mapped to `FT_ABSOLUTE_TIME`.
+    pub type EpochMillis = SystemTime;
+    /// Microseconds since the Unix epoch, as a 64-bit integer. Mapped to `FT_ABSOLUTE_TIME`.
+    pub type EpochMicros = SystemTime;
+    /// Nanoseconds since the Unix epoch, as a 64-bit integer. Mapped to `FT_ABSOLUTE_TIME`.
+    pub type EpochNanos = SystemTime;
+    /// A 64-bit NTP timestamp, i.e. 32 bits of seconds since 1900 followed by 32 bits of
+    /// fraction. Mapped to `FT_ABSOLUTE_TIME`. Like Wireshark, timestamps with the top bit clear
+    /// are taken to be from 2036 onwards. A time which the platform cannot represent marks the
+    /// packet as malformed.
+    pub type NtpTime = SystemTime;
+    /// An interval in seconds, as a 32-bit integer. Mapped to `FT_RELATIVE_TIME`.
+    pub type DurationSecs = Duration;
+    /// An interval in milliseconds, as a 64-bit integer. Mapped to `FT_RELATIVE_TIME`.
+    pub type DurationMillis = Duration;
+    /// An interval in microseconds, as a 64-bit integer. Mapped to `FT_RELATIVE_TIME`.
+    pub type DurationMicros = Duration;
+    /// An interval in nanoseconds, as a 64-bit integer. Mapped to `FT_RELATIVE_TIME`.
+    pub type DurationNanos = Duration;
+
+    /// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+    const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+    /// *Not intended for public use*.
+    #[doc(hidden)]
+    pub fn nstime_from_duration(duration: Duration) -> epan_sys::nstime_t {
+        epan_sys::nstime_t {
+            secs: duration.as_secs() as _,
+            nsecs: duration.subsec_nanos() as c_int,
+        }
+    }
+
+    /// *Not intended for public use*.
+    #[doc(hidden)]
+    pub fn nstime_from_time(time: SystemTime) -> epan_sys::nstime_t {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => nstime_from_duration(since),
+            // Wireshark keeps the nanoseconds positive, even before the epoch.
+            Err(err) => {
+                let before = err.duration();
+                let mut nstime = nstime_from_duration(before);
+                nstime.secs = -nstime.secs;
+                if nstime.nsecs > 0 {
+                    nstime.secs -= 1;
+                    nstime.nsecs = 1_000_000_000 - nstime.nsecs;
+                }
+                nstime
+            }
+        }
+    }
+
+    // The getters below are called by the generated code once the bounds have been checked. They
+    // all take an encoding, even if Wireshark ignores it for that type, to keep the codegen simple.
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the address must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_ipv4(tvb: *mut epan_sys::tvbuff, start: c_int, encoding: u32) -> Ipv4 {
+        // Wireshark gives back the address in network byte order, i.e. as it is in the packet.
+        let mut octets = epan_sys::tvb_get_ipv4(tvb, start).to_ne_bytes();
+        if encoding & epan_sys::ENC_LITTLE_ENDIAN != 0 {
+            octets.reverse();
+        }
+        Ipv4::from(octets)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the address must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_ipv6(tvb: *mut epan_sys::tvbuff, start: c_int, _encoding: u32) -> Ipv6 {
+        let mut addr = epan_sys::ws_in6_addr { bytes: [0; 16] };
+        epan_sys::tvb_get_ipv6(tvb, start, &mut addr);
+        Ipv6::from(addr.bytes)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the address must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_ether(tvb: *mut epan_sys::tvbuff, start: c_int, _encoding: u32) -> Ether {
+        let mut addr = Ether::default();
+        epan_sys::tvb_memcpy(tvb, addr.0.as_mut_ptr().cast(), start, addr.0.len());
+        addr
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the identifier must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_eui64(tvb: *mut epan_sys::tvbuff, start: c_int, encoding: u32) -> Eui64 {
+        Eui64(epan_sys::tvb_get_uint64(tvb, start, encoding).to_be_bytes())
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the GUID must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_guid(tvb: *mut epan_sys::tvbuff, start: c_int, encoding: u32) -> Guid {
+        let mut guid = Guid::default().into();
+        epan_sys::tvb_get_guid(tvb, start, &mut guid, encoding);
+        guid.into()
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the integer must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_u128(tvb: *mut epan_sys::tvbuff, start: c_int, encoding: u32) -> u128 {
+        let first = epan_sys::tvb_get_uint64(tvb, start, encoding) as u128;
+        let second = epan_sys::tvb_get_uint64(tvb, start + 8, encoding) as u128;
+        if encoding & epan_sys::ENC_LITTLE_ENDIAN != 0 {
+            second << 64 | first
+        } else {
+            first << 64 | second
+        }
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the integer must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_i128(tvb: *mut epan_sys::tvbuff, start: c_int, encoding: u32) -> i128 {
+        get_u128(tvb, start, encoding) as i128
+    }
+
+    unsafe fn get_count(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+        size: usize,
+    ) -> u64 {
+        let encoding = encoding & epan_sys::ENC_LITTLE_ENDIAN;
+        match size {
+            4 => epan_sys::tvb_get_uint32(tvb, start, encoding) as u64,
+            _ => epan_sys::tvb_get_uint64(tvb, start, encoding),
+        }
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the time must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_epoch_secs(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> SystemTime {
+        UNIX_EPOCH + get_duration_secs(tvb, start, encoding)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the interval must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_duration_secs(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> Duration {
+        Duration::from_secs(get_count(tvb, start, encoding, 4))
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the time must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_epoch_millis(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> SystemTime {
+        UNIX_EPOCH + get_duration_millis(tvb, start, encoding)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the interval must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_duration_millis(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> Duration {
+        Duration::from_millis(get_count(tvb, start, encoding, 8))
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the time must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_epoch_micros(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> SystemTime {
+        UNIX_EPOCH + get_duration_micros(tvb, start, encoding)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the interval must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_duration_micros(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> Duration {
+        Duration::from_micros(get_count(tvb, start, encoding, 8))
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the time must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_epoch_nanos(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> SystemTime {
+        UNIX_EPOCH + get_duration_nanos(tvb, start, encoding)
+    }
+
+    /// *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the interval must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_duration_nanos(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> Duration {
+        Duration::from_nanos(get_count(tvb, start, encoding, 8))
+    }
+
+    /// Returns `None` if the platform cannot represent the time. *Not intended for public use*.
+    ///
+    /// # Safety
+    ///
+    /// `tvb` must be valid, and the timestamp must lie within the captured data.
+    #[doc(hidden)]
+    pub unsafe fn get_ntp_time(
+        tvb: *mut epan_sys::tvbuff,
+        start: c_int,
+        encoding: u32,
+    ) -> Option<SystemTime> {
+        let secs = get_count(tvb, start, encoding, 4);
+        let fraction = get_count(tvb, start + 4, encoding, 4);
+        ntp_to_system_time(secs, fraction)
+    }
+
+    /// Like Wireshark, we take timestamps with the top bit clear to be in the era starting in 2036.
+    /// Times in era 0 from 1968 onwards are before the Unix epoch.
+    fn ntp_to_system_time(secs: u64, fraction: u64) -> Option<SystemTime> {
+        let secs = if secs & 0x8000_0000 == 0 {
+            secs + (1 << 32)
+        } else {
+            secs
+        };
+        let nanos = Duration::from_nanos((fraction * 1_000_000_000) >> 32);
+        let secs = secs as i64 - NTP_UNIX_OFFSET;
+        let time = match u64::try_from(secs) {
+            Ok(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+            Err(_) => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+        };
+        time?.checked_add(nanos)
+    }
+
+    #[cfg(test)]
+    mod test_display {
+        use super::*;
+
+        #[test]
+        fn formats_like_wireshark() {
+            let mac = Ether([0x00, 0x1b, 0x21, 0xaa, 0xbb, 0x0c]);
+            assert_eq!(mac.to_string(), "00:1b:21:aa:bb:0c");
+
+            let guid = Guid {
+                data1: 0x6ba7b810,
+                data2: 0x9dad,
+                data3: 0x11d1,
+                data4: [0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8],
+            };
+            assert_eq!(guid.to_string(), "6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+        }
+    }
+
+    #[cfg(test)]
+    mod test_sdnv {
+        use super::*;
+
+        #[test]
+        fn rfc_6256_examples() {
+            assert_eq!(get_sdnv(&[0x7f]), (0x7f, 1));
+            assert_eq!(get_sdnv(&[0x95, 0x3c, 0xff]), (0xabc, 2));
+            assert_eq!(get_sdnv(&[0x81, 0x84, 0x34]), (0x4234, 3));
+            assert_eq!(get_sdnv(&[0x81, 0x84]).1, 0);
+            assert_eq!(get_sdnv(&[0x80; 11]).1, 0);
+        }
+    }
+
+    #[cfg(test)]
+    mod test_times {
+        use super::*;
+
+        #[test]
+        fn ntp_eras() {
+            // 2024-01-01T00:00:00.5Z, which is in era 0.
+            let time = ntp_to_system_time(0xe93c_7f00, 0x8000_0000).unwrap();
+            let since = time.duration_since(UNIX_EPOCH).unwrap();
+            assert_eq!(since, Duration::new(1_704_067_200, 500_000_000));
+
+            // Era 1 starts at 2036-02-07T06:28:16Z.
+            let time = ntp_to_system_time(0, 0).unwrap();
+            assert_eq!(
+                time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                2_085_978_496
+            );
+        }
+
+        #[test]
+        fn ntp_before_unix_epoch() {
+            // 1968-01-20T03:14:08.25Z, the earliest time in era 0.
+            let time = ntp_to_system_time(0x8000_0000, 0x4000_0000).unwrap();
+            let before = UNIX_EPOCH.duration_since(time).unwrap();
+            assert_eq!(before, Duration::new(61_505_151, 750_000_000));
+
+            let time = ntp_to_system_time(0x8000_0000, 0).unwrap();
+            let before = UNIX_EPOCH.duration_since(time).unwrap();
+            assert_eq!(before, Duration::from_secs(61_505_152));
+        }
+
+        #[test]
+        fn nstime_before_epoch() {
+            let nstime = nstime_from_time(UNIX_EPOCH - Duration::from_millis(1500));
+            assert_eq!((nstime.secs, nstime.nsecs), (-2, 500_000_000));
+        }
+    }
+}
+
+/// Helper types to work with taps, inspired by Axum's magic functions.
+///
+/// Although the module is called "tap", it is used in multiple places in wsdf, beyond the `tap`
+/// attribute.
+///
+/// See <https://github.com/alexpusch/rust-magic-function-params> for how the magic parameter
+/// passing stuff works.
+pub mod tap {
+    use crate::FieldsStore;
+
+    /// A context holding packet information we might care about. *Meant for internal use*.
+    #[derive(Clone)]
+    #[doc(hidden)]
+    pub struct Context<'a, T: Clone> {
+        pub field: T,
+        pub fields: &'a FieldsStore<'a>,
+        pub pinfo: *mut epan_sys::_packet_info,
+        pub packet: &'a [u8],
+        pub offset: usize,
+    }
+
+    trait FromContext<'a, T: Clone> {
+        fn from_ctx(ctx: &Context<'a, T>) -> Self;
+    }
+
+    /// The current field, if any. The absence of a value is represented by `Field(())`.
+    ///
+    /// ```rust
+    /// # use wsdf::tap::Field;
+    /// # use wsdf::ProtocolField;
+    /// #[derive(ProtocolField)]
+    /// struct MyProto {
+    ///     #[wsdf(tap = "log_port")]
+    ///     src_port: u16,
+    /// }
+    /// fn log_port(Field(port): Field<u16>) {
+    ///     println!("Received packet from port {port}");
+    /// }
+    /// ```
+    pub struct Field<T: Clone>(pub T);
+    /// A key value store of previous fields encountered and saved. Each key is the Wireshark
+    /// filter for that field.
+    pub struct Fields<'a>(pub &'a FieldsStore<'a>);
+    /// The nanosecond timestamp recorded in the packet capture data.
+    pub struct PacketNanos(pub i64);
+    /// Raw bytes of the packet.
+    pub struct Packet<'a>(pub &'a [u8]);
+    /// Current offset into the packet.
+    ///
+    /// You probably want to use this in combination with [`Packet`] to index and slice the packet
+    /// data.
+    pub struct Offset(pub usize);
+    /// The network layer source and destination addresses of the packet, if they are IP
+    /// addresses. Useful for checksums which cover a pseudo-header, like UDP's.
+    pub struct Addresses {
+        pub src: Option<std::net::IpAddr>,
+        pub dst: Option<std::net::IpAddr>,
+    }
+    /// The current values of the protocol's preferences. See [`Preferences`](crate::Preferences).
+    ///
+    /// ```rust
+    /// # use wsdf::tap::{Columns, Field, Prefs};
+    /// # use wsdf::ProtocolField;
+    /// #[derive(wsdf::Preferences)]
+    /// struct MyPrefs {
+    ///     show_seq: bool,
+    /// }
+    /// #[derive(ProtocolField)]
+    /// struct MyProto {
+    ///     #[wsdf(tap = "show_seq")]
+    ///     seq: u64,
+    /// }
+    /// fn show_seq(Field(seq): Field<u64>, Prefs(prefs): Prefs<MyPrefs>, cols: Columns) {
+    ///     if prefs.show_seq {
+    ///         cols.append_info(format!("Seq={seq}"));
+    ///     }
+    /// }
+    /// ```
+    pub struct Prefs<P: crate::Preferences>(pub P);
+    /// The columns of the packet list, e.g. the Info column.
+    ///
+    /// ```rust
+    /// # use wsdf::tap::{Columns, Field};
+    /// # use wsdf::ProtocolField;
+    /// #[derive(ProtocolField)]
+    /// struct MyProto {
+    ///     #[wsdf(tap = "show_seq")]
+    ///     seq: u64,
+    /// }
+    /// fn show_seq(Field(seq): Field<u64>, cols: Columns) {
+    ///     cols.append_info(format!("Seq={seq}"));
+    /// }
+    /// ```
+    pub struct Columns(*mut epan_sys::_packet_info);
+
+    impl Columns {
+        /// *Not intended for public use*.
+        #[doc(hidden)]
+        pub fn new(pinfo: *mut epan_sys::_packet_info) -> Self {
+            Self(pinfo)
+        }
+
+        fn cinfo(&self) -> *mut epan_sys::column_info {
+            if self.0.is_null() {
+                return std::ptr::null_mut();
+            }
+            unsafe { (*self.0).cinfo }
+        }
+
+        fn set(&self, col: std::ffi::c_int, text: impl std::fmt::Display) {
+            let cinfo = self.cinfo();
+            if cinfo.is_null() {
+                return;
+            }
+            let text = crate::to_ui_cstring(text);
+            unsafe { epan_sys::col_add_str(cinfo, col, text.as_ptr()) };
+        }
+
+        /// Replaces the text of the Info column.
+        pub fn set_info(&self, text: impl std::fmt::Display) {
+            self.set(epan_sys::COL_INFO as std::ffi::c_int, text);
+        }
+
+        /// Appends to the Info column, separated from any existing text by a comma.
+        pub fn append_info(&self, text: impl std::fmt::Display) {
+            let cinfo = self.cinfo();
+            if cinfo.is_null() {
+                return;
+            }
+            let text = crate::to_ui_cstring(text);
+            unsafe {
+                epan_sys::col_append_sep_str(
+                    cinfo,
+                    epan_sys::COL_INFO as std::ffi::c_int,
+                    c", ".as_ptr(),
+                    text.as_ptr(),
+                )
+            };
+        }
+
+        /// Clears the Info column.
+        pub fn clear_info(&self) {
+            let cinfo = self.cinfo();
+            if cinfo.is_null() {
+                return;
+            }
+            unsafe { epan_sys::col_clear(cinfo, epan_sys::COL_INFO as std::ffi::c_int) };
+        }
+
+        /// Replaces the text of the Source column.
+        pub fn set_src(&self, text: impl std::fmt::Display) {
+            self.set(epan_sys::COL_DEF_SRC as std::ffi::c_int, text);
+        }
+
+        /// Replaces the text of the Destination column.
+        pub fn set_dst(&self, text: impl std::fmt::Display) {
+            self.set(epan_sys::COL_DEF_DST as std::ffi::c_int, text);
+        }
+    }
+
+    impl<T: Clone> FromContext<'_, T> for Field<T> {
+        fn from_ctx(ctx: &Context<T>) -> Self {
+            Self(ctx.field.clone())
+        }
+    }
+
+    impl<T: Clone> FromContext<'_, T> for PacketNanos {
+        fn from_ctx(ctx: &Context<T>) -> Self {
+            let abs_ts = unsafe { (*ctx.pinfo).abs_ts };
+            Self(abs_ts.secs * 1e9 as i64 + abs_ts.nsecs as i64)
         }
     }
 
@@ -801,6 +3197,51 @@ pub mod tap {
         }
     }
 
+    fn ip_addr(addr: &epan_sys::address) -> Option<std::net::IpAddr> {
+        if addr.data.is_null() {
+            return None;
+        }
+        // SAFETY: Wireshark guarantees that `data` points to `len` bytes.
+        let data = unsafe { std::slice::from_raw_parts(addr.data as *const u8, addr.len as usize) };
+        match addr.type_ as epan_sys::address_type {
+            epan_sys::address_type_AT_IPv4 => {
+                <[u8; 4]>::try_from(data).ok().map(std::net::IpAddr::from)
+            }
+            epan_sys::address_type_AT_IPv6 => {
+                <[u8; 16]>::try_from(data).ok().map(std::net::IpAddr::from)
+            }
+            _ => None,
+        }
+    }
+
+    impl<T: Clone> FromContext<'_, T> for Addresses {
+        fn from_ctx(ctx: &Context<T>) -> Self {
+            // SAFETY: a non-null pinfo is valid for the duration of the dissection.
+            match unsafe { ctx.pinfo.as_ref() } {
+                Some(pinfo) => Self {
+                    src: ip_addr(&pinfo.net_src),
+                    dst: ip_addr(&pinfo.net_dst),
+                },
+                None => Self {
+                    src: None,
+                    dst: None,
+                },
+            }
+        }
+    }
+
+    impl<T: Clone, P: crate::Preferences> FromContext<'_, T> for Prefs<P> {
+        fn from_ctx(_ctx: &Context<T>) -> Self {
+            Self(P::current())
+        }
+    }
+
+    impl<T: Clone> FromContext<'_, T> for Columns {
+        fn from_ctx(ctx: &Context<T>) -> Self {
+            Self(ctx.pinfo)
+        }
+    }
+
     #[doc(hidden)]
     pub trait Handler<'a, T: Clone, Args, Ret> {
         fn call(self, ctx: &Context<'a, T>) -> Ret;
@@ -827,6 +3268,7 @@ pub mod tap {
     impl_handler!(Arg1, Arg2, Arg3);
     impl_handler!(Arg1, Arg2, Arg3, Arg4);
     impl_handler!(Arg1, Arg2, Arg3, Arg4, Arg5);
+    impl_handler!(Arg1, Arg2, Arg3, Arg4, Arg5, Arg6);
 
     #[doc(hidden)]
     pub fn handle_tap<'a, T, Args, H>(ctx: &Context<'a, T>, handler: H)
@@ -847,6 +3289,30 @@ pub mod tap {
         handler.call(ctx)
     }
 
+    #[doc(hidden)]
+    pub fn handle_present_if<'a, Args, H>(ctx: &Context<'a, ()>, handler: H) -> bool
+    where
+        H: Handler<'a, (), Args, bool>,
+    {
+        handler.call(ctx)
+    }
+
+    #[doc(hidden)]
+    pub fn handle_heuristic<'a, Args, H>(ctx: &Context<'a, ()>, handler: H) -> bool
+    where
+        H: Handler<'a, (), Args, bool>,
+    {
+        handler.call(ctx)
+    }
+
+    #[doc(hidden)]
+    pub fn handle_checksum_fn<'a, Args, H>(ctx: &Context<'a, &'a [u8]>, handler: H) -> Option<u32>
+    where
+        H: Handler<'a, &'a [u8], Args, Option<u32>>,
+    {
+        handler.call(ctx)
+    }
+
     #[doc(hidden)]
     pub fn handle_consume_with<'a, Args, Ret, H>(ctx: &Context<'a, ()>, handler: H) -> (usize, Ret)
     where
@@ -870,7 +3336,20 @@ pub struct FieldsStore<'a> {
     i16s: FieldsMap<i16>,
     i32s: FieldsMap<i32>,
     i64s: FieldsMap<i64>,
+    u128s: FieldsMap<u128>,
+    i128s: FieldsMap<i128>,
+    f32s: FieldsMap<f32>,
+    f64s: FieldsMap<f64>,
+    bools: FieldsMap<bool>,
     bytes: FieldsMap<&'a [u8]>,
+    strs: FieldsMap<&'a str>,
+    ipv4s: FieldsMap<types::Ipv4>,
+    ipv6s: FieldsMap<types::Ipv6>,
+    ethers: FieldsMap<types::Ether>,
+    eui64s: FieldsMap<types::Eui64>,
+    guids: FieldsMap<types::Guid>,
+    times: FieldsMap<std::time::SystemTime>,
+    durations: FieldsMap<std::time::Duration>,
 }
 
 impl FieldsStore<'_> {
@@ -928,12 +3407,90 @@ impl FieldsStore<'_> {
     pub fn get_i64_multi(&self, filter: &str) -> Option<&[i64]> {
         Self::get_multi(&self.i64s, filter)
     }
+    pub fn get_u128(&self, filter: &str) -> Option<&u128> {
+        Self::get_first(&self.u128s, filter)
+    }
+    pub fn get_u128_multi(&self, filter: &str) -> Option<&[u128]> {
+        Self::get_multi(&self.u128s, filter)
+    }
+    pub fn get_i128(&self, filter: &str) -> Option<&i128> {
+        Self::get_first(&self.i128s, filter)
+    }
+    pub fn get_i128_multi(&self, filter: &str) -> Option<&[i128]> {
+        Self::get_multi(&self.i128s, filter)
+    }
+    pub fn get_f32(&self, filter: &str) -> Option<&f32> {
+        Self::get_first(&self.f32s, filter)
+    }
+    pub fn get_f32_multi(&self, filter: &str) -> Option<&[f32]> {
+        Self::get_multi(&self.f32s, filter)
+    }
+    pub fn get_f64(&self, filter: &str) -> Option<&f64> {
+        Self::get_first(&self.f64s, filter)
+    }
+    pub fn get_f64_multi(&self, filter: &str) -> Option<&[f64]> {
+        Self::get_multi(&self.f64s, filter)
+    }
+    pub fn get_bool(&self, filter: &str) -> Option<&bool> {
+        Self::get_first(&self.bools, filter)
+    }
+    pub fn get_bool_multi(&self, filter: &str) -> Option<&[bool]> {
+        Self::get_multi(&self.bools, filter)
+    }
     pub fn get_bytes(&self, filter: &str) -> Option<&[u8]> {
         Self::get_first(&self.bytes, filter).copied()
     }
     pub fn get_bytes_multi(&self, filter: &str) -> Option<&[&[u8]]> {
         Self::get_multi(&self.bytes, filter)
     }
+    pub fn get_str(&self, filter: &str) -> Option<&str> {
+        Self::get_first(&self.strs, filter).copied()
+    }
+    pub fn get_str_multi(&self, filter: &str) -> Option<&[&str]> {
+        Self::get_multi(&self.strs, filter)
+    }
+    pub fn get_ipv4(&self, filter: &str) -> Option<&types::Ipv4> {
+        Self::get_first(&self.ipv4s, filter)
+    }
+    pub fn get_ipv4_multi(&self, filter: &str) -> Option<&[types::Ipv4]> {
+        Self::get_multi(&self.ipv4s, filter)
+    }
+    pub fn get_ipv6(&self, filter: &str) -> Option<&types::Ipv6> {
+        Self::get_first(&self.ipv6s, filter)
+    }
+    pub fn get_ipv6_multi(&self, filter: &str) -> Option<&[types::Ipv6]> {
+        Self::get_multi(&self.ipv6s, filter)
+    }
+    pub fn get_ether(&self, filter: &str) -> Option<&types::Ether> {
+        Self::get_first(&self.ethers, filter)
+    }
+    pub fn get_ether_multi(&self, filter: &str) -> Option<&[types::Ether]> {
+        Self::get_multi(&self.ethers, filter)
+    }
+    pub fn get_eui64(&self, filter: &str) -> Option<&types::Eui64> {
+        Self::get_first(&self.eui64s, filter)
+    }
+    pub fn get_eui64_multi(&self, filter: &str) -> Option<&[types::Eui64]> {
+        Self::get_multi(&self.eui64s, filter)
+    }
+    pub fn get_guid(&self, filter: &str) -> Option<&types::Guid> {
+        Self::get_first(&self.guids, filter)
+    }
+    pub fn get_guid_multi(&self, filter: &str) -> Option<&[types::Guid]> {
+        Self::get_multi(&self.guids, filter)
+    }
+    pub fn get_time(&self, filter: &str) -> Option<&std::time::SystemTime> {
+        Self::get_first(&self.times, filter)
+    }
+    pub fn get_time_multi(&self, filter: &str) -> Option<&[std::time::SystemTime]> {
+        Self::get_multi(&self.times, filter)
+    }
+    pub fn get_duration(&self, filter: &str) -> Option<&std::time::Duration> {
+        Self::get_first(&self.durations, filter)
+    }
+    pub fn get_duration_multi(&self, filter: &str) -> Option<&[std::time::Duration]> {
+        Self::get_multi(&self.durations, filter)
+    }
     pub fn insert_u8(&mut self, filter: &str, value: u8) {
         self.u8s.entry(filter.to_string()).or_default().push(value);
     }
@@ -958,6 +3515,72 @@ impl FieldsStore<'_> {
     pub fn insert_i64(&mut self, filter: &str, value: i64) {
         self.i64s.entry(filter.to_string()).or_default().push(value);
     }
+    pub fn insert_u128(&mut self, filter: &str, value: u128) {
+        self.u128s
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_i128(&mut self, filter: &str, value: i128) {
+        self.i128s
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_f32(&mut self, filter: &str, value: f32) {
+        self.f32s.entry(filter.to_string()).or_default().push(value);
+    }
+    pub fn insert_f64(&mut self, filter: &str, value: f64) {
+        self.f64s.entry(filter.to_string()).or_default().push(value);
+    }
+    pub fn insert_bool(&mut self, filter: &str, value: bool) {
+        self.bools
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_ipv4(&mut self, filter: &str, value: types::Ipv4) {
+        self.ipv4s
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_ipv6(&mut self, filter: &str, value: types::Ipv6) {
+        self.ipv6s
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_ether(&mut self, filter: &str, value: types::Ether) {
+        self.ethers
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_eui64(&mut self, filter: &str, value: types::Eui64) {
+        self.eui64s
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_guid(&mut self, filter: &str, value: types::Guid) {
+        self.guids
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_time(&mut self, filter: &str, value: std::time::SystemTime) {
+        self.times
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
+    pub fn insert_duration(&mut self, filter: &str, value: std::time::Duration) {
+        self.durations
+            .entry(filter.to_string())
+            .or_default()
+            .push(value);
+    }
 }
 
 impl<'a> FieldsStore<'a> {
@@ -967,6 +3590,9 @@ impl<'a> FieldsStore<'a> {
             .or_default()
             .push(value);
     }
+    pub fn insert_str(&mut self, filter: &str, value: &'a str) {
+        self.strs.entry(filter.to_string()).or_default().push(value);
+    }
 }
 
 #[cfg(test)]
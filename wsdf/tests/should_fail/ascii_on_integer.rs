@@ -0,0 +1,11 @@
+// Tests that the ascii shorthand is only accepted on byte arrays
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct MyField {
+    #[wsdf(ascii)]
+    magic: u32,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    #[wsdf(bits = 3)]
+    version: u8,
+    #[wsdf(bits = 5)]
+    flags: u8,
+}
+
+fn main() {}
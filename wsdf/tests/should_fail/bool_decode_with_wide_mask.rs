@@ -0,0 +1,18 @@
+use wsdf::tap::Field;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Options {
+    #[wsdf(width = u64, mask = 0x1_0000_0000, decode_with = "show_flag")]
+    extended: bool,
+}
+
+fn show_flag(Field(flag): Field<bool>) -> &'static str {
+    if flag {
+        "Extended"
+    } else {
+        "Basic"
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Tests that the le and be shorthands cannot be combined with an explicit encoding
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct MyField {
+    #[wsdf(le, enc = "ENC_BIG_ENDIAN")]
+    x: u32,
+}
+
+fn main() {}
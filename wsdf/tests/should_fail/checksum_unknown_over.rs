@@ -0,0 +1,12 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    header: u32,
+    payload: [u8; 8],
+    #[wsdf(checksum = "crc32", over = "header..body")]
+    crc: u32,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+// Tests that enc_field must come with an enc_fn
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct MyField {
+    bom: u16,
+    #[wsdf(enc_field = "bom")]
+    x: u32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Tests that every flag must have the same integer type
+
+use wsdf::*;
+
+#[derive(Flags)]
+struct MyFlags {
+    #[wsdf(mask = 0x80)]
+    a: u8,
+    #[wsdf(mask = 0x0100)]
+    b: u16,
+}
+
+fn main() {}
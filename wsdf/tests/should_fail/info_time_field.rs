@@ -0,0 +1,9 @@
+use wsdf::*;
+
+#[derive(ProtocolField)]
+#[wsdf(info = "Sent at {sent}")]
+struct Order {
+    sent: wsdf::types::EpochNanos,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Tests that the info format string may only reference fields of the type
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+#[wsdf(info = "Seq={sequence}")]
+struct Header {
+    seq: u64,
+}
+
+fn main() {}
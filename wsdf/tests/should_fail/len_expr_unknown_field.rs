@@ -0,0 +1,11 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Header {
+    #[wsdf(len = "hdr_len * 4")]
+    options: Vec<u8>,
+    hdr_len: u8,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Message {
+    flags: u8,
+    #[wsdf(if = "flags & 0x80 != 0")]
+    count: Option<u8>,
+    #[wsdf(len_field = "count")]
+    values: Vec<u16>,
+}
+
+fn main() {}
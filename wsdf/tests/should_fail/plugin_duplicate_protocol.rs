@@ -0,0 +1,13 @@
+// Tests that a protocol cannot be registered twice in the same plugin
+
+use wsdf::*;
+
+plugin!(MyProtocol, MyProtocol);
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.payload")]
+struct MyProtocol {
+    foo: u32,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use wsdf::*;
+
+#[derive(Preferences)]
+struct MyPrefs {
+    rounding: Rounding,
+}
+
+#[derive(ValueStrings)]
+enum Rounding {
+    Down,
+    Up,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(prefs = "MyPrefs")]
+struct MyProto {
+    price: u64,
+}
+
+#[derive(Preferences)]
+struct MyPrefs {
+    decimal_prices: bool,
+    #[wsdf(default = "9000-9010")]
+    ports: PrefRange,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    len: u8,
+    #[wsdf(skip = 2, skip_expr = "len")]
+    value: u32,
+}
+
+fn main() {}
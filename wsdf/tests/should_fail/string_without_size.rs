@@ -0,0 +1,8 @@
+use wsdf::ProtocolField;
+
+#[derive(ProtocolField)]
+struct Login {
+    user: String,
+}
+
+fn main() {}
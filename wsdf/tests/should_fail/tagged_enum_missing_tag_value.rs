@@ -0,0 +1,13 @@
+// Tests that every variant of a tagged enum needs a tag value, unless it is the fallback
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+#[wsdf(tag = u8)]
+enum Side {
+    #[wsdf(tag_value = 0x42)]
+    Bid,
+    Ask,
+}
+
+fn main() {}
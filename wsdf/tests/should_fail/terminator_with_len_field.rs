@@ -0,0 +1,11 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Message {
+    count: u8,
+    #[wsdf(len_field = "count", terminator = 0)]
+    values: Vec<u16>,
+}
+
+fn main() {}
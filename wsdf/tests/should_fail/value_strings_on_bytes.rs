@@ -0,0 +1,16 @@
+// Tests that value strings are only accepted on integer fields
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct MyField {
+    #[wsdf(value_strings = "Kind")]
+    bytes: [u8; 4],
+}
+
+#[derive(ValueStrings)]
+enum Kind {
+    A = 1,
+}
+
+fn main() {}
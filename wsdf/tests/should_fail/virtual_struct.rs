@@ -0,0 +1,15 @@
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    #[wsdf(virtual)]
+    header: Header,
+}
+
+#[derive(ProtocolField)]
+struct Header {
+    version: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use wsdf::types::Ether;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Station {
+    mac: Ether,
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+use std::net::Ipv6Addr;
+
+use wsdf::tap::*;
+use wsdf::types::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Neighbour {
+    #[wsdf(save)]
+    ip: wsdf::types::Ipv4,
+    #[wsdf(tap = "check_ip6")]
+    ip6: Ipv6Addr,
+    #[wsdf(decode_with = "show_mac")]
+    mac: wsdf::types::Ether,
+    #[wsdf(save)]
+    eui: wsdf::types::Eui64,
+    #[wsdf(enc = "ENC_LITTLE_ENDIAN", save)]
+    guid: wsdf::types::Guid,
+    #[wsdf(enc = "ENC_LITTLE_ENDIAN")]
+    ip_le: std::net::Ipv4Addr,
+    gateways: [wsdf::types::Ipv4; 2],
+}
+
+fn check_ip6(Field(ip6): Field<Ipv6>, Fields(fields): Fields) {
+    let _ip: Option<&Ipv4> = fields.get_ipv4("ip");
+    let _eui: Option<&[Eui64]> = fields.get_eui64_multi("eui");
+    let _is_loopback = ip6.is_loopback();
+}
+
+fn show_mac(Field(mac): Field<Ether>) -> String {
+    format!("MAC {mac}")
+}
+
+fn main() {}
@@ -0,0 +1,43 @@
+#![allow(dead_code)]
+
+use wsdf::tap::Field;
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    header: Header,
+    count: u8,
+    #[wsdf(len_field = "count")]
+    quotes: Vec<Quote>,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(bits)]
+struct Header {
+    #[wsdf(bits = 3)]
+    version: u8,
+    #[wsdf(bits = 1)]
+    urgent: bool,
+    #[wsdf(bits = 12)]
+    sequence: u16,
+    #[wsdf(bits = 4, if = "urgent")]
+    priority: Option<u8>,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(bits, le)]
+struct Quote {
+    #[wsdf(bits = 5, display = "BASE_HEX")]
+    side: u8,
+    #[wsdf(bits = 20, hide)]
+    reserved: u32,
+    #[wsdf(bits = 34, tap = "check_price")]
+    price: u64,
+}
+
+fn check_price(Field(price): Field<u64>) {
+    assert!(price < 1 << 34);
+}
+
+fn main() {}
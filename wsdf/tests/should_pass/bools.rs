@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Options {
+    #[wsdf(save)]
+    enabled: bool,
+    #[wsdf(width = u16, mask = 0x0100, tfs = ("Compressed", "Not compressed"))]
+    compressed: bool,
+    #[wsdf(width = "u32", enc = "ENC_LITTLE_ENDIAN", tap = "check_enabled")]
+    wide: bool,
+    #[wsdf(decode_with = "yes_no")]
+    custom: bool,
+    #[wsdf(width = u64, mask = 0x8000_0000, decode_with = "yes_no")]
+    custom_wide: bool,
+    #[wsdf(width = u64, mask = 0x1_0000_0000)]
+    high: bool,
+    count: u8,
+    #[wsdf(len_field = "count")]
+    many: Vec<bool>,
+    flags: OptionFlags,
+    mixed: MixedFlags,
+}
+
+#[derive(Flags)]
+#[wsdf(width = u8)]
+struct OptionFlags {
+    #[wsdf(mask = 0x80, tfs = ("Set", "Not set"), save)]
+    urgent: bool,
+    #[wsdf(mask = 0x40)]
+    ack: bool,
+}
+
+#[derive(Flags)]
+struct MixedFlags {
+    #[wsdf(mask = 0x8000)]
+    response: bool,
+    #[wsdf(mask = 0x00ff)]
+    code: u16,
+}
+
+fn check_enabled(Field(_wide): Field<bool>, Fields(fields): Fields) {
+    let _enabled: Option<&bool> = fields.get_bool("enabled");
+}
+
+fn yes_no(Field(custom): Field<bool>) -> &'static str {
+    if custom {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn main() {}
@@ -0,0 +1,68 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port", le)]
+struct LittleProto {
+    len: u16,
+    #[wsdf(be)]
+    port: u16,
+    #[wsdf(len_prefix = u16)]
+    name: String,
+    #[wsdf(ascii)]
+    magic: [u8; 4],
+    #[wsdf(ascii, len_field = "len")]
+    label: Vec<u8>,
+    sent: wsdf::types::EpochMillis,
+    header: Header,
+    #[wsdf(be)]
+    trailer: Header,
+    #[wsdf(be)]
+    trailers: [Header; 2],
+    flags: Flags16,
+    msg: Message,
+}
+
+#[derive(ProtocolField)]
+struct Header {
+    id: u32,
+    serial: wsdf::types::Guid,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(be)]
+struct BigHeader {
+    id: u32,
+    #[wsdf(le)]
+    checksum: u32,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(tag = u16)]
+enum Message {
+    #[wsdf(tag_value = 1)]
+    Ping,
+    #[wsdf(tag_value = 2)]
+    Data(u64),
+    #[wsdf(tag_value = 3)]
+    Big(BigHeader),
+}
+
+#[derive(Flags)]
+#[wsdf(width = u16)]
+struct Flags16 {
+    #[wsdf(mask = 0x8000)]
+    top: bool,
+    #[wsdf(mask = 0x0001)]
+    bottom: bool,
+}
+
+#[derive(Flags)]
+#[wsdf(le)]
+struct LittleFlags {
+    #[wsdf(mask = 0x8000)]
+    top: u16,
+}
+
+fn main() {}
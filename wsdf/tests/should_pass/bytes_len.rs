@@ -0,0 +1,27 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Message {
+    records_len: u16,
+    #[wsdf(bytes_len_field = "records_len")]
+    records: Vec<Record>,
+    values_len: u8,
+    #[wsdf(bytes_len_field = "values_len")]
+    values: Vec<u32>,
+    payload_len: u16,
+    #[wsdf(bytes_len_field = "payload_len")]
+    payload: Vec<u8>,
+}
+
+#[derive(ProtocolField)]
+struct Record {
+    typ: u8,
+    len: u8,
+    #[wsdf(len_field = "len")]
+    value: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+use wsdf::tap::{Field, Packet};
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Frame {
+    #[wsdf(checksum = "internet", over = "checksum..payload")]
+    checksum: u16,
+    len: u8,
+    #[wsdf(len_field = "len")]
+    payload: Vec<u8>,
+    #[wsdf(checksum = "crc32c", over = "len..payload")]
+    crc: u32,
+    trailer: Trailer,
+}
+
+#[derive(ProtocolField)]
+struct Trailer {
+    kind: u8,
+    #[wsdf(if = "kind == 1")]
+    extra: Option<u16>,
+    flags: u8,
+    #[wsdf(checksum_fn = "sum_bytes", le)]
+    sum: u8,
+    #[wsdf(checksum = "fletcher16", over = "kind")]
+    fletcher: u16,
+}
+
+fn sum_bytes(Field(bytes): Field<&[u8]>, Packet(packet): Packet) -> Option<u32> {
+    if packet.is_empty() {
+        return None;
+    }
+    Some(bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) as u32)
+}
+
+fn main() {}
@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct SectionHeader {
+    bom: [u8; 4],
+    #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+    major: u16,
+    #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+    minors: [u16; 2],
+    #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+    created: wsdf::types::EpochSecs,
+    #[wsdf(enc_field = "bom", enc_fn = "pick_enc")]
+    body: Body,
+    #[wsdf(enc_field = "major", enc_fn = "pick_enc_by_version")]
+    trailer: Trailer,
+}
+
+#[derive(ProtocolField)]
+struct Body {
+    len: u32,
+    #[wsdf(len_prefix = u16)]
+    name: String,
+    flags: BodyFlags,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(be)]
+struct Trailer {
+    checksum: u32,
+}
+
+#[derive(Flags)]
+#[wsdf(width = u16)]
+struct BodyFlags {
+    #[wsdf(mask = 0x1)]
+    last: bool,
+}
+
+fn pick_enc(bom: &[u8]) -> u32 {
+    match bom {
+        [0x4d, 0x3c, 0x2b, 0x1a] => epan_sys::ENC_LITTLE_ENDIAN,
+        _ => epan_sys::ENC_BIG_ENDIAN,
+    }
+}
+
+fn pick_enc_by_version(major: &u16) -> u32 {
+    match major {
+        1 => epan_sys::ENC_BIG_ENDIAN,
+        _ => epan_sys::ENC_LITTLE_ENDIAN,
+    }
+}
+
+fn main() {}
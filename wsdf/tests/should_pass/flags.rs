@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use wsdf::tap::Fields;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Header {
+    #[wsdf(tap = "check_flags")]
+    flags: HeaderFlags,
+    wide: WideFlags,
+}
+
+#[derive(Flags)]
+#[wsdf(display = "BASE_HEX")]
+struct HeaderFlags {
+    #[wsdf(mask = 0x80, rename = "Response", save)]
+    qr: u8,
+    #[wsdf(mask = 0x70, display = "BASE_HEX")]
+    opcode: u8,
+    #[wsdf(mask = 0x0f)]
+    rcode: u8,
+}
+
+#[derive(Flags)]
+#[wsdf(enc = "ENC_LITTLE_ENDIAN")]
+struct WideFlags {
+    #[wsdf(mask = 0x8000_0000_0000_0000)]
+    top: u64,
+    #[wsdf(mask = 0x1)]
+    bottom: u64,
+}
+
+fn check_flags(_fields: Fields) {}
+
+fn main() {}
@@ -0,0 +1,28 @@
+#![allow(dead_code)]
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Quote {
+    #[wsdf(save)]
+    bid: f64,
+    #[wsdf(enc = "ENC_LITTLE_ENDIAN", tap = "check_ask")]
+    ask: f64,
+    #[wsdf(decode_with = "show_ratio")]
+    ratio: f32,
+    count: u8,
+    #[wsdf(len_field = "count")]
+    history: Vec<f32>,
+    levels: [f64; 2],
+}
+
+fn check_ask(_ask: Field<f64>, Fields(fields): Fields) {
+    let _bid: Option<&f64> = fields.get_f64("bid");
+}
+
+fn show_ratio(Field(ratio): Field<f32>) -> String {
+    format!("{:.2}%", ratio * 100.0)
+}
+
+fn main() {}
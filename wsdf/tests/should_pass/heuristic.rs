@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+
+use wsdf::tap::{Offset, Packet};
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(heuristic = ("udp", "is_feed"))]
+struct Feed {
+    magic: [u8; 4],
+    sequence: u64,
+}
+
+#[derive(Protocol)]
+#[wsdf(
+    decode_from = [("tcp.port", 9000)],
+    heuristic = [("udp", "is_feed"), ("tcp", "checks::is_feed")],
+)]
+struct OtherFeed {
+    magic: [u8; 4],
+    count: u16,
+}
+
+fn is_feed(Packet(packet): Packet, Offset(offset): Offset) -> bool {
+    packet[offset..].starts_with(b"FEED")
+}
+
+mod checks {
+    pub fn is_feed() -> bool {
+        true
+    }
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+use wsdf::tap::{Columns, Field};
+use wsdf::*;
+
+#[derive(ProtocolField)]
+#[wsdf(info = "Seq={seq} Len={len:#06x} {{literal}}")]
+struct Header {
+    seq: u64,
+    #[wsdf(tap = "show_len")]
+    len: u16,
+    #[wsdf(len_field = "len")]
+    data: Vec<u8>,
+    body: Body,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(tag = u8)]
+enum Body {
+    #[wsdf(tag_value = 1, info = "Login as {user_id} ({name}) from {addr}, rate {rate:.1}")]
+    Login {
+        user_id: u32,
+        #[wsdf(nul_terminated)]
+        name: String,
+        addr: wsdf::types::Ipv4,
+        rate: f32,
+    },
+    #[wsdf(tag_value = 2, info = "Logout")]
+    Logout,
+}
+
+fn show_len(Field(len): Field<u16>, cols: Columns) {
+    cols.append_info(format!("{len} bytes"));
+    cols.set_src("a");
+    cols.set_dst("b");
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Header {
+    ihl: u8,
+    total_len: u64,
+    #[wsdf(len = "ihl * 4 - 20")]
+    options: Vec<u8>,
+    name_len: u16,
+    #[wsdf(len = "name_len + 1")]
+    name: String,
+    #[wsdf(len = "(total_len - ihl * 4) / 2")]
+    words: Vec<u16>,
+}
+
+fn main() {}
@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Frame {
+    #[wsdf(value_strings = "Kind")]
+    kind: wsdf::types::U24,
+    length: wsdf::types::U24,
+    #[wsdf(len_field = "length")]
+    payload: Vec<u8>,
+    #[wsdf(save)]
+    sequence: wsdf::types::U48,
+    #[wsdf(decode_with = "show_offset")]
+    offset: wsdf::types::I24,
+    #[wsdf(enc = "ENC_LITTLE_ENDIAN")]
+    bytes_seen: wsdf::types::U40,
+    delta: wsdf::types::I56,
+    #[wsdf(tap = "check_id")]
+    id: u128,
+    #[wsdf(save)]
+    nonce: i128,
+    #[wsdf(dispatch_field = "kind")]
+    body: Body,
+}
+
+#[derive(ValueStrings)]
+#[repr(u32)]
+enum Kind {
+    Data = 1,
+    Ack = 2,
+}
+
+#[derive(ProtocolField)]
+enum Body {
+    Data(wsdf::types::U56),
+    Ack(wsdf::types::I40),
+}
+
+impl Body {
+    fn dispatch_kind(kind: &U24) -> usize {
+        *kind as usize - 1
+    }
+}
+
+fn show_offset(Field(offset): Field<i32>) -> String {
+    format!("{offset:+}")
+}
+
+fn check_id(Field(_id): Field<u128>, Fields(fields): Fields) {
+    let _sequence: Option<&u64> = fields.get_u64("sequence");
+    let _nonces: Option<&[i128]> = fields.get_i128_multi("nonce");
+}
+
+fn main() {}
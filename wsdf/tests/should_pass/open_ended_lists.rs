@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Message {
+    #[wsdf(terminator = 0x00)]
+    options: Vec<Opt>,
+    #[wsdf(until = "at_trailer")]
+    words: Vec<u16>,
+    #[wsdf(terminator = 0xff)]
+    kinds: Vec<Kind>,
+    #[wsdf(terminator = 0x00)]
+    name: Vec<u8>,
+    #[wsdf(until = "at_trailer", save)]
+    body: Vec<u8>,
+    #[wsdf(until_end)]
+    records: Vec<Record>,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(tag = u8)]
+enum Kind {
+    #[wsdf(tag_value = 1)]
+    Short { value: u8 },
+    #[wsdf(tag_value = 2)]
+    Long { value: u32 },
+}
+
+#[derive(ProtocolField)]
+struct Opt {
+    kind: u8,
+    len: u8,
+    #[wsdf(len_field = "len")]
+    value: Vec<u8>,
+}
+
+#[derive(ProtocolField)]
+struct Record {
+    len: u8,
+    #[wsdf(bytes_len_field = "len")]
+    items: Vec<Item>,
+}
+
+#[derive(ProtocolField)]
+struct Item {
+    id: u16,
+    #[wsdf(until_end)]
+    rest: Vec<u8>,
+}
+
+fn at_trailer(rest: &[u8]) -> bool {
+    rest.starts_with(&[0xff, 0xff])
+}
+
+fn main() {}
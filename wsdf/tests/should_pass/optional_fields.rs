@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+use wsdf::tap::{Fields, Packet};
+use wsdf::*;
+
+const HAS_CHECKSUM: u8 = 0x80;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Message {
+    #[wsdf(save)]
+    flags: u8,
+    #[wsdf(if = "flags & HAS_CHECKSUM != 0")]
+    checksum: Option<u16>,
+    #[wsdf(if = "(flags & 0x40) != 0")]
+    ext: Option<Extension>,
+    #[wsdf(present_if = "has_trailer")]
+    trailer: Option<u32>,
+    #[wsdf(present_if = "has_more")]
+    more: Option<Extension>,
+}
+
+#[derive(ProtocolField)]
+struct Extension {
+    len: u8,
+    #[wsdf(len_field = "len")]
+    data: Vec<u8>,
+}
+
+fn has_trailer(Fields(fields): Fields) -> bool {
+    fields.get_u8("message.flags").is_some_and(|flags| flags & 0x01 != 0)
+}
+
+fn has_more(Packet(packet): Packet) -> bool {
+    packet.len() > 16
+}
+
+fn main() {}
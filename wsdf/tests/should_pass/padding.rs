@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    kind: u8,
+    #[wsdf(align = 4)]
+    len: u16,
+    #[wsdf(skip = 2, show_padding)]
+    flags: u8,
+    #[wsdf(len_field = "len")]
+    payload: Vec<u8>,
+    #[wsdf(skip_expr = "len % 4", align = 8)]
+    records: Record,
+    count: u8,
+    #[wsdf(len_field = "count")]
+    blocks: Vec<Block>,
+}
+
+// Each block's fields are aligned from the start of the block, not of the packet.
+#[derive(ProtocolField)]
+struct Block {
+    kind: u8,
+    #[wsdf(align = 4)]
+    value: u32,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(align = 4, show_padding)]
+struct Record {
+    id: u16,
+    value: u8,
+}
+
+fn main() {}
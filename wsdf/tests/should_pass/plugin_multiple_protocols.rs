@@ -0,0 +1,30 @@
+#![allow(dead_code)]
+
+// Tests that a single plugin can register multiple protocols
+
+use wsdf::*;
+
+plugin!(ProtoFoo, nested::ProtoBar);
+
+#[derive(Protocol)]
+#[wsdf(decode_from = [("udp.port", 1234)])]
+struct ProtoFoo {
+    bar: u64,
+    #[wsdf(subdissector = "proto_foo.payload")]
+    payload: Vec<u8>,
+}
+
+mod nested {
+    use wsdf::*;
+
+    #[derive(Protocol)]
+    #[wsdf(decode_from = "proto_foo.payload")]
+    pub struct ProtoBar {
+        baz: [u8; 9],
+    }
+}
+
+fn main() {
+    // There should be exactly one entry point, registering both protocols.
+    let _: extern "C" fn() = plugin_register;
+}
@@ -0,0 +1,43 @@
+#![allow(dead_code)]
+
+use wsdf::tap::{Field, Prefs};
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(prefs = "MyPrefs")]
+struct MyProto {
+    #[wsdf(tap = "show_price")]
+    price: u64,
+}
+
+#[derive(Preferences)]
+struct MyPrefs {
+    /// Show prices as decimals rather than fixed point integers.
+    decimal_prices: bool,
+    #[wsdf(default = 4, rename = "Price scale")]
+    scale: u32,
+    #[wsdf(default = "USD")]
+    currency: String,
+    #[wsdf(default = "9000-9010", decode_from = "udp.port")]
+    ports: PrefRange,
+    #[wsdf(default = "1-100", max = 1000)]
+    venues: PrefRange,
+    #[wsdf(default = "Rounding::Nearest")]
+    rounding: Rounding,
+}
+
+#[derive(ValueStrings)]
+enum Rounding {
+    Down,
+    #[wsdf(rename = "Round to nearest")]
+    Nearest,
+    Up,
+}
+
+fn show_price(Field(price): Field<u64>, Prefs(prefs): Prefs<MyPrefs>) {
+    if prefs.decimal_prices && prefs.ports.contains(9000) {
+        let _ = price as f64 / 10f64.powi(prefs.scale as i32);
+    }
+}
+
+fn main() {}
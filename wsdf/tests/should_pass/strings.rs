@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Login {
+    #[wsdf(size = 8, trim, save)]
+    user: String,
+    #[wsdf(nul_terminated, enc = "ENC_UTF_8", tap = "check_user")]
+    greeting: String,
+    #[wsdf(len_prefix = u16, enc = "ENC_UTF_16 | ENC_LITTLE_ENDIAN")]
+    display_name: String,
+    #[wsdf(len_prefix = "u8", decode_with = "shout")]
+    motto: String,
+    password_len: u8,
+    #[wsdf(len_field = "password_len", enc = "ENC_EBCDIC", hide)]
+    password: String,
+    #[wsdf(nul_terminated, hide)]
+    secret: String,
+    group_count: u8,
+    #[wsdf(len_field = "group_count", nul_terminated)]
+    groups: Vec<String>,
+    #[wsdf(size = 4)]
+    codes: [String; 2],
+    note: Note<'static>,
+}
+
+#[derive(ProtocolField)]
+struct Note<'a> {
+    #[wsdf(nul_terminated, enc = "ENC_UTF_16 | ENC_LITTLE_ENDIAN")]
+    title: &'a str,
+    flags: u16,
+    #[wsdf(len_prefix = u8, tap = "check_body")]
+    body: &'a str,
+}
+
+fn check_user(Field(_greeting): Field<&str>, Fields(fields): Fields) {
+    let _user: Option<&str> = fields.get_str("user");
+}
+
+fn check_body(Field(_body): Field<&str>) {}
+
+fn shout(Field(motto): Field<&str>) -> String {
+    motto.to_uppercase()
+}
+
+fn main() {}
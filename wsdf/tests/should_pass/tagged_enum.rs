@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Order {
+    side: Side,
+    price: i32,
+    quantity: u64,
+    message: Message,
+    count: u8,
+    #[wsdf(len_field = "count")]
+    messages: Vec<Message>,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(tag = u8)]
+enum Side {
+    #[wsdf(tag_value = 0x42)]
+    Bid,
+    #[wsdf(tag_value = 0x41, rename = "Ask (sell)")]
+    Ask,
+    #[wsdf(fallback)]
+    Unknown,
+}
+
+#[derive(ProtocolField)]
+#[wsdf(tag = "u16")]
+enum Message {
+    #[wsdf(tag_value = 1)]
+    Heartbeat,
+    #[wsdf(tag_value = 2)]
+    Login { user: [u8; 8] },
+    #[wsdf(tag_value = 3)]
+    Cancel(u32),
+}
+
+fn main() {}
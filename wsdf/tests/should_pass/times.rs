@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Feed {
+    #[wsdf(save)]
+    sent: wsdf::types::EpochNanos,
+    #[wsdf(enc = "ENC_LITTLE_ENDIAN", tap = "check_received")]
+    received: wsdf::types::EpochMicros,
+    #[wsdf(display = "ABSOLUTE_TIME_UTC")]
+    created: wsdf::types::EpochSecs,
+    expires: wsdf::types::EpochMillis,
+    #[wsdf(tap = "check_reference")]
+    reference: wsdf::types::NtpTime,
+    #[wsdf(save)]
+    ttl: wsdf::types::DurationSecs,
+    #[wsdf(decode_with = "show_latency")]
+    latency: wsdf::types::DurationNanos,
+    timeouts: [wsdf::types::DurationMillis; 2],
+    jitter: wsdf::types::DurationMicros,
+}
+
+fn check_received(Field(received): Field<SystemTime>, Fields(fields): Fields) {
+    let _sent: Option<&SystemTime> = fields.get_time("sent");
+    let _ttl: Option<&[Duration]> = fields.get_duration_multi("ttl");
+    let _elapsed = received.elapsed();
+}
+
+fn show_latency(Field(latency): Field<Duration>) -> String {
+    format!("{latency:?}")
+}
+
+fn check_reference(Field(reference): Field<SystemTime>) {
+    let _ = reference.duration_since(UNIX_EPOCH);
+}
+
+fn main() {}
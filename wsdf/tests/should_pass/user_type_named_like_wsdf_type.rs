@@ -0,0 +1,47 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+// Shadows wsdf::U24, which only applies when it is named with its path.
+#[derive(ProtocolField)]
+struct U24 {
+    high: u8,
+    low: u16,
+}
+
+#[derive(ProtocolField)]
+struct Varint {
+    len: u8,
+    #[wsdf(len_field = "len")]
+    digits: Vec<u8>,
+}
+
+#[derive(ProtocolField)]
+struct Guid {
+    version: u8,
+    #[wsdf(size = 16)]
+    text: String,
+}
+
+#[derive(ProtocolField)]
+struct EpochSecs {
+    days: u16,
+    seconds: u32,
+}
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Frame {
+    own: U24,
+    theirs: wsdf::types::U24,
+    #[wsdf(len_field = "theirs")]
+    payload: Vec<u8>,
+    number: Varint,
+    length: wsdf::Varint<u32>,
+    id: Guid,
+    session: wsdf::types::Guid,
+    uptime: EpochSecs,
+    boot: wsdf::types::EpochSecs,
+}
+
+fn main() {}
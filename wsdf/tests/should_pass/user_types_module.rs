@@ -0,0 +1,32 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+// A module of the user's own which is also called types. Its types are not wsdf's.
+mod types {
+    use wsdf::ProtocolField;
+
+    #[derive(ProtocolField)]
+    pub struct Guid {
+        pub version: u8,
+        #[wsdf(size = 16)]
+        pub text: String,
+    }
+
+    #[derive(ProtocolField)]
+    pub struct EpochSecs {
+        pub days: u16,
+        pub seconds: u32,
+    }
+}
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Frame {
+    id: types::Guid,
+    session: wsdf::types::Guid,
+    uptime: crate::types::EpochSecs,
+    boot: wsdf::types::EpochSecs,
+}
+
+fn main() {}
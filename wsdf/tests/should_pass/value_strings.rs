@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Header {
+    #[wsdf(value_strings = "Kind")]
+    kind: u8,
+    #[wsdf(value_strings = "Kind", display = "BASE_HEX")]
+    signed: i32,
+    #[wsdf(value_strings = "Kind")]
+    wide: u64,
+    #[wsdf(value_strings = "Kind", len_field = "kind")]
+    kinds: Vec<u16>,
+    flags: HeaderFlags,
+}
+
+#[derive(ValueStrings)]
+#[repr(i8)]
+enum Kind {
+    Request = 1,
+    #[wsdf(rename = "Reply")]
+    Response = 2,
+    Error = -1,
+    #[wsdf(range = (10, 20))]
+    Reserved,
+}
+
+#[derive(Flags)]
+struct HeaderFlags {
+    #[wsdf(mask = 0xf0, value_strings = "Kind")]
+    kind: u8,
+}
+
+fn main() {}
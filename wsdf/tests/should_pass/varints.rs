@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use wsdf::tap::*;
+use wsdf::*;
+
+#[derive(ProtocolField)]
+struct Message {
+    #[wsdf(save)]
+    tag: wsdf::types::Varint<u32>,
+    length: wsdf::types::Varint<u64>,
+    #[wsdf(len_field = "length")]
+    payload: Vec<u8>,
+    #[wsdf(enc = "ENC_VARINT_QUIC", tap = "check_stream")]
+    stream_id: wsdf::types::Varint<u64>,
+    #[wsdf(enc = "ENC_VARINT_ZIGZAG", decode_with = "show_delta")]
+    delta: wsdf::types::Varint<i64>,
+    #[wsdf(enc = "ENC_VARINT_ZIGZAG", save)]
+    offset: wsdf::types::Varint<i8>,
+    #[wsdf(save)]
+    window: wsdf::types::Varint<wsdf::types::U24>,
+    #[wsdf(enc = "ENC_VARINT_ZIGZAG", save)]
+    skew: wsdf::types::Varint<wsdf::types::I40>,
+    count: wsdf::types::Sdnv<u16>,
+    #[wsdf(len_field = "count")]
+    values: Vec<wsdf::types::Varint<u32>>,
+    #[wsdf(dispatch_field = "tag")]
+    body: Body,
+}
+
+#[derive(ProtocolField)]
+enum Body {
+    Empty,
+    Block(wsdf::types::Sdnv<u64>),
+}
+
+impl Body {
+    fn dispatch_tag(tag: &u32) -> usize {
+        *tag as usize
+    }
+}
+
+fn check_stream(Field(_stream_id): Field<u64>, Fields(fields): Fields) {
+    let _tag: Option<&u32> = fields.get_u32("tag");
+}
+
+fn show_delta(Field(delta): Field<i64>) -> String {
+    format!("{delta:+}")
+}
+
+fn main() {}
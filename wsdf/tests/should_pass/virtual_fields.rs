@@ -0,0 +1,34 @@
+#![allow(dead_code)]
+
+use wsdf::*;
+
+#[derive(Protocol)]
+#[wsdf(decode_from = "udp.port")]
+struct Packet {
+    #[wsdf(virtual, generated)]
+    version: u8,
+    #[wsdf(dispatch_field = "version")]
+    header: Header,
+    #[wsdf(virtual = true)]
+    len: u16,
+    #[wsdf(len_field = "len")]
+    payload: Vec<u8>,
+    #[wsdf(virtual, hide)]
+    peek: u32,
+    #[wsdf(virtual)]
+    tag: [u8; 4],
+}
+
+#[derive(ProtocolField)]
+enum Header {
+    V1(u32),
+    V2(u64),
+}
+
+impl Header {
+    fn dispatch_version(version: &u8) -> usize {
+        (*version >> 4) as usize
+    }
+}
+
+fn main() {}